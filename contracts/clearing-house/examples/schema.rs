@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 // use clearing_house::states::{market, state, user};
-use clearing_house::package::execute::{InstantiateMsg, ExecuteMsg};
+use clearing_house::package::execute::{InstantiateMsg, ExecuteMsg, MigrateMsg};
 use clearing_house::package::queries::QueryMsg;
 // use clearing_house::states::history::{CurveRecord, DepositRecord, FundingPaymentRecord, FundingRateRecord, LiquidationRecord, TradeRecord};
 // use clearing_house::package::response::{UserResponse, UserPositionResponse, AdminResponse, IsExchangePausedResponse, IsFundingPausedResponse, AdminControlsPricesResponse, VaultsResponse, MarginRatioResponse, PartialLiquidationClosePercentageResponse, PartialLiquidationPenaltyPercentageResponse, FullLiquidationPenaltyPercentageResponse, PartialLiquidatorSharePercentageResponse, FullLiquidatorSharePercentageResponse, MaxDepositLimitResponse, FeeStructureResponse, CurveHistoryResponse, DepositHistoryResponse, FundingPaymentHistoryResponse, FundingRateHistoryResponse, LiquidationHistoryResponse, TradeHistoryResponse, MarketInfoResponse, LengthResponse};
@@ -17,6 +17,7 @@ fn main() {
     // messages schema export
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
 
     // state schema export
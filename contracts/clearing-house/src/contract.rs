@@ -1,15 +1,21 @@
 use crate::package::number::Number128;
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, Uint128,
+    entry_point, to_binary, Addr, Binary, ContractResult, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, Uint128,
 };
 
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 
 use crate::states::constants::*;
 use crate::states::state::{State, OrderState, FEESTRUCTURE, ORACLEGUARDRAILS, ORDERSTATE, STATE};
+use crate::states::market::{MARKETS, MARKETS_V020};
+use crate::states::user::{POSITIONS, POSITIONS_MIGRATION, POSITIONS_V020, USERS};
+use crate::states::withdrawal::{WithdrawalQueue, WITHDRAWAL_QUEUE};
+use cw_storage_plus::{Bound, U64Key};
 
-use crate::package::execute::{ExecuteMsg, InstantiateMsg};
+use crate::package::execute::{ExecuteMsg, InstantiateMsg, MigrateMsg};
 use crate::package::helper::addr_validate_to_lower;
+use crate::package::hooks::HOOK_REPLY_ID;
 use crate::package::queries::QueryMsg;
 use crate::package::types::{FeeStructure, OracleGuardRails};
 
@@ -114,6 +120,9 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    if POSITIONS_MIGRATION.may_load(deps.storage)?.is_some() && touches_positions(&msg) {
+        return Err(ContractError::MigrationPending);
+    }
     match msg {
         ExecuteMsg::InitializeMarket {
             market_index,
@@ -141,17 +150,58 @@ pub fn execute(
             margin_ratio_partial,
             margin_ratio_maintenance,
         ),
-        ExecuteMsg::DepositCollateral { amount, referrer } => {
-            try_deposit_collateral(deps, _env, info, amount, referrer)
-        }
-        ExecuteMsg::WithdrawCollateral { amount } => {
-            try_withdraw_collateral(deps, _env, info, amount)
+        ExecuteMsg::DepositCollateral {
+            amount,
+            referrer,
+            subaccount,
+        } => try_deposit_collateral(
+            deps,
+            _env,
+            info,
+            amount,
+            referrer,
+            subaccount.unwrap_or_default(),
+        ),
+        ExecuteMsg::WithdrawCollateral { amount, subaccount } => {
+            try_withdraw_collateral(deps, _env, info, amount, subaccount.unwrap_or_default())
         }
+        ExecuteMsg::TransferCollateral {
+            amount,
+            from_subaccount,
+            to_subaccount,
+        } => try_transfer_collateral(deps, _env, info, amount, from_subaccount, to_subaccount),
+        ExecuteMsg::AddIsolatedMargin {
+            market_index,
+            amount,
+            subaccount,
+        } => try_add_isolated_margin(
+            deps,
+            _env,
+            info,
+            market_index,
+            amount,
+            subaccount.unwrap_or_default(),
+        ),
+        ExecuteMsg::RemoveIsolatedMargin {
+            market_index,
+            amount,
+            subaccount,
+        } => try_remove_isolated_margin(
+            deps,
+            _env,
+            info,
+            market_index,
+            amount,
+            subaccount.unwrap_or_default(),
+        ),
+        ExecuteMsg::ProcessWithdrawalQueue {} => try_process_withdrawal_queue(deps, _env),
+        ExecuteMsg::MigratePositions { limit } => try_migrate_positions(deps, limit),
         ExecuteMsg::OpenPosition {
             is_direction_long,
             quote_asset_amount,
             market_index,
             limit_price,
+            subaccount,
         } => try_open_position(
             deps,
             _env,
@@ -160,6 +210,7 @@ pub fn execute(
             quote_asset_amount,
             market_index,
             limit_price,
+            subaccount.unwrap_or_default(),
         ),
         // ExecuteMsg::PlaceOrder { order } => try_place_order(deps, _env, info, order),
         // ExecuteMsg::CancelOrder {
@@ -174,12 +225,24 @@ pub fn execute(
         //     user_address,
         //     market_index,
         // } => try_fill_order(deps, _env, info, order_id, user_address, market_index),
-        ExecuteMsg::ClosePosition { market_index } => {
-            try_close_position(deps, _env, info, market_index)
-        }
-        ExecuteMsg::Liquidate { user, market_index } => {
-            try_liquidate(deps, _env, info, user, market_index)
-        }
+        ExecuteMsg::ClosePosition {
+            market_index,
+            subaccount,
+        } => try_close_position(deps, _env, info, market_index, subaccount.unwrap_or_default()),
+        ExecuteMsg::Liquidate {
+            user,
+            market_index,
+            subaccount,
+            liquidator_subaccount,
+        } => try_liquidate(
+            deps,
+            _env,
+            info,
+            user,
+            market_index,
+            subaccount.unwrap_or_default(),
+            liquidator_subaccount.unwrap_or_default(),
+        ),
         ExecuteMsg::MoveAMMPrice {
             base_asset_reserve,
             quote_asset_reserve,
@@ -192,7 +255,7 @@ pub fn execute(
         ExecuteMsg::WithdrawFromInsuranceVaultToMarket {
             market_index,
             amount,
-        } => try_withdraw_from_insurance_vault_to_market(deps, info, market_index, amount),
+        } => try_withdraw_from_insurance_vault_to_market(deps, _env, info, market_index, amount),
         ExecuteMsg::RepegAMMCurve {
             new_peg_candidate,
             market_index,
@@ -203,7 +266,9 @@ pub fn execute(
         ExecuteMsg::ResetAMMOracleTwap { market_index } => {
             try_reset_amm_oracle_twap(deps, _env, market_index)
         }
-        ExecuteMsg::SettleFundingPayment {} => try_settle_funding_payment(deps, _env, info),
+        ExecuteMsg::SettleFundingPayment { subaccount } => {
+            try_settle_funding_payment(deps, _env, info, subaccount.unwrap_or_default())
+        }
         ExecuteMsg::UpdateFundingRate { market_index } => {
             try_update_funding_rate(deps, _env, market_index)
         }
@@ -283,9 +348,22 @@ pub fn execute(
         ),
         ExecuteMsg::UpdateAdmin { admin } => try_update_admin(deps, info, admin),
         ExecuteMsg::UpdateHistoryStore { history_contract } => try_update_history_contract(deps, info, history_contract),
+        ExecuteMsg::AcceptVaultsClearingHouse {} => try_accept_vaults_clearing_house(deps, info),
         ExecuteMsg::UpdateMaxDeposit { max_deposit } => {
             try_update_max_deposit(deps, info, max_deposit)
         }
+        ExecuteMsg::UpdateAmmSnapshotInterval { interval } => {
+            try_update_amm_snapshot_interval(deps, info, interval)
+        }
+        ExecuteMsg::AddHook {
+            contract,
+            events,
+            revert_on_error,
+        } => try_add_hook(deps, info, contract, events, revert_on_error),
+        ExecuteMsg::RemoveHook { contract } => try_remove_hook(deps, info, contract),
+        ExecuteMsg::UpdateHookGasLimit { gas_limit } => {
+            try_update_hook_gas_limit(deps, info, gas_limit)
+        }
         ExecuteMsg::UpdateExchangePaused { exchange_paused } => {
             try_update_exchange_paused(deps, info, exchange_paused)
         }
@@ -335,24 +413,211 @@ pub fn execute(
     }
 }
 
+// only hooks registered with `revert_on_error: false` ask for a reply, and only when they fail
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        HOOK_REPLY_ID => {
+            let error = match msg.result {
+                ContractResult::Err(error) => error,
+                ContractResult::Ok(_) => String::new(),
+            };
+            Ok(Response::new()
+                .add_attribute("method", "hook_skipped")
+                .add_attribute("error", error))
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    // every step moves the stored structs one version forward
+    let mut version = stored.version.clone();
+    if version == "0.1.0" {
+        migrate_from_0_1_0(deps.storage)?;
+        version = "0.2.0".to_string();
+    }
+    if version == "0.2.0" {
+        migrate_from_0_2_0(deps.storage)?;
+        version = "0.3.0".to_string();
+    }
+    // 0.4.0 adds subaccounts, existing accounts already sit under subaccount 0's key and are
+    // listed as such until they are touched again
+    if version == "0.3.0" {
+        version = "0.4.0".to_string();
+    }
+    if version != CONTRACT_VERSION {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+// 0.2.0 adds the withdrawal queue
+fn migrate_from_0_1_0(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    if WITHDRAWAL_QUEUE.may_load(storage)?.is_none() {
+        WITHDRAWAL_QUEUE.save(storage, &WithdrawalQueue::default())?;
+    }
+    Ok(())
+}
+
+// user actions that read or write positions, they wait for the 0.2.0 positions to be re-keyed
+fn touches_positions(msg: &ExecuteMsg) -> bool {
+    matches!(
+        msg,
+        ExecuteMsg::DepositCollateral { .. }
+            | ExecuteMsg::WithdrawCollateral { .. }
+            | ExecuteMsg::TransferCollateral { .. }
+            | ExecuteMsg::AddIsolatedMargin { .. }
+            | ExecuteMsg::RemoveIsolatedMargin { .. }
+            | ExecuteMsg::OpenPosition { .. }
+            | ExecuteMsg::ClosePosition { .. }
+            | ExecuteMsg::Liquidate { .. }
+            | ExecuteMsg::SettleFundingPayment { .. }
+    )
+}
+
+// 0.3.0 keys markets and positions on the big-endian market index. markets are few and move
+// here, positions move a batch of users at a time and the rest through MigratePositions
+fn migrate_from_0_2_0(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let markets = MARKETS_V020
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (key, market) in markets {
+        let key = String::from_utf8(key).map_err(StdError::from)?;
+        let market_index = parse_v020_index(&key)?;
+        MARKETS_V020.remove(storage, key);
+        MARKETS.save(storage, U64Key::new(market_index), &market)?;
+    }
+
+    POSITIONS_MIGRATION.save(storage, &None)?;
+    migrate_positions(storage, DEFAULT_MIGRATE_LIMIT)?;
+    Ok(())
+}
+
+// re-keys the 0.2.0 positions of the next `limit` users after the cursor and clears the cursor
+// once no user is left, returns how many users were done
+fn migrate_positions(storage: &mut dyn Storage, limit: u32) -> Result<u32, ContractError> {
+    let cursor = match POSITIONS_MIGRATION.may_load(storage)? {
+        Some(cursor) => cursor,
+        None => return Ok(0),
+    };
+    let start = cursor.map(|user| Bound::exclusive(user.as_bytes()));
+    let users = USERS
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit as usize)
+        .map(|user| Addr::unchecked(String::from_utf8_lossy(&user)))
+        .collect::<Vec<Addr>>();
+    for user in users.iter() {
+        let positions = POSITIONS_V020
+            .prefix(user)
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (key, position) in positions {
+            let key = String::from_utf8(key).map_err(StdError::from)?;
+            let market_index = parse_v020_index(&key)?;
+            POSITIONS_V020.remove(storage, (user, key));
+            POSITIONS.save(storage, (user, U64Key::new(market_index)), &position)?;
+        }
+    }
+    if users.len() < limit as usize {
+        POSITIONS_MIGRATION.remove(storage);
+    } else {
+        POSITIONS_MIGRATION.save(storage, &users.last().cloned())?;
+    }
+    Ok(users.len() as u32)
+}
+
+fn try_migrate_positions(deps: DepsMut, limit: Option<u32>) -> Result<Response, ContractError> {
+    if POSITIONS_MIGRATION.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NothingToMigrate);
+    }
+    let limit = limit.unwrap_or(DEFAULT_MIGRATE_LIMIT).min(MAX_MIGRATE_LIMIT);
+    let users = migrate_positions(deps.storage, limit)?;
+    let done = POSITIONS_MIGRATION.may_load(deps.storage)?.is_none();
+    Ok(Response::new()
+        .add_attribute("method", "try_migrate_positions")
+        .add_attribute("users", users.to_string())
+        .add_attribute("done", done.to_string()))
+}
+
+fn parse_v020_index(key: &str) -> StdResult<u64> {
+    key.parse::<u64>()
+        .map_err(|_| StdError::parse_err("u64", format!("invalid market index key {}", key)))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
-        QueryMsg::GetUser { user_address } => Ok(to_binary(&get_user(deps, user_address)?)?),
+        QueryMsg::GetUser {
+            user_address,
+            subaccount,
+        } => Ok(to_binary(&get_user(
+            deps,
+            user_address,
+            subaccount.unwrap_or_default(),
+        )?)?),
         QueryMsg::GetUserMarketPosition {
             user_address,
             index,
-        } => Ok(to_binary(&get_user_position(deps, user_address, index)?)?),
+            subaccount,
+        } => Ok(to_binary(&get_user_position(
+            deps,
+            user_address,
+            index,
+            subaccount.unwrap_or_default(),
+        )?)?),
         QueryMsg::GetUserPositions {
             user_address,
             start_after,
             limit,
+            subaccount,
         } => Ok(to_binary(&get_active_positions(
             deps,
             user_address,
             start_after,
             limit,
+            subaccount.unwrap_or_default(),
         )?)?),
+        QueryMsg::GetUserSubaccounts {
+            user_address,
+            start_after,
+            limit,
+        } => Ok(to_binary(&get_user_subaccounts(
+            deps,
+            user_address,
+            start_after,
+            limit,
+        )?)?),
+        QueryMsg::GetUserWithdrawalClaims {
+            user_address,
+            start_after,
+            limit,
+        } => Ok(to_binary(&get_user_withdrawal_claims(
+            deps,
+            user_address,
+            start_after,
+            limit,
+        )?)?),
+        QueryMsg::GetHooks { start_after, limit } => {
+            Ok(to_binary(&get_hooks(deps, start_after, limit)?)?)
+        }
         QueryMsg::GetOracleGuardRails {} => Ok(to_binary(&get_oracle_guard_rails(deps)?)?),
         QueryMsg::GetOrderState {} => Ok(to_binary(&get_order_state(deps)?)?),
         QueryMsg::GetFeeStructure {} => Ok(to_binary(&get_fee_structure(deps)?)?),
@@ -2,12 +2,16 @@ use std::ops::Mul;
 use crate::package::number::Number128;
 use integer_sqrt::IntegerSquareRoot;
 use cosmwasm_std::{DepsMut, Uint128};
+use cw_storage_plus::U64Key;
 
 use crate::error::{ContractError};
 
 use crate::package::types::SwapDirection;
 
-use crate::states::market::{Market, MARKETS};
+use crate::states::constants::DEFAULT_AMM_SNAPSHOT_INTERVAL;
+use crate::states::history::AmmSnapshotRecord;
+use crate::states::market::{Market, LAST_AMM_SNAPSHOT_TS, MARKETS};
+use crate::states::state::AMM_SNAPSHOT_INTERVAL;
 
 use crate::helpers::amm::{calculate_quote_asset_amount_swapped, calculate_new_oracle_price_twap};
 use crate::states::constants::MARK_PRICE_PRECISION;
@@ -21,23 +25,65 @@ pub fn update_mark_twap(
     now: u64,
     precomputed_mark_price: Option<Uint128>,
 ) -> Result<Uint128, ContractError> {
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let mark_twap = amm::calculate_new_mark_twap(&market.amm, now, precomputed_mark_price)?;
     market.amm.last_mark_price_twap = mark_twap;
     market.amm.last_mark_price_twap_ts = now;
-    MARKETS.update(deps.storage, market_index.to_string(), |_m| -> Result<Market, ContractError> {
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m| -> Result<Market, ContractError> {
         Ok(market)
     })?;
     return Ok(mark_twap);
 }
 
+// at most one snapshot per market per interval, taken from the market as it is saved
+pub fn take_amm_snapshot(
+    deps: &mut DepsMut,
+    market_index: u64,
+    now: u64,
+) -> Result<Option<AmmSnapshotRecord>, ContractError> {
+    let interval = AMM_SNAPSHOT_INTERVAL
+        .may_load(deps.storage)?
+        .unwrap_or(DEFAULT_AMM_SNAPSHOT_INTERVAL);
+    if interval == 0 {
+        return Ok(None);
+    }
+    let last_snapshot_ts = LAST_AMM_SNAPSHOT_TS.may_load(deps.storage, U64Key::new(market_index))?;
+    if let Some(last_snapshot_ts) = last_snapshot_ts {
+        if now < last_snapshot_ts.saturating_add(interval) {
+            return Ok(None);
+        }
+    }
+    LAST_AMM_SNAPSHOT_TS.save(deps.storage, U64Key::new(market_index), &now)?;
+
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
+    Ok(Some(AmmSnapshotRecord {
+        ts: now,
+        market_index,
+        mark_price: market.amm.mark_price()?,
+        mark_price_twap: market.amm.last_mark_price_twap,
+        oracle_price: market.amm.last_oracle_price,
+        base_asset_reserve: market.amm.base_asset_reserve,
+        quote_asset_reserve: market.amm.quote_asset_reserve,
+        sqrt_k: market.amm.sqrt_k,
+        peg_multiplier: market.amm.peg_multiplier,
+        base_asset_amount_long: Uint128::from(
+            market.base_asset_amount_long.i128().unsigned_abs(),
+        ),
+        base_asset_amount_short: Uint128::from(
+            market.base_asset_amount_short.i128().unsigned_abs(),
+        ),
+        base_asset_amount: market.base_asset_amount,
+        open_interest: market.open_interest,
+    }))
+}
+
 pub fn update_oracle_price_twap(
     deps: &mut DepsMut,
     market_index: u64,
     now: u64,
     oracle_price: i128,
 ) -> Result<i128, ContractError> {
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let mut a = market.amm.clone();
     let new_oracle_price_spread = oracle_price
         .checked_sub(a.last_oracle_price_twap.i128())
@@ -73,7 +119,7 @@ pub fn update_oracle_price_twap(
     }
 
     market.amm = a;
-    MARKETS.update(deps.storage, market_index.to_string(), |_m| -> Result<Market, ContractError> {
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m| -> Result<Market, ContractError> {
         Ok(market)
     })?;
 
@@ -84,7 +130,7 @@ pub fn update_oracle_price_twap(
 /// Increasing k costs the protocol money because it reduces slippage and improves the exit price for net market position
 /// Decreasing k costs the protocol money because it increases slippage and hurts the exit price for net market position
 pub fn adjust_k_cost(deps: &mut DepsMut, market_index: u64, new_sqrt_k: Uint128) -> Result<i128, ContractError> {
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     // Find the net market value before adjusting k
     let (current_net_market_value, _) =
         _calculate_base_asset_value_and_pnl(market.base_asset_amount.i128(), Uint128::zero(), &market.amm)?;
@@ -122,7 +168,7 @@ pub fn adjust_k_cost(deps: &mut DepsMut, market_index: u64, new_sqrt_k: Uint128)
         &market.amm,
     )?;
 
-    MARKETS.update(deps.storage, market_index.to_string(), |_m| -> Result<Market, ContractError> {
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m| -> Result<Market, ContractError> {
         Ok(market)
     })?;
 
@@ -137,7 +183,7 @@ pub fn swap_quote_asset(
     now: u64,
     precomputed_mark_price: Option<Uint128>,
 ) -> Result<i128, ContractError> {
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let a = market.amm.clone();
     update_mark_twap(deps, market_index, now, precomputed_mark_price)?;
     let quote_asset_reserve_amount =
@@ -162,7 +208,7 @@ pub fn swap_quote_asset(
         .checked_sub(new_base_asset_reserve.u128() as i128)
         .ok_or_else(|| (ContractError::MathError4))?;
 
-    MARKETS.update(deps.storage, market_index.to_string(), |_m| -> Result<Market, ContractError> {
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m| -> Result<Market, ContractError> {
         Ok(market)
     })?;
 
@@ -177,7 +223,7 @@ pub fn swap_base_asset(
     now: u64,
     precomputed_mark_price: Option<Uint128>
 ) -> Result<Uint128, ContractError> {
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let a = market.amm.clone();
     
     update_mark_twap(deps, market_index, now, precomputed_mark_price)?;
@@ -193,7 +239,7 @@ pub fn swap_base_asset(
     market.amm.base_asset_reserve = new_base_asset_reserve;
     market.amm.quote_asset_reserve = new_quote_asset_reserve;
 
-    MARKETS.update(deps.storage, market_index.to_string(), |_m| -> Result<Market, ContractError> {
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m| -> Result<Market, ContractError> {
         Ok(market)
     })?;
 
@@ -215,13 +261,13 @@ pub fn move_price(
     let k = base_asset_reserve
         .mul(quote_asset_reserve);
 
-    let mut mark = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut mark = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     
     mark.amm.base_asset_reserve = base_asset_reserve;
     mark.amm.quote_asset_reserve = quote_asset_reserve;
     mark.amm.sqrt_k = Uint128::from(k.u128().integer_sqrt());
 
-    MARKETS.update(deps.storage, market_index.to_string(), |_m| -> Result<Market, ContractError> {
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m| -> Result<Market, ContractError> {
         Ok(mark)
     })?;
     Ok(())
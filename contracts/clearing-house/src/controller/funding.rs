@@ -2,6 +2,7 @@ use std::cmp::max;
 
 use crate::package::number::Number128;
 use cosmwasm_std::Addr;
+use cw_storage_plus::U64Key;
 use cosmwasm_std::DepsMut;
 use cosmwasm_std::Uint128;
 
@@ -9,13 +10,14 @@ use crate::error::ContractError;
 
 use crate::helpers::amm::normalise_oracle_price;
 use crate::states::history::{
+    AmmSnapshotRecord,
     FundingPaymentRecord,
     FundingRateRecord,
 };
 use crate::states::market::{Market, MARKETS};
 use crate::states::state::ORACLEGUARDRAILS;
 use crate::states::state::STATE;
-use crate::states::user::{Position, POSITIONS, User, USERS};
+use crate::states::user::{Position, ISOLATED_COLLATERAL, POSITIONS, User, USERS};
 
 use crate::helpers::position::calculate_updated_collateral;
 use crate::states::constants::{
@@ -25,6 +27,7 @@ use crate::helpers::funding::{calculate_funding_payment, calculate_funding_rate_
 use crate::helpers::oracle;
 
 use crate::controller::amm;
+use crate::controller::margin;
 
 /// Funding payments are settled lazily. The amm tracks its cumulative funding rate (for longs and shorts)
 /// and the user's market position tracks how much funding the user been cumulatively paid for that market.
@@ -46,13 +49,13 @@ pub fn settle_funding_payment(
     }
     let markets_length = STATE.load(deps.storage)?.markets_length;
     for n in 1..markets_length {
-        let market_position = POSITIONS.load(deps.storage, (user_addr, n.to_string()));
+        let market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(n)));
         match market_position {
             Ok(mut m) => {
                 if m.base_asset_amount.i128() == 0 {
                     continue;
                 }
-                let market = MARKETS.load(deps.storage, n.to_string())?;
+                let market = MARKETS.load(deps.storage, U64Key::new(n))?;
                 let amm_cumulative_funding_rate = if m.base_asset_amount.i128() > 0 {
                     market.amm.cumulative_funding_rate_long.i128()
                 } else {
@@ -73,16 +76,30 @@ pub fn settle_funding_payment(
                             amm_cumulative_funding_short: market.amm.cumulative_funding_rate_short, //10e14
                             base_asset_amount: m.base_asset_amount,
                     });
-                    funding_payment = funding_payment
-                        .checked_add(market_funding_rate_payment)
-                        .ok_or_else(|| (ContractError::MathError))?;
+                    // an isolated position pays and receives funding out of its own collateral
+                    if ISOLATED_COLLATERAL.has(deps.storage, (user_addr, U64Key::new(n))) {
+                        let market_funding_payment_collateral = market_funding_rate_payment
+                            .checked_div(AMM_TO_QUOTE_PRECISION_RATIO_I128.u128() as i128)
+                            .ok_or(ContractError::MathError)?;
+                        margin::settle_collateral(
+                            deps.storage,
+                            user_addr,
+                            &mut user,
+                            Some(n),
+                            market_funding_payment_collateral,
+                        )?;
+                    } else {
+                        funding_payment = funding_payment
+                            .checked_add(market_funding_rate_payment)
+                            .ok_or_else(|| (ContractError::MathError))?;
+                    }
         
                     m.last_cumulative_funding_rate = Number128::new(amm_cumulative_funding_rate);
                     m.last_funding_rate_ts = market.amm.last_funding_rate_ts;
         
                     POSITIONS.update(
                         deps.storage,
-                        (user_addr, n.to_string()),
+                        (user_addr, U64Key::new(n)),
                         |_p| -> Result<Position, ContractError> { Ok(m) },
                     )?;
                 }
@@ -113,8 +130,8 @@ pub fn update_funding_rate(
     now: u64,
     funding_paused: bool,
     precomputed_mark_price: Option<Uint128>,
-) -> Result<Option<FundingRateRecord>, ContractError> {
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+) -> Result<(Option<FundingRateRecord>, Option<AmmSnapshotRecord>), ContractError> {
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let guard_rails = ORACLEGUARDRAILS.load(deps.storage)?;
 
     let time_since_last_update = now
@@ -211,7 +228,7 @@ pub fn update_funding_rate(
 
         MARKETS.update(
             deps.storage,
-            market_index.to_string(),
+            U64Key::new(market_index),
             |_m| -> Result<Market, ContractError> { Ok(market.clone()) },
         )?;
 
@@ -225,7 +242,9 @@ pub fn update_funding_rate(
                 mark_price_twap,
                 oracle_price_twap: Number128::new(oracle_price_twap),
         };
-        return Ok(Some(f));
+        let snapshot = amm::take_amm_snapshot(deps, market_index, now)?;
+        return Ok((Some(f), snapshot));
     }
-    Ok(None)
+    let snapshot = amm::take_amm_snapshot(deps, market_index, now)?;
+    Ok((None, snapshot))
 }
@@ -1,4 +1,5 @@
-use cosmwasm_std::{Addr, DepsMut, Uint128};
+use cosmwasm_std::{Addr, DepsMut, Storage, Uint128};
+use cw_storage_plus::U64Key;
 
 use crate::error::ContractError;
 use crate::helpers::position::{calculate_updated_collateral, calculate_slippage};
@@ -8,7 +9,7 @@ use crate::helpers::position::{
 };
 use crate::states::market::{LiquidationStatus, LiquidationType, MarketStatus, MARKETS};
 use crate::states::state::{ORACLEGUARDRAILS, STATE};
-use crate::states::user::{POSITIONS, USERS};
+use crate::states::user::{User, ISOLATED_COLLATERAL, POSITIONS, USERS};
 
 use crate::helpers::amm::use_oracle_price_for_margin_calculation;
 use crate::helpers::oracle::get_oracle_status;
@@ -18,21 +19,21 @@ use std::ops::Div;
 pub fn meets_initial_margin_requirement(
     deps: &mut DepsMut,
     user_addr: &Addr,
+    isolated_market: Option<u64>,
 ) -> Result<bool, ContractError> {
-    let user = USERS.load(deps.storage, user_addr)?;
+    let (collateral, markets) = margin_bucket(deps.storage, user_addr, isolated_market)?;
 
     let mut initial_margin_requirement: Uint128 = Uint128::zero();
     let mut unrealized_pnl: i128 = 0;
 
-    let markets_length = STATE.load(deps.storage)?.markets_length;
-    for n in 1..markets_length {
-        let market_position = POSITIONS.load(deps.storage, (user_addr, n.to_string()));
+    for n in markets {
+        let market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(n)));
         match market_position {
             Ok(m) => {
                 if m.base_asset_amount.i128() == 0 {
                     continue;
                 }
-                let market = MARKETS.load(deps.storage, n.to_string())?;
+                let market = MARKETS.load(deps.storage, U64Key::new(n))?;
                 let a = &market.amm;
                 let (position_base_asset_value, position_unrealized_pnl) =
                     calculate_base_asset_value_and_pnl(&m, a)?;
@@ -53,7 +54,7 @@ pub fn meets_initial_margin_requirement(
     initial_margin_requirement = initial_margin_requirement
         .checked_div(MARGIN_PRECISION)?;
 
-    let total_collateral = calculate_updated_collateral(user.collateral, unrealized_pnl)?;
+    let total_collateral = calculate_updated_collateral(collateral, unrealized_pnl)?;
 
     Ok(total_collateral.u128() >= initial_margin_requirement.u128())
 }
@@ -61,21 +62,21 @@ pub fn meets_initial_margin_requirement(
 pub fn meets_partial_margin_requirement(
     deps: &DepsMut,
     user_addr: &Addr,
+    isolated_market: Option<u64>,
 ) -> Result<bool, ContractError> {
-    let user = USERS.load(deps.storage, user_addr)?;
+    let (collateral, markets) = margin_bucket(deps.storage, user_addr, isolated_market)?;
 
     let mut partial_margin_requirement: Uint128 = Uint128::zero();
     let mut unrealized_pnl: i128 = 0;
 
-    let markets_length = STATE.load(deps.storage)?.markets_length;
-    for n in 1..markets_length {
-        let market_position = POSITIONS.load(deps.storage, (user_addr, n.to_string()));
+    for n in markets {
+        let market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(n)));
         match market_position {
             Ok(m) => {
                 if m.base_asset_amount.i128() == 0 {
                     continue;
                 }
-                let market = MARKETS.load(deps.storage, n.to_string())?;
+                let market = MARKETS.load(deps.storage, U64Key::new(n))?;
                 let a = &market.amm;
 
                 let (position_base_asset_value, position_unrealized_pnl) =
@@ -97,7 +98,7 @@ pub fn meets_partial_margin_requirement(
     partial_margin_requirement = partial_margin_requirement
         .checked_div(MARGIN_PRECISION)?;
 
-    let total_collateral = calculate_updated_collateral(user.collateral, unrealized_pnl)?;
+    let total_collateral = calculate_updated_collateral(collateral, unrealized_pnl)?;
 
     Ok(total_collateral >= partial_margin_requirement)
 }
@@ -106,23 +107,23 @@ pub fn calculate_free_collateral(
     deps: &DepsMut,
     user_addr: &Addr,
     market_to_close: Option<u64>,
+    isolated_market: Option<u64>,
 ) -> Result<(Uint128, Uint128), ContractError> {
     let mut closed_position_base_asset_value: Uint128 = Uint128::zero();
     let mut initial_margin_requirement: Uint128 = Uint128::zero();
     let mut unrealized_pnl: i128 = 0;
 
-    let user = USERS.load(deps.storage, user_addr)?;
+    let (collateral, markets) = margin_bucket(deps.storage, user_addr, isolated_market)?;
 
-    let markets_length = STATE.load(deps.storage)?.markets_length;
-    for n in 1..markets_length {
-        let market_position = POSITIONS.load(deps.storage, (user_addr, n.to_string()));
+    for n in markets {
+        let market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(n)));
         match market_position {
             Ok(m) => {
                 if m.base_asset_amount.i128() == 0 {
                     continue;
                 }
 
-                let market = MARKETS.load(deps.storage, n.to_string())?;
+                let market = MARKETS.load(deps.storage, U64Key::new(n))?;
                 let a = &market.amm;
                 let (position_base_asset_value, position_unrealized_pnl) =
                     calculate_base_asset_value_and_pnl(&m, a)?;
@@ -149,7 +150,7 @@ pub fn calculate_free_collateral(
     initial_margin_requirement = initial_margin_requirement
         .checked_div(MARGIN_PRECISION)?;
 
-    let total_collateral = calculate_updated_collateral(user.collateral, unrealized_pnl)?;
+    let total_collateral = calculate_updated_collateral(collateral, unrealized_pnl)?;
 
     let free_collateral = if initial_margin_requirement < total_collateral {
         total_collateral
@@ -164,8 +165,9 @@ pub fn calculate_free_collateral(
 pub fn calculate_liquidation_status(
     deps: &mut DepsMut,
     user_addr: &Addr,
+    isolated_market: Option<u64>,
 ) -> Result<LiquidationStatus, ContractError> {
-    let user = USERS.load(deps.storage, user_addr)?;
+    let (collateral, markets) = margin_bucket(deps.storage, user_addr, isolated_market)?;
     let oracle_guard_rails = ORACLEGUARDRAILS.load(deps.storage)?;
 
     let mut partial_margin_requirement: Uint128 = Uint128::zero();
@@ -175,16 +177,15 @@ pub fn calculate_liquidation_status(
     let mut adjusted_unrealized_pnl: i128 = 0;
     let mut market_statuses: Vec<MarketStatus> = Vec::new();
 
-    let markets_length = STATE.load(deps.storage)?.markets_length;
-    for n in 1..markets_length {
-        let market_position = POSITIONS.load(deps.storage, (user_addr, n.to_string()));
+    for n in markets {
+        let market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(n)));
         match market_position {
             Ok(m) => {
                 if m.base_asset_amount.i128() == 0 {
                     continue;
                 }
 
-                let market = MARKETS.load(deps.storage, n.to_string())?;
+                let market = MARKETS.load(deps.storage, U64Key::new(n))?;
                 let a = &market.amm;
                 let (amm_position_base_asset_value, amm_position_unrealized_pnl) =
                     calculate_base_asset_value_and_pnl(&m, a)?;
@@ -298,7 +299,7 @@ pub fn calculate_liquidation_status(
                     close_position_slippage,
                 });
             }
-            Err(_) => todo!(),
+            Err(_) => continue,
         }
     }
 
@@ -308,9 +309,9 @@ pub fn calculate_liquidation_status(
     maintenance_margin_requirement = maintenance_margin_requirement
         .checked_div(MARGIN_PRECISION)?;
 
-    let total_collateral = calculate_updated_collateral(user.collateral, unrealized_pnl)?;
+    let total_collateral = calculate_updated_collateral(collateral, unrealized_pnl)?;
     let adjusted_total_collateral =
-        calculate_updated_collateral(user.collateral, adjusted_unrealized_pnl)?;
+        calculate_updated_collateral(collateral, adjusted_unrealized_pnl)?;
 
     let requires_partial_liquidation = adjusted_total_collateral < partial_margin_requirement;
     let requires_full_liquidation = adjusted_total_collateral < maintenance_margin_requirement;
@@ -362,6 +363,61 @@ pub fn calculate_liquidation_status(
     })
 }
 
+// the collateral and markets margined together: an isolated market against its own collateral,
+// or every market not isolated against the user's cross collateral
+fn margin_bucket(
+    storage: &dyn Storage,
+    user_addr: &Addr,
+    isolated_market: Option<u64>,
+) -> Result<(Uint128, Vec<u64>), ContractError> {
+    if let Some(market_index) = isolated_market {
+        let collateral =
+            ISOLATED_COLLATERAL.load(storage, (user_addr, U64Key::new(market_index)))?;
+        return Ok((collateral, vec![market_index]));
+    }
+    let user = USERS.load(storage, user_addr)?;
+    let markets_length = STATE.load(storage)?.markets_length;
+    let markets = (1..markets_length)
+        .filter(|n| !ISOLATED_COLLATERAL.has(storage, (user_addr, U64Key::new(*n))))
+        .collect();
+    Ok((user.collateral, markets))
+}
+
+pub fn isolated_market(
+    storage: &dyn Storage,
+    user_addr: &Addr,
+    market_index: u64,
+) -> Result<Option<u64>, ContractError> {
+    if ISOLATED_COLLATERAL.has(storage, (user_addr, U64Key::new(market_index))) {
+        Ok(Some(market_index))
+    } else {
+        Ok(None)
+    }
+}
+
+// realized pnl, funding and fees land in the collateral of the bucket the market belongs to
+pub fn settle_collateral(
+    storage: &mut dyn Storage,
+    user_addr: &Addr,
+    user: &mut User,
+    isolated_market: Option<u64>,
+    amount: i128,
+) -> Result<(), ContractError> {
+    match isolated_market {
+        Some(market_index) => {
+            let key = (user_addr, U64Key::new(market_index));
+            let collateral = ISOLATED_COLLATERAL.load(storage, key.clone())?;
+            ISOLATED_COLLATERAL.save(
+                storage,
+                key,
+                &calculate_updated_collateral(collateral, amount)?,
+            )?;
+        }
+        None => user.collateral = calculate_updated_collateral(user.collateral, amount)?,
+    }
+    Ok(())
+}
+
 pub fn validate_margin(
     margin_ratio_initial: u32,
     margin_ratio_partial: u32,
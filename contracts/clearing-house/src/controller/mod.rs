@@ -3,4 +3,5 @@ pub mod funding;
 pub mod margin;
 // pub mod order;
 pub mod position;
-pub mod repeg;
\ No newline at end of file
+pub mod repeg;
+pub mod withdrawal;
\ No newline at end of file
@@ -12,6 +12,7 @@ use std::cmp::min;
 use crate::package::number::Number128;
 use crate::package::types::{Order, OrderType, PositionDirection, SwapDirection, OrderStatus, OrderParams};
 use cosmwasm_std::{DepsMut, Addr, Uint128};
+use cw_storage_plus::U64Key;
 
 use crate::helpers::amm::{calculate_swap_output, normalise_oracle_price};
 use crate::states::constants::{
@@ -36,7 +37,7 @@ pub fn calculate_base_asset_amount_user_can_execute(
 ) -> Result<Uint128, ContractError> {
 
     let position_index = market_index;
-    let market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     
     let order = ORDERS.load(deps.storage, ((user_addr, market_index.to_string()), order_index.to_string()))?;
 
@@ -83,10 +84,10 @@ pub fn calculate_available_quote_asset_user_can_execute(
     position_index: u64,
 ) -> Result<Uint128, ContractError> {
 
-    let market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
     
     let market_index = position_index;
-    let market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     
     let order = ORDERS.load(deps.storage, ((user_addr, position_index.to_string()), order_index.to_string()))?;
 
@@ -137,10 +138,10 @@ pub fn place_order(
 
     let user = USERS.load(deps.storage, &user_addr.clone())?;
     let position_index = params.market_index;
-    let mut market_position = POSITIONS.load(deps.storage, (&user_addr.clone(), position_index.to_string()))?;
+    let mut market_position = POSITIONS.load(deps.storage, (&user_addr.clone(), U64Key::new(position_index)))?;
     
     let market_index = params.market_index;
-    let market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
    
     settle_funding_payment(
         deps,
@@ -237,11 +238,11 @@ pub fn cancel_order(
     now: u64
 ) -> Result<bool, ContractError> {
 
-    let mut market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let mut market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
     let oracle_guard_rails = ORACLEGUARDRAILS.load(deps.storage)?;
 
     let order = ORDERS.load(deps.storage, ((user_addr, position_index.to_string()), order_index.to_string()))?;
-    let market = MARKETS.load(deps.storage, position_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(position_index))?;
 
     settle_funding_payment(
         deps,
@@ -300,7 +301,7 @@ pub fn cancel_order(
 
     // Decrement open orders for existing position
     market_position.order_length -= 1;
-    POSITIONS.update(deps.storage, (user_addr, position_index.to_string()), |_p| -> Result<Position, ContractError> {
+    POSITIONS.update(deps.storage, (user_addr, U64Key::new(position_index)), |_p| -> Result<Position, ContractError> {
         Ok(market_position)
     })?;
 
@@ -334,7 +335,7 @@ pub fn expire_orders(
     let mut expired_order_len: u64 = 0;
     if state.markets_length > 0 {
         for i in 1..state.markets_length {
-            let market_position = POSITIONS.load(deps.storage, (user_addr,i.to_string()));
+            let market_position = POSITIONS.load(deps.storage, (user_addr,U64Key::new(i)));
             match market_position {
                 Ok(p) => {
                     if p.order_length > 0 {
@@ -354,7 +355,7 @@ pub fn expire_orders(
 
     if state.markets_length > 0 {
         for i in 1..state.markets_length {
-            let market_position = POSITIONS.load(deps.storage, (user_addr,i.to_string()));
+            let market_position = POSITIONS.load(deps.storage, (user_addr,U64Key::new(i)));
             match market_position {
                 Ok(mut p) => {
                     if p.clone().order_length > 0 {
@@ -402,7 +403,7 @@ pub fn expire_orders(
 
                             p.order_length -= 1;
                             // Decrement open orders for existing position
-                            POSITIONS.update(deps.storage, (user_addr, i.to_string()), |_position| -> Result<Position, ContractError> {
+                            POSITIONS.update(deps.storage, (user_addr, U64Key::new(i)), |_position| -> Result<Position, ContractError> {
                                 Ok(p.clone())
                             })?;
                         }
@@ -428,12 +429,12 @@ pub fn fill_order(
     let order_state = ORDERSTATE.load(deps.storage)?;
     let mut user = USERS.load(deps.storage, user_addr)?;
     let mut filler = USERS.load(deps.storage, filler_addr)?;
-    let mut market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let mut market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
     let order = ORDERS.load(deps.storage, ((user_addr, position_index.to_string()), order_index.to_string()))?;
     let market_index = position_index;
     let oracle_guard_rails = ORACLEGUARDRAILS.load(deps.storage)?;
     let fee_structure = FEESTRUCTURE.load(deps.storage)?;
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let mut referrer : Option<User> = None;
 
     if let Some(s) = user.referrer.clone() {
@@ -690,7 +691,7 @@ pub fn fill_order(
 
     // Decrement open orders for existing position
     market_position.order_length -= 1;
-    POSITIONS.update(deps.storage, (user_addr, position_index.to_string()), |_p| -> Result<Position, ContractError> {
+    POSITIONS.update(deps.storage, (user_addr, U64Key::new(position_index)), |_p| -> Result<Position, ContractError> {
         Ok(market_position)
     })?;
 
@@ -712,7 +713,7 @@ pub fn fill_order(
         }
     }
 
-    MARKETS.update(deps.storage, market_index.to_string(), |_m|-> Result<Market, ContractError> {
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m|-> Result<Market, ContractError> {
         Ok(market.clone())
     })?;
 
@@ -771,7 +772,7 @@ pub fn execute_market_order(
     now: u64,
 ) -> Result<(Uint128, Uint128, bool, Uint128), ContractError> {
     let order = ORDERS.load(deps.storage, ((user_addr, market_index.to_string()), order_index.to_string()))?;
-    let market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     let position_index = market_index;
 
@@ -850,7 +851,7 @@ pub fn execute_non_market_order(
     }
 
     let order = ORDERS.load(deps.storage, ((user_addr, market_index.to_string()), order_index.to_string()))?;
-    let market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     // Determine the base asset amount the market can fill
     let base_asset_amount_market_can_execute = calculate_base_asset_amount_market_can_execute(
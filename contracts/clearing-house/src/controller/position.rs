@@ -1,5 +1,6 @@
 use crate::package::number::Number128;
 use cosmwasm_std::{Addr, DepsMut, Uint128};
+use cw_storage_plus::U64Key;
 
 use crate::package::types::{PositionDirection, SwapDirection};
 
@@ -11,9 +12,10 @@ use crate::helpers::position::calculate_base_asset_value_and_pnl;
 use crate::states::market::{Market, MARKETS};
 use crate::states::user::{Position, User, POSITIONS, USERS};
 
-use crate::helpers::position::{calculate_pnl, calculate_updated_collateral};
+use crate::helpers::position::calculate_pnl;
 
 use crate::controller::amm;
+use crate::controller::margin;
 
 pub fn increase(
     deps: &mut DepsMut,
@@ -25,8 +27,8 @@ pub fn increase(
     now: u64,
     precomputed_mark_price: Option<Uint128>,
 ) -> Result<i128, ContractError> {
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
-    let mut market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
+    let mut market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
     if quote_asset_amount.is_zero() {
         return Ok(0 as i128);
     }
@@ -52,7 +54,7 @@ pub fn increase(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
@@ -65,7 +67,7 @@ pub fn increase(
         precomputed_mark_price,
     )?;
 
-    market = MARKETS.load(deps.storage, market_index.to_string())?;
+    market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     // update the position size on market and user
     market_position.base_asset_amount = Number128::new(
@@ -103,13 +105,13 @@ pub fn increase(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
     POSITIONS.update(
         deps.storage,
-        (user_addr, market_index.to_string()),
+        (user_addr, U64Key::new(market_index)),
         |_p| -> Result<Position, ContractError> { Ok(market_position) },
     )?;
 
@@ -127,7 +129,7 @@ pub fn reduce(
     precomputed_mark_price: Option<Uint128>,
 ) -> Result<i128, ContractError> {
     let mut user = USERS.load(deps.storage, user_addr)?;
-    let mut market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let mut market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
     let swap_direction = match direction {
         PositionDirection::Long => SwapDirection::Add,
         PositionDirection::Short => SwapDirection::Remove,
@@ -142,7 +144,7 @@ pub fn reduce(
         precomputed_mark_price,
     )?;
 
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     
     let base_asset_amount_before = market_position.base_asset_amount;
     market_position.base_asset_amount = Number128::new(
@@ -208,17 +210,18 @@ pub fn reduce(
         (initial_quote_asset_amount_closed.checked_sub(quote_asset_swap_amount)?).u128() as i128
     };
 
-    user.collateral = calculate_updated_collateral(user.collateral, pnl)?;
+    let isolated_market = margin::isolated_market(deps.storage, user_addr, market_index)?;
+    margin::settle_collateral(deps.storage, user_addr, &mut user, isolated_market, pnl)?;
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
     POSITIONS.update(
         deps.storage,
-        (user_addr, position_index.to_string()),
+        (user_addr, U64Key::new(position_index)),
         |_p| -> Result<Position, ContractError> { Ok(market_position) },
     )?;
 
@@ -241,7 +244,7 @@ pub fn close(
     precomputed_mark_price: Option<Uint128>,
 ) -> Result<(Uint128, i128, Uint128), ContractError> {
     let mut user = USERS.load(deps.storage, user_addr)?;
-    let mut market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let mut market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
     // If user has no base asset, return early
     if market_position.base_asset_amount.i128() == 0 {
         return Ok((Uint128::zero(), 0, Uint128::zero()));
@@ -262,7 +265,7 @@ pub fn close(
         precomputed_mark_price,
     )?;
 
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     
 
     let (quote_asset_amount, quote_asset_amount_surplus) = match maker_limit_price {
@@ -281,7 +284,8 @@ pub fn close(
         swap_direction,
     )?;
 
-    user.collateral = calculate_updated_collateral(user.collateral, pnl)?;
+    let isolated_market = margin::isolated_market(deps.storage, user_addr, market_index)?;
+    margin::settle_collateral(deps.storage, user_addr, &mut user, isolated_market, pnl)?;
     market_position.last_cumulative_funding_rate = Number128::zero();
     market_position.last_funding_rate_ts = 0;
 
@@ -320,13 +324,13 @@ pub fn close(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
     POSITIONS.update(
         deps.storage,
-        (user_addr, position_index.to_string()),
+        (user_addr, U64Key::new(position_index)),
         |_p| -> Result<Position, ContractError> { Ok(market_position) },
     )?;
 
@@ -361,7 +365,7 @@ pub fn add_new_position(
 
     POSITIONS.update(
         deps.storage,
-        (user_addr, market_index.to_string()),
+        (user_addr, U64Key::new(market_index)),
         |_p| -> Result<Position, ContractError> { Ok(new_market_position) },
     )?;
 
@@ -379,7 +383,7 @@ pub fn increase_with_base_asset_amount(
     precomputed_mark_price: Option<Uint128>,
 ) -> Result<(Uint128, Uint128), ContractError> {
     let user = USERS.load(deps.storage, user_addr)?;
-    let mut market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let mut market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
 
     let market_index = position_index;
 
@@ -387,7 +391,7 @@ pub fn increase_with_base_asset_amount(
         return Ok((Uint128::zero(), Uint128::zero()));
     }
 
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     // Update funding rate if this is a new position
     if market_position.base_asset_amount.i128() == 0 {
@@ -406,7 +410,7 @@ pub fn increase_with_base_asset_amount(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
@@ -419,7 +423,7 @@ pub fn increase_with_base_asset_amount(
         precomputed_mark_price,
     )?;
 
-    market = MARKETS.load(deps.storage, market_index.to_string())?;
+    market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     let (quote_asset_amount, quote_asset_amount_surplus) = match maker_limit_price {
         Some(limit_price) => calculate_quote_asset_amount_surplus(
@@ -475,13 +479,13 @@ pub fn increase_with_base_asset_amount(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
     POSITIONS.update(
         deps.storage,
-        (user_addr, position_index.to_string()),
+        (user_addr, U64Key::new(position_index)),
         |_p| -> Result<Position, ContractError> { Ok(market_position) },
     )?;
 
@@ -505,7 +509,7 @@ pub fn reduce_with_base_asset_amount(
     precomputed_mark_price: Option<Uint128>,
 ) -> Result<(Uint128, Uint128), ContractError> {
     let mut user = USERS.load(deps.storage, user_addr)?;
-    let mut market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let mut market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
 
     let market_index = position_index;
     
@@ -523,7 +527,7 @@ pub fn reduce_with_base_asset_amount(
         precomputed_mark_price,
     )?;
 
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     let (quote_asset_amount, quote_asset_amount_surplus) = match maker_limit_price {
         Some(limit_price) => calculate_quote_asset_amount_surplus(
@@ -603,17 +607,18 @@ pub fn reduce_with_base_asset_amount(
             .ok_or_else(|| (ContractError::MathError))?
     };
 
-    user.collateral = calculate_updated_collateral(user.collateral, pnl)?;
+    let isolated_market = margin::isolated_market(deps.storage, user_addr, market_index)?;
+    margin::settle_collateral(deps.storage, user_addr, &mut user, isolated_market, pnl)?;
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
     POSITIONS.update(
         deps.storage,
-        (user_addr, position_index.to_string()),
+        (user_addr, U64Key::new(position_index)),
         |_p| -> Result<Position, ContractError> { Ok(market_position) },
     )?;
 
@@ -636,7 +641,7 @@ pub fn update_position_with_base_asset_amount(
     now: u64,
     maker_limit_price: Option<Uint128>,
 ) -> Result<(bool, bool, Uint128, Uint128, Uint128), ContractError> {
-    let market_position = POSITIONS.load(deps.storage, (user_addr, position_index.to_string()))?;
+    let market_position = POSITIONS.load(deps.storage, (user_addr, U64Key::new(position_index)))?;
 
     let market_index = position_index;
 
@@ -751,7 +756,7 @@ pub fn update_position_with_quote_asset_amount(
 ) -> Result<(bool, bool, Uint128, Uint128, Uint128), ContractError> {
     let market_position;
     let existing_position =
-        POSITIONS.may_load(deps.storage, (&user_addr.clone(), position_index.to_string()))?;
+        POSITIONS.may_load(deps.storage, (&user_addr.clone(), U64Key::new(position_index)))?;
     match existing_position {
         Some(exp) => {
             market_position = exp;
@@ -768,13 +773,13 @@ pub fn update_position_with_quote_asset_amount(
             };
             POSITIONS.save(
                 deps.storage,
-                (&user_addr.clone(), position_index.to_string()),
+                (&user_addr.clone(), U64Key::new(position_index)),
                 &market_position,
             )?;
         }
     }
     let market_index = market_position.market_index;
-    let market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     // A trade is risk increasing if it increases the users leverage
     // If a trade is risk increasing and brings the user's margin ratio below initial requirement
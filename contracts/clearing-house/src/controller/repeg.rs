@@ -1,4 +1,5 @@
 use cosmwasm_std::{DepsMut, Uint128};
+use cw_storage_plus::U64Key;
 
 use crate::error::ContractError;
 
@@ -17,7 +18,7 @@ pub fn repeg(
     new_peg_candidate: Uint128
 ) -> Result<i128, ContractError> {
 
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     let oracle_guard_rails = ORACLEGUARDRAILS.load(deps.storage)?;
 
@@ -113,7 +114,7 @@ pub fn repeg(
             .checked_add(Uint128::from(adjustment_cost.unsigned_abs()))?;
     }
 
-    MARKETS.update(deps.storage, market_index.to_string(), |_m| ->  Result<Market, ContractError>{
+    MARKETS.update(deps.storage, U64Key::new(market_index), |_m| ->  Result<Market, ContractError>{
         Ok(market)
     })?;
 
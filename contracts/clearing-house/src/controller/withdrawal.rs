@@ -0,0 +1,220 @@
+use crate::error::ContractError;
+use crate::helpers::position::calculate_withdrawal_amounts;
+use crate::package::helper::{InsuranceVaultInterface, PayoutReason, VaultInterface};
+use crate::states::constants::MAX_WITHDRAWAL_CLAIMS_PER_TX;
+use crate::states::state::State;
+use crate::states::withdrawal::{
+    WithdrawalClaim, WITHDRAWAL_CLAIMS, WITHDRAWAL_QUEUE, WITHDRAWAL_QUEUE_ORDER,
+};
+
+use cosmwasm_std::{coins, to_binary, Addr, CosmosMsg, Storage, Uint128, WasmMsg};
+use cw_storage_plus::U64Key;
+
+pub fn vault_withdraw_messages(
+    state: &State,
+    to_address: &Addr,
+    collateral_account_withdrawal: Uint128,
+    insurance_account_withdrawal: Uint128,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if collateral_account_withdrawal.gt(&Uint128::zero()) {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: state.collateral_vault.to_string(),
+            msg: to_binary(&VaultInterface::Withdraw {
+                to_address: to_address.clone(),
+                amount: collateral_account_withdrawal,
+            })?,
+            funds: vec![],
+        }));
+    }
+    if insurance_account_withdrawal.gt(&Uint128::zero()) {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: state.insurance_vault.to_string(),
+            msg: to_binary(&InsuranceVaultInterface::Withdraw {
+                to_address: to_address.clone(),
+                amount: insurance_account_withdrawal,
+                reason: PayoutReason::UserWithdrawal,
+                market_index: None,
+            })?,
+            funds: vec![],
+        }));
+    }
+    Ok(messages)
+}
+
+fn vault_deposit_message(vault: &Addr, amount: Uint128) -> Result<CosmosMsg, ContractError> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: vault.to_string(),
+        msg: to_binary(&VaultInterface::Deposit {})?,
+        funds: coins(amount.u128(), "uusd"),
+    }))
+}
+
+// funds move between the vaults through the clearing house, withdrawn to it and deposited into
+// the other vault, so they are credited to its sub-ledger there. a send straight from one vault
+// to the other would sit in the receiving vault untracked
+pub fn insurance_fee_messages(
+    state: &State,
+    clearing_house: &Addr,
+    amount: Uint128,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    if amount.is_zero() {
+        return Ok(vec![]);
+    }
+    let mut messages = vault_withdraw_messages(state, clearing_house, amount, Uint128::zero())?;
+    messages.push(vault_deposit_message(&state.insurance_vault, amount)?);
+    Ok(messages)
+}
+
+pub fn market_top_up_messages(
+    state: &State,
+    clearing_house: &Addr,
+    amount: Uint128,
+    market_index: u64,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    Ok(vec![
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: state.insurance_vault.to_string(),
+            msg: to_binary(&InsuranceVaultInterface::Withdraw {
+                to_address: clearing_house.clone(),
+                amount,
+                reason: PayoutReason::MarketTopUp,
+                market_index: Some(market_index),
+            })?,
+            funds: vec![],
+        }),
+        vault_deposit_message(&state.collateral_vault, amount)?,
+    ])
+}
+
+pub fn has_outstanding_claims(storage: &dyn Storage) -> Result<bool, ContractError> {
+    let queue = WITHDRAWAL_QUEUE.may_load(storage)?.unwrap_or_default();
+    Ok(queue.head < queue.next_claim_id)
+}
+
+pub fn queue_withdrawal_claim(
+    storage: &mut dyn Storage,
+    user_address: &Addr,
+    amount: Uint128,
+    now: u64,
+) -> Result<u64, ContractError> {
+    let mut queue = WITHDRAWAL_QUEUE.may_load(storage)?.unwrap_or_default();
+    let claim_id = queue.next_claim_id;
+    WITHDRAWAL_CLAIMS.save(
+        storage,
+        (user_address, U64Key::new(claim_id)),
+        &WithdrawalClaim {
+            claim_id,
+            ts: now,
+            amount,
+            amount_paid: Uint128::zero(),
+        },
+    )?;
+    WITHDRAWAL_QUEUE_ORDER.save(storage, U64Key::new(claim_id), user_address)?;
+    queue.next_claim_id = claim_id + 1;
+    queue.total_outstanding = queue.total_outstanding.checked_add(amount)?;
+    WITHDRAWAL_QUEUE.save(storage, &queue)?;
+    Ok(claim_id)
+}
+
+// pays queued claims oldest first out of the given vault balances, the head claim can be paid partially.
+// returns the payout messages and what is left of the collateral and insurance balances
+pub fn process_withdrawal_queue(
+    storage: &mut dyn Storage,
+    state: &State,
+    clearing_house: &Addr,
+    balance_collateral: Uint128,
+    balance_insurance: Uint128,
+) -> Result<(Vec<CosmosMsg>, Uint128, Uint128), ContractError> {
+    let mut queue = WITHDRAWAL_QUEUE.may_load(storage)?.unwrap_or_default();
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut balance_collateral = balance_collateral;
+    let mut balance_insurance = balance_insurance;
+    let mut processed = 0;
+
+    // the head only moves past settled claims, claim_id runs ahead of it over the insurance
+    // vault's claims that have to wait for the collateral vault
+    let mut claim_id = queue.head;
+    let mut skipped = false;
+    while claim_id < queue.next_claim_id && processed < MAX_WITHDRAWAL_CLAIMS_PER_TX {
+        if balance_collateral.is_zero() && balance_insurance.is_zero() {
+            break;
+        }
+        let claimant = WITHDRAWAL_QUEUE_ORDER.may_load(storage, U64Key::new(claim_id))?;
+        if claimant.is_none() {
+            claim_id += 1;
+            if !skipped {
+                queue.head = claim_id;
+            }
+            continue;
+        }
+        let claimant = claimant.unwrap();
+        let key = (&claimant, U64Key::new(claim_id));
+        let mut claim = WITHDRAWAL_CLAIMS.load(storage, key.clone())?;
+
+        // liquidation fees queued for the insurance fund are only paid out of the collateral vault
+        let is_insurance_fee = claimant == state.insurance_vault;
+        let claimant_balance_insurance = if is_insurance_fee {
+            Uint128::zero()
+        } else {
+            balance_insurance
+        };
+        let (collateral_account_withdrawal, insurance_account_withdrawal) =
+            calculate_withdrawal_amounts(
+                claim.outstanding(),
+                balance_collateral,
+                claimant_balance_insurance,
+            )?;
+        if collateral_account_withdrawal.is_zero() && insurance_account_withdrawal.is_zero() {
+            // the user claims behind an unpayable fee can still be paid by the insurance vault
+            if is_insurance_fee {
+                claim_id += 1;
+                skipped = true;
+                processed += 1;
+                continue;
+            }
+            break;
+        }
+        let amount_paid = collateral_account_withdrawal.checked_add(insurance_account_withdrawal)?;
+
+        balance_collateral = balance_collateral.checked_sub(collateral_account_withdrawal)?;
+        balance_insurance = balance_insurance.checked_sub(insurance_account_withdrawal)?;
+        if is_insurance_fee {
+            messages.extend(insurance_fee_messages(
+                state,
+                clearing_house,
+                collateral_account_withdrawal,
+            )?);
+        } else {
+            messages.extend(vault_withdraw_messages(
+                state,
+                &claimant,
+                collateral_account_withdrawal,
+                insurance_account_withdrawal,
+            )?);
+        }
+
+        claim.amount_paid = claim.amount_paid.checked_add(amount_paid)?;
+        queue.total_outstanding = queue.total_outstanding.checked_sub(amount_paid)?;
+        processed += 1;
+
+        if claim.outstanding().is_zero() {
+            WITHDRAWAL_CLAIMS.remove(storage, key);
+            WITHDRAWAL_QUEUE_ORDER.remove(storage, U64Key::new(claim_id));
+            claim_id += 1;
+            if !skipped {
+                queue.head = claim_id;
+            }
+        } else {
+            WITHDRAWAL_CLAIMS.save(storage, key, &claim)?;
+            if !is_insurance_fee {
+                break;
+            }
+            claim_id += 1;
+            skipped = true;
+        }
+    }
+
+    WITHDRAWAL_QUEUE.save(storage, &queue)?;
+    Ok((messages, balance_collateral, balance_insurance))
+}
@@ -7,12 +7,30 @@ pub enum ContractError {
     Std(#[from] StdError),
     #[error("Unauthorized")]
     Unauthorized {},
+    #[error("Cannot migrate from {contract} {version}")]
+    InvalidMigration { contract: String, version: String },
+    #[error("Positions are still being migrated, try again once MigratePositions is done")]
+    MigrationPending,
+    #[error("Nothing to migrate")]
+    NothingToMigrate,
+    #[error("Hook not found")]
+    HookNotFound {},
+    #[error("At most {max} hooks can be registered")]
+    TooManyHooks { max: u32 },
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
     #[error("Clearing house cannot call collateral contract")]
     InvalidCollateralAccountAuthority,
     #[error("Clearing house cannot call insurance contract")]
     InvalidInsuranceAccountAuthority,
     #[error("The User does not exist")]
     UserDoesNotExist,
+    #[error("Cannot transfer collateral to the same subaccount")]
+    SameSubaccount,
+    #[error("Market position is not isolated")]
+    PositionNotIsolated,
+    #[error("Close the cross margined position before isolating its market")]
+    CrossPositionOpen,
     #[error("The state is not saved prior to this action")]
     ObjectDoesNotExist,
     #[error("Insufficient deposit")]
@@ -0,0 +1,193 @@
+//! Wasm events emitted by the clearing house, one per history record, so indexers can follow
+//! state changes from tx logs without the historical store. Every event carries `ts`, the block
+//! time in seconds. Amounts are decimal strings; signed amounts carry a leading `-`.
+//! `user` and `liquidator` name the margin account: the wallet for subaccount 0,
+//! `<wallet>/<n>` for subaccount n.
+//!
+//! | event             | attributes                                                            |
+//! |-------------------|-----------------------------------------------------------------------|
+//! | `trade`           | user, market_index, direction (`long`/`short`), base_asset_amount,    |
+//! |                   | quote_asset_amount, mark_price_before, mark_price_after, fee,         |
+//! |                   | referrer_reward, referee_discount, token_discount, liquidation,       |
+//! |                   | oracle_price                                                          |
+//! | `deposit`         | user, amount, collateral_before, cumulative_deposits_before           |
+//! | `withdraw`        | user, amount, collateral_before, cumulative_deposits_before           |
+//! | `funding_payment` | user, market_index, funding_payment, base_asset_amount,               |
+//! |                   | user_last_cumulative_funding, amm_cumulative_funding_long,            |
+//! |                   | amm_cumulative_funding_short                                          |
+//! | `funding_rate`    | market_index, funding_rate, cumulative_funding_rate_long,             |
+//! |                   | cumulative_funding_rate_short, mark_price_twap, oracle_price_twap     |
+//! | `liquidation`     | user, liquidator, partial, base_asset_value, base_asset_value_closed, |
+//! |                   | liquidation_fee, fee_to_liquidator, fee_to_insurance_fund,            |
+//! |                   | total_collateral, collateral, unrealized_pnl, margin_ratio            |
+//! | `liquidation_skip`| user, market_index, reason, value                                     |
+//! | `curve_update`    | market_index, peg_multiplier_before, peg_multiplier_after,            |
+//! |                   | base_asset_reserve_before, base_asset_reserve_after,                  |
+//! |                   | quote_asset_reserve_before, quote_asset_reserve_after, sqrt_k_before, |
+//! |                   | sqrt_k_after, adjustment_cost, oracle_price                           |
+//! | `amm_snapshot`    | market_index, mark_price, mark_price_twap, oracle_price,              |
+//! |                   | base_asset_reserve, quote_asset_reserve, sqrt_k, peg_multiplier,      |
+//! |                   | base_asset_amount, open_interest                                      |
+//!
+//! `order` is reserved for order fills; orders are disabled in this build so none are emitted.
+
+use cosmwasm_std::{Addr, Event};
+
+use crate::package::history::HistoryRecord;
+use crate::package::types::{DepositDirection, PositionDirection};
+use crate::states::history::{
+    AmmSnapshotRecord, CurveRecord, DepositRecord, FundingPaymentRecord, FundingRateRecord,
+    LiquidationRecord, TradeRecord,
+};
+
+pub fn history_events(records: &[HistoryRecord]) -> Vec<Event> {
+    records
+        .iter()
+        .map(|record| match record {
+            HistoryRecord::AmmSnapshot(s) => amm_snapshot_event(s),
+            HistoryRecord::Curve(c) => curve_update_event(c),
+            HistoryRecord::Deposit(d) => deposit_event(d),
+            HistoryRecord::FundingPayment(f) => funding_payment_event(f),
+            HistoryRecord::FundingRate(f) => funding_rate_event(f),
+            HistoryRecord::Liquidation(l) => liquidation_event(l),
+            HistoryRecord::Trade(t) => trade_event(t),
+        })
+        .collect()
+}
+
+pub fn liquidation_skip_event(
+    user: &Addr,
+    market_index: u64,
+    reason: &str,
+    value: i128,
+) -> Event {
+    Event::new("liquidation_skip")
+        .add_attribute("user", user.to_string())
+        .add_attribute("market_index", market_index.to_string())
+        .add_attribute("reason", reason)
+        .add_attribute("value", value.to_string())
+}
+
+fn trade_event(t: &TradeRecord) -> Event {
+    let direction = match t.direction {
+        PositionDirection::Long => "long",
+        PositionDirection::Short => "short",
+    };
+    Event::new("trade")
+        .add_attribute("ts", t.ts.to_string())
+        .add_attribute("user", t.user.to_string())
+        .add_attribute("market_index", t.market_index.to_string())
+        .add_attribute("direction", direction)
+        .add_attribute("base_asset_amount", t.base_asset_amount.to_string())
+        .add_attribute("quote_asset_amount", t.quote_asset_amount.to_string())
+        .add_attribute("mark_price_before", t.mark_price_before.to_string())
+        .add_attribute("mark_price_after", t.mark_price_after.to_string())
+        .add_attribute("fee", t.fee.to_string())
+        .add_attribute("referrer_reward", t.referrer_reward.to_string())
+        .add_attribute("referee_discount", t.referee_discount.to_string())
+        .add_attribute("token_discount", t.token_discount.to_string())
+        .add_attribute("liquidation", t.liquidation.to_string())
+        .add_attribute("oracle_price", t.oracle_price.i128().to_string())
+}
+
+fn deposit_event(d: &DepositRecord) -> Event {
+    let ty = match d.direction {
+        DepositDirection::DEPOSIT => "deposit",
+        DepositDirection::WITHDRAW => "withdraw",
+    };
+    Event::new(ty)
+        .add_attribute("ts", d.ts.to_string())
+        .add_attribute("user", d.user.to_string())
+        .add_attribute("amount", d.amount.to_string())
+        .add_attribute("collateral_before", d.collateral_before.to_string())
+        .add_attribute(
+            "cumulative_deposits_before",
+            d.cumulative_deposits_before.to_string(),
+        )
+}
+
+fn funding_payment_event(f: &FundingPaymentRecord) -> Event {
+    Event::new("funding_payment")
+        .add_attribute("ts", f.ts.to_string())
+        .add_attribute("user", f.user.to_string())
+        .add_attribute("market_index", f.market_index.to_string())
+        .add_attribute("funding_payment", f.funding_payment.i128().to_string())
+        .add_attribute("base_asset_amount", f.base_asset_amount.i128().to_string())
+        .add_attribute(
+            "user_last_cumulative_funding",
+            f.user_last_cumulative_funding.i128().to_string(),
+        )
+        .add_attribute(
+            "amm_cumulative_funding_long",
+            f.amm_cumulative_funding_long.i128().to_string(),
+        )
+        .add_attribute(
+            "amm_cumulative_funding_short",
+            f.amm_cumulative_funding_short.i128().to_string(),
+        )
+}
+
+fn funding_rate_event(f: &FundingRateRecord) -> Event {
+    Event::new("funding_rate")
+        .add_attribute("ts", f.ts.to_string())
+        .add_attribute("market_index", f.market_index.to_string())
+        .add_attribute("funding_rate", f.funding_rate.i128().to_string())
+        .add_attribute(
+            "cumulative_funding_rate_long",
+            f.cumulative_funding_rate_long.i128().to_string(),
+        )
+        .add_attribute(
+            "cumulative_funding_rate_short",
+            f.cumulative_funding_rate_short.i128().to_string(),
+        )
+        .add_attribute("mark_price_twap", f.mark_price_twap.to_string())
+        .add_attribute("oracle_price_twap", f.oracle_price_twap.i128().to_string())
+}
+
+fn liquidation_event(l: &LiquidationRecord) -> Event {
+    Event::new("liquidation")
+        .add_attribute("ts", l.ts.to_string())
+        .add_attribute("user", l.user.to_string())
+        .add_attribute("liquidator", l.liquidator.to_string())
+        .add_attribute("partial", l.partial.to_string())
+        .add_attribute("base_asset_value", l.base_asset_value.to_string())
+        .add_attribute("base_asset_value_closed", l.base_asset_value_closed.to_string())
+        .add_attribute("liquidation_fee", l.liquidation_fee.to_string())
+        .add_attribute("fee_to_liquidator", l.fee_to_liquidator.to_string())
+        .add_attribute("fee_to_insurance_fund", l.fee_to_insurance_fund.to_string())
+        .add_attribute("total_collateral", l.total_collateral.to_string())
+        .add_attribute("collateral", l.collateral.to_string())
+        .add_attribute("unrealized_pnl", l.unrealized_pnl.i128().to_string())
+        .add_attribute("margin_ratio", l.margin_ratio.to_string())
+}
+
+fn curve_update_event(c: &CurveRecord) -> Event {
+    Event::new("curve_update")
+        .add_attribute("ts", c.ts.to_string())
+        .add_attribute("market_index", c.market_index.to_string())
+        .add_attribute("peg_multiplier_before", c.peg_multiplier_before.to_string())
+        .add_attribute("peg_multiplier_after", c.peg_multiplier_after.to_string())
+        .add_attribute("base_asset_reserve_before", c.base_asset_reserve_before.to_string())
+        .add_attribute("base_asset_reserve_after", c.base_asset_reserve_after.to_string())
+        .add_attribute("quote_asset_reserve_before", c.quote_asset_reserve_before.to_string())
+        .add_attribute("quote_asset_reserve_after", c.quote_asset_reserve_after.to_string())
+        .add_attribute("sqrt_k_before", c.sqrt_k_before.to_string())
+        .add_attribute("sqrt_k_after", c.sqrt_k_after.to_string())
+        .add_attribute("adjustment_cost", c.adjustment_cost.i128().to_string())
+        .add_attribute("oracle_price", c.oracle_price.i128().to_string())
+}
+
+fn amm_snapshot_event(s: &AmmSnapshotRecord) -> Event {
+    Event::new("amm_snapshot")
+        .add_attribute("ts", s.ts.to_string())
+        .add_attribute("market_index", s.market_index.to_string())
+        .add_attribute("mark_price", s.mark_price.to_string())
+        .add_attribute("mark_price_twap", s.mark_price_twap.to_string())
+        .add_attribute("oracle_price", s.oracle_price.i128().to_string())
+        .add_attribute("base_asset_reserve", s.base_asset_reserve.to_string())
+        .add_attribute("quote_asset_reserve", s.quote_asset_reserve.to_string())
+        .add_attribute("sqrt_k", s.sqrt_k.to_string())
+        .add_attribute("peg_multiplier", s.peg_multiplier.to_string())
+        .add_attribute("base_asset_amount", s.base_asset_amount.i128().to_string())
+        .add_attribute("open_interest", s.open_interest.to_string())
+}
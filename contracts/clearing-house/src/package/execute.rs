@@ -2,6 +2,8 @@ use cosmwasm_std::{Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::states::hooks::HookEvent;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub collateral_vault: String,
@@ -11,6 +13,9 @@ pub struct InstantiateMsg {
     pub oracle: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -28,22 +33,51 @@ pub enum ExecuteMsg {
         margin_ratio_maintenance: u32,
     },
     //deposit collateral, updates user struct
+    // `subaccount` picks one of the sender's margin accounts, none is subaccount 0
     DepositCollateral {
         amount: u64,
-        referrer: Option<String>
+        referrer: Option<String>,
+        subaccount: Option<u64>,
     },
     //user function withdraw collateral, updates user struct
     WithdrawCollateral {
         amount: u64,
+        subaccount: Option<u64>,
+    },
+    //moves collateral between two of the sender's subaccounts without touching the vaults
+    TransferCollateral {
+        amount: u64,
+        from_subaccount: u64,
+        to_subaccount: u64,
+    },
+    //moves cross collateral onto a market's position, which is then margined on its own
+    AddIsolatedMargin {
+        market_index: u64,
+        amount: u64,
+        subaccount: Option<u64>,
+    },
+    //moves isolated collateral back to cross, a flat market with none left returns to cross margin
+    RemoveIsolatedMargin {
+        market_index: u64,
+        amount: u64,
+        subaccount: Option<u64>,
+    },
+    //anyone can call it, pays out queued withdrawal claims from the vault balances
+    ProcessWithdrawalQueue {},
+    //anyone can call it, re-keys the positions of the next users left over from 0.2.0
+    MigratePositions {
+        limit: Option<u32>,
     },
     OpenPosition {
         is_direction_long: bool,
         quote_asset_amount: Uint128,
         market_index: u64,
         limit_price: Option<Uint128>,
+        subaccount: Option<u64>,
     },
     ClosePosition {
         market_index: u64,
+        subaccount: Option<u64>,
     },
 
     // order related messages
@@ -62,9 +96,12 @@ pub enum ExecuteMsg {
     //     user_address: String,
     //     market_index: u64,
     // },
+    // the fee share is credited to the sender's `liquidator_subaccount`
     Liquidate {
         user: String,
         market_index: u64,
+        subaccount: Option<u64>,
+        liquidator_subaccount: Option<u64>,
     },
     MoveAMMPrice {
         base_asset_reserve: Uint128,
@@ -98,7 +135,9 @@ pub enum ExecuteMsg {
         market_index: u64,
     },
     //user calls it we get the user identification from msg address sender
-    SettleFundingPayment {},
+    SettleFundingPayment {
+        subaccount: Option<u64>,
+    },
     UpdateFundingRate {
         market_index: u64,
     },
@@ -176,9 +215,28 @@ pub enum ExecuteMsg {
     UpdateHistoryStore {
         history_contract: String,
     },
+    //admin accepts this contract as the clearing house proposed by both vaults
+    AcceptVaultsClearingHouse {},
     UpdateMaxDeposit {
         max_deposit: Uint128,
     },
+    // seconds between amm snapshots of a market, 0 turns them off
+    UpdateAmmSnapshotInterval {
+        interval: u64,
+    },
+    // registers a contract to be called on the given events, replacing an existing registration
+    AddHook {
+        contract: String,
+        events: Vec<HookEvent>,
+        revert_on_error: bool,
+    },
+    RemoveHook {
+        contract: String,
+    },
+    // gas each hook registered with revert_on_error false may use
+    UpdateHookGasLimit {
+        gas_limit: u64,
+    },
     UpdateExchangePaused {
         exchange_paused: bool,
     },
@@ -1,6 +1,6 @@
 use cosmwasm_std::{
-    Addr, Api, BalanceResponse, BankQuery, MessageInfo, QuerierWrapper, QueryRequest, StdError,
-    StdResult, Uint128,
+    to_binary, Addr, Api, MessageInfo, QuerierWrapper, QueryRequest, StdError, StdResult, Uint128,
+    WasmQuery,
 };
 use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
@@ -39,12 +39,20 @@ pub fn assert_sent_uusd_balance(message_info: &MessageInfo, input_amount: u128)
     }
 }
 
-pub fn query_balance(querier: &QuerierWrapper, account_addr: Addr) -> StdResult<u128> {
-    let balance: BalanceResponse = querier.query(&QueryRequest::Bank(BankQuery::Balance {
-        address: String::from(account_addr),
-        denom: "uusd".to_string(),
+/// Returns the deposits a vault tracks for this clearing house rather than its raw bank balance,
+/// so funds sent to the vault directly or deposited by another clearing house are never paid out.
+pub fn query_vault_balance(
+    querier: &QuerierWrapper,
+    vault_addr: Addr,
+    clearing_house: &Addr,
+) -> StdResult<u128> {
+    let balance: VaultBalanceResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: vault_addr.to_string(),
+        msg: to_binary(&VaultQueryInterface::GetClearingHouseBalance {
+            clearing_house: clearing_house.to_string(),
+        })?,
     }))?;
-    Ok(balance.amount.amount.u128())
+    Ok(balance.balance.u128())
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -54,6 +62,37 @@ pub enum VaultInterface {
         to_address: Addr,
         amount: Uint128
     },
-    Deposit {}
+    Deposit {},
+    AcceptClearingHouse {}
+}
 
-}
\ No newline at end of file
+// the insurance vault records every payout with the reason it was made for
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum InsuranceVaultInterface {
+    Withdraw {
+        to_address: Addr,
+        amount: Uint128,
+        reason: PayoutReason,
+        market_index: Option<u64>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutReason {
+    UserWithdrawal,
+    MarketTopUp,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultQueryInterface {
+    GetBalance {},
+    GetClearingHouseBalance { clearing_house: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VaultBalanceResponse {
+    pub balance: Uint128,
+}
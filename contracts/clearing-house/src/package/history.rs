@@ -1,7 +1,11 @@
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, StdResult, WasmMsg};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::states::history::{CurveRecord, DepositRecord, FundingPaymentRecord, FundingRateRecord, LiquidationRecord, TradeRecord};
+use crate::states::history::{
+    AmmSnapshotRecord, CurveRecord, DepositRecord, FundingPaymentRecord, FundingRateRecord,
+    LiquidationRecord, TradeRecord,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -12,6 +16,9 @@ pub enum HistoryExecuteMsg {
     UpdateClearingHouse {
         new_house: String,
     },
+    RecordAmmSnapshot {
+        s: AmmSnapshotRecord
+    },
     RecordCurve {
         c: CurveRecord
     },
@@ -33,4 +40,34 @@ pub enum HistoryExecuteMsg {
     RecordFundingPaymentsMultiple {
         vecf: Vec<FundingPaymentRecord>
     },
+    RecordBatch {
+        records: Vec<HistoryRecord>
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryRecord {
+    AmmSnapshot(AmmSnapshotRecord),
+    Curve(CurveRecord),
+    Deposit(DepositRecord),
+    FundingPayment(FundingPaymentRecord),
+    FundingRate(FundingRateRecord),
+    Liquidation(LiquidationRecord),
+    Trade(TradeRecord),
+}
+
+// everything a transaction records goes out in one message, none when there is nothing to record
+pub fn history_batch_message(
+    history_contract: &Addr,
+    records: Vec<HistoryRecord>,
+) -> StdResult<Option<CosmosMsg>> {
+    if records.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: history_contract.to_string(),
+        msg: to_binary(&HistoryExecuteMsg::RecordBatch { records })?,
+        funds: vec![],
+    })))
 }
@@ -0,0 +1,125 @@
+//! Interface the clearing house calls on contracts registered with `AddHook`. After a transaction
+//! records a trade, liquidation or funding payment, each hook subscribed to that event type gets
+//! one `clearing_house_hook` execute message per record. A hook registered with
+//! `revert_on_error: false` is sent as a submessage that replies on error, so its failure is
+//! logged and skipped rather than reverting the transaction. Such a hook only gets the gas set
+//! with `UpdateHookGasLimit`, so a hook that runs out of gas is skipped the same way.
+
+use cosmwasm_std::{
+    to_binary, Addr, CosmosMsg, Order, StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::package::history::HistoryRecord;
+use crate::package::number::Number128;
+use crate::package::types::PositionDirection;
+use crate::states::constants::DEFAULT_HOOK_GAS_LIMIT;
+use crate::states::hooks::{Hook, HookEvent, HOOKS, HOOK_GAS_LIMIT};
+
+pub const HOOK_REPLY_ID: u64 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookExecuteMsg {
+    ClearingHouseHook(HookPayload),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookPayload {
+    Trade {
+        ts: u64,
+        user: Addr,
+        market_index: u64,
+        direction: PositionDirection,
+        base_asset_amount: Uint128,
+        quote_asset_amount: Uint128,
+        liquidation: bool,
+    },
+    Liquidation {
+        ts: u64,
+        user: Addr,
+        liquidator: Addr,
+        partial: bool,
+        base_asset_value_closed: Uint128,
+        liquidation_fee: Uint128,
+    },
+    FundingPayment {
+        ts: u64,
+        user: Addr,
+        market_index: u64,
+        funding_payment: Number128,
+    },
+}
+
+fn hook_payload(record: &HistoryRecord) -> Option<(HookEvent, HookPayload)> {
+    match record {
+        HistoryRecord::Trade(t) => Some((
+            HookEvent::Trade,
+            HookPayload::Trade {
+                ts: t.ts,
+                user: t.user.clone(),
+                market_index: t.market_index,
+                direction: t.direction,
+                base_asset_amount: t.base_asset_amount,
+                quote_asset_amount: t.quote_asset_amount,
+                liquidation: t.liquidation,
+            },
+        )),
+        HistoryRecord::Liquidation(l) => Some((
+            HookEvent::Liquidation,
+            HookPayload::Liquidation {
+                ts: l.ts,
+                user: l.user.clone(),
+                liquidator: l.liquidator.clone(),
+                partial: l.partial,
+                base_asset_value_closed: l.base_asset_value_closed,
+                liquidation_fee: l.liquidation_fee,
+            },
+        )),
+        HistoryRecord::FundingPayment(f) => Some((
+            HookEvent::FundingPayment,
+            HookPayload::FundingPayment {
+                ts: f.ts,
+                user: f.user.clone(),
+                market_index: f.market_index,
+                funding_payment: f.funding_payment,
+            },
+        )),
+        _ => None,
+    }
+}
+
+// one submessage per subscribed hook per record, in record order
+pub fn hook_messages(storage: &dyn Storage, records: &[HistoryRecord]) -> StdResult<Vec<SubMsg>> {
+    let payloads: Vec<(HookEvent, HookPayload)> =
+        records.iter().filter_map(hook_payload).collect();
+    if payloads.is_empty() {
+        return Ok(vec![]);
+    }
+    let hooks = HOOKS
+        .range(storage, None, None, Order::Ascending)
+        .map(|hook| hook.map(|(_, hook)| hook))
+        .collect::<StdResult<Vec<Hook>>>()?;
+    let gas_limit = HOOK_GAS_LIMIT
+        .may_load(storage)?
+        .unwrap_or(DEFAULT_HOOK_GAS_LIMIT);
+
+    let mut messages = vec![];
+    for (event, payload) in payloads {
+        for hook in hooks.iter().filter(|hook| hook.events.contains(&event)) {
+            let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: hook.contract.to_string(),
+                msg: to_binary(&HookExecuteMsg::ClearingHouseHook(payload.clone()))?,
+                funds: vec![],
+            });
+            messages.push(if hook.revert_on_error {
+                SubMsg::new(msg)
+            } else {
+                SubMsg::reply_on_error(msg, HOOK_REPLY_ID).with_gas_limit(gas_limit)
+            });
+        }
+    }
+    Ok(messages)
+}
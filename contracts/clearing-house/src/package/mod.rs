@@ -5,4 +5,6 @@ pub mod types;
 pub mod helper;
 pub mod number;
 pub mod history;
-pub mod oracle;
\ No newline at end of file
+pub mod events;
+pub mod oracle;
+pub mod hooks;
\ No newline at end of file
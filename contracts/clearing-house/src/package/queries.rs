@@ -6,13 +6,31 @@ use serde::{Deserialize, Serialize};
 pub enum QueryMsg {
     GetUser {
         user_address: String,
+        subaccount: Option<u64>,
     },
     GetUserMarketPosition {
         user_address: String,
         index: u64,
+        subaccount: Option<u64>,
     },
     GetUserPositions {
         user_address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        subaccount: Option<u64>,
+    },
+    GetUserSubaccounts {
+        user_address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // claims belong to the wallet since queued withdrawals are paid out to it
+    GetUserWithdrawalClaims {
+        user_address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetHooks {
         start_after: Option<String>,
         limit: Option<u32>,
     },
@@ -3,6 +3,7 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::package::{types::{OracleSource, PositionDirection}, number::Number128};
+use crate::states::hooks::HookEvent;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct UserResponse {
@@ -47,10 +48,13 @@ pub struct StateResponse {
 
     pub max_deposit: Uint128,
     pub markets_length: u64,
+    pub amm_snapshot_interval: u64,
+    pub hook_gas_limit: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct PositionResponse {
+    pub market_index: u64,
     pub base_asset_amount: Number128,
     pub quote_asset_amount: Uint128,
     pub last_cumulative_funding_rate: Number128,
@@ -59,7 +63,9 @@ pub struct PositionResponse {
     pub direction: PositionDirection,
     pub initial_size: Uint128,
     pub entry_notional: Number128,
-    pub pnl: Number128
+    pub pnl: Number128,
+    // collateral of an isolated position, none when the market is cross margined
+    pub isolated_collateral: Option<Uint128>,
 }
 
 
@@ -95,6 +101,28 @@ pub struct OrderStateResponse {
     pub reward: Decimal,
     pub time_based_reward_lower_bound: Uint128, // minimum filler reward for time-based reward
 }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SubaccountResponse {
+    pub subaccount: u64,
+    pub collateral: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HookResponse {
+    pub contract: String,
+    pub events: Vec<HookEvent>,
+    pub revert_on_error: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalClaimResponse {
+    pub claim_id: u64,
+    pub ts: u64,
+    pub amount: Uint128,
+    pub amount_paid: Uint128,
+    pub outstanding: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MarketLengthResponse {
     pub length: u64,
@@ -27,6 +27,11 @@ pub const UPDATE_K_ALLOWED_PRICE_CHANGE: Uint128 = Uint128::new(1_000_000_000);
 
 // TIME PERIODS
 pub const ONE_HOUR: Uint128 =  Uint128::new(3600);
+pub const DEFAULT_AMM_SNAPSHOT_INTERVAL: u64 = 3600;
+
+// HOOKS
+pub const MAX_HOOKS: u32 = 10;
+pub const DEFAULT_HOOK_GAS_LIMIT: u64 = 300_000;
 
 // FEES
 pub const SHARE_OF_FEES_ALLOCATED_TO_CLEARING_HOUSE_NUMERATOR: Uint128 = Uint128::new(5);
@@ -70,4 +75,11 @@ pub const MINIMUM_MARGIN_RATIO: Uint128 =  Uint128::new(200);// MARGIN_PRECISION
 
 // iterator limits
 pub const MAX_LIMIT: u32 = 20;
-pub const DEFAULT_LIMIT: u32 = 10;
\ No newline at end of file
+pub const DEFAULT_LIMIT: u32 = 10;
+
+// WITHDRAWAL QUEUE
+pub const MAX_WITHDRAWAL_CLAIMS_PER_TX: u32 = 10;
+
+// users whose positions are re-keyed per MigratePositions call
+pub const DEFAULT_MIGRATE_LIMIT: u32 = 30;
+pub const MAX_MIGRATE_LIMIT: u32 = 100;
@@ -17,6 +17,23 @@ impl Default for Type {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AmmSnapshotRecord {
+    pub ts: u64,
+    pub market_index: u64,
+    pub mark_price: Uint128,
+    pub mark_price_twap: Uint128,
+    pub oracle_price: Number128,
+    pub base_asset_reserve: Uint128,
+    pub quote_asset_reserve: Uint128,
+    pub sqrt_k: Uint128,
+    pub peg_multiplier: Uint128,
+    pub base_asset_amount_long: Uint128,
+    pub base_asset_amount_short: Uint128,
+    pub base_asset_amount: Number128,
+    pub open_interest: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CurveRecord {
     pub ts: u64,
@@ -0,0 +1,25 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::Addr;
+use cw_storage_plus::{Item, Map};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    Trade,
+    Liquidation,
+    FundingPayment,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Hook {
+    pub contract: Addr,
+    pub events: Vec<HookEvent>,
+    // true fails the whole transaction when the hook errors, false drops the hook's changes only
+    pub revert_on_error: bool,
+}
+
+pub const HOOKS: Map<&Addr, Hook> = Map::new("hooks");
+// gas each hook that doesn't revert on error may use before it is skipped
+pub const HOOK_GAS_LIMIT: Item<u64> = Item::new("hook_gas_limit");
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{Addr, Uint128, DepsMut, WasmQuery, QueryRequest, to_binary};
 
-use cw_storage_plus::Map;
+use cw_storage_plus::{Map, U64Key};
 
 use crate::package::types::{OracleSource, OracleStatus, OraclePriceData};
 
@@ -54,7 +54,10 @@ pub struct Amm {
     pub minimum_base_asset_trade_size: Uint128,
 }
 
-pub const MARKETS: Map<String, Market> = Map::new("markets");
+pub const MARKETS: Map<U64Key, Market> = Map::new("markets");
+// keyed by market_index.to_string() up to 0.2.0
+pub const MARKETS_V020: Map<String, Market> = Map::new("markets");
+pub const LAST_AMM_SNAPSHOT_TS: Map<U64Key, u64> = Map::new("last_amm_snapshot_ts");
 
 impl Amm {
     pub fn mark_price(&self) -> Result<Uint128, ContractError> {
@@ -76,9 +79,9 @@ impl Amm {
         }))?;
 
         let price = x.price.u128();
-        let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+        let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
         market.amm.last_oracle_price = Number128::new(price as i128);
-        MARKETS.update(deps.storage, market_index.to_string(), |_m| -> Result<Market,ContractError> {
+        MARKETS.update(deps.storage, U64Key::new(market_index), |_m| -> Result<Market,ContractError> {
             Ok(market)
         })?;
 
@@ -3,4 +3,6 @@ pub mod market;
 pub mod state;
 pub mod user;
 pub mod history;
-pub mod constants;
\ No newline at end of file
+pub mod constants;
+pub mod withdrawal;
+pub mod hooks;
\ No newline at end of file
@@ -42,3 +42,5 @@ pub const STATE: Item<State> = Item::new("state");
 pub const FEESTRUCTURE: Item<FeeStructure> = Item::new("fee_structure");
 pub const ORACLEGUARDRAILS: Item<OracleGuardRails> = Item::new("oracle_guard_rails");
 pub const ORDERSTATE: Item<OrderState> = Item::new("order_state");
+// seconds between amm snapshots of a market, 0 turns them off
+pub const AMM_SNAPSHOT_INTERVAL: Item<u64> = Item::new("amm_snapshot_interval");
@@ -3,7 +3,7 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::Map;
+use cw_storage_plus::{Item, Map, U64Key};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct User {
@@ -28,7 +28,26 @@ pub struct Position {
 }
 
 pub const USERS: Map<&Addr, User> = Map::new("users");
-pub const POSITIONS: Map<(&Addr, String), Position> = Map::new("market_positions");
+pub const POSITIONS: Map<(&Addr, U64Key), Position> = Map::new("market_positions");
+// keyed by (user, market_index.to_string()) up to 0.2.0
+pub const POSITIONS_V020: Map<(&Addr, String), Position> = Map::new("market_positions");
+// present while 0.2.0 positions are still being re-keyed, holds the last user that was done
+pub const POSITIONS_MIGRATION: Item<Option<Addr>> = Item::new("positions_migration");
+// (account, market_index) -> collateral assigned to an isolated position, markets without an entry
+// are cross margined against User.collateral
+pub const ISOLATED_COLLATERAL: Map<(&Addr, U64Key), Uint128> = Map::new("isolated_collateral");
+// (wallet, subaccount) -> the account key its User and positions are stored under
+pub const SUBACCOUNTS: Map<(&Addr, U64Key), Addr> = Map::new("subaccounts");
+
+// subaccount 0 is the wallet itself so accounts opened before subaccounts keep their storage,
+// subaccount n is kept under `<wallet>/<n>`, which no validated address can collide with
+pub fn subaccount_address(wallet: &Addr, subaccount: u64) -> Addr {
+    if subaccount == 0 {
+        wallet.clone()
+    } else {
+        Addr::unchecked(format!("{}/{}", wallet, subaccount))
+    }
+}
 
 impl Position {
     pub fn is_for(&self, market_index: u64) -> bool {
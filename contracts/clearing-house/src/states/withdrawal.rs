@@ -0,0 +1,31 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Uint128};
+use cw_storage_plus::{Item, Map, U64Key};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalClaim {
+    pub claim_id: u64,
+    pub ts: u64,
+    pub amount: Uint128,
+    pub amount_paid: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct WithdrawalQueue {
+    pub head: u64,
+    pub next_claim_id: u64,
+    pub total_outstanding: Uint128,
+}
+
+impl WithdrawalClaim {
+    pub fn outstanding(&self) -> Uint128 {
+        self.amount.saturating_sub(self.amount_paid)
+    }
+}
+
+pub const WITHDRAWAL_QUEUE: Item<WithdrawalQueue> = Item::new("withdrawal_queue");
+// claim id -> claimant, walked from `head` to pay claims in the order they were queued
+pub const WITHDRAWAL_QUEUE_ORDER: Map<U64Key, Addr> = Map::new("withdrawal_queue_order");
+pub const WITHDRAWAL_CLAIMS: Map<(&Addr, U64Key), WithdrawalClaim> = Map::new("withdrawal_claims");
@@ -0,0 +1,94 @@
+use super::helpers::*;
+use crate::contract::execute;
+use crate::package::execute::ExecuteMsg;
+use crate::package::history::HistoryRecord;
+use crate::package::types::DepositDirection;
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{Event, Response};
+
+fn event_attr<'a>(event: &'a Event, key: &str) -> &'a str {
+    event
+        .attributes
+        .iter()
+        .find(|a| a.key == key)
+        .map(|a| a.value.as_str())
+        .unwrap_or_else(|| panic!("{} has no {}", event.ty, key))
+}
+
+// the event types of the response, checked to be one per history record in the same order
+fn event_types(res: &Response) -> Vec<&str> {
+    let records = history_records(res)
+        .iter()
+        .map(|r| match r {
+            HistoryRecord::AmmSnapshot(_) => "amm_snapshot",
+            HistoryRecord::Curve(_) => "curve_update",
+            HistoryRecord::Deposit(d) => match d.direction {
+                DepositDirection::DEPOSIT => "deposit",
+                DepositDirection::WITHDRAW => "withdraw",
+            },
+            HistoryRecord::FundingPayment(_) => "funding_payment",
+            HistoryRecord::FundingRate(_) => "funding_rate",
+            HistoryRecord::Liquidation(_) => "liquidation",
+            HistoryRecord::Trade(_) => "trade",
+        })
+        .collect::<Vec<&str>>();
+    let types = res
+        .events
+        .iter()
+        .map(|e| e.ty.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(types, records);
+    types
+}
+
+#[test]
+fn every_history_record_is_emitted_as_a_typed_event() {
+    let mut deps = setup();
+    init_market(&mut deps, 1);
+    let ts = mock_env().block.time.seconds().to_string();
+
+    let res = deposit(&mut deps, "alice", 100_000_000);
+    assert_eq!(event_types(&res), vec!["deposit"]);
+    let deposit = &res.events[0];
+    assert_eq!(event_attr(deposit, "ts"), ts);
+    assert_eq!(event_attr(deposit, "user"), "alice");
+    assert_eq!(event_attr(deposit, "amount"), "100000000");
+    assert_eq!(event_attr(deposit, "collateral_before"), "0");
+    assert_eq!(event_attr(deposit, "cumulative_deposits_before"), "0");
+
+    // the first trade of a market also snapshots its amm
+    let res = open_position(&mut deps, "alice", true, 50_000_000, 1).unwrap();
+    assert_eq!(event_types(&res), vec!["trade", "amm_snapshot"]);
+    let trade = &res.events[0];
+    assert_eq!(event_attr(trade, "user"), "alice");
+    assert_eq!(event_attr(trade, "market_index"), "1");
+    assert_eq!(event_attr(trade, "direction"), "long");
+    assert_eq!(event_attr(trade, "quote_asset_amount"), "50000000");
+    assert_eq!(event_attr(trade, "fee"), "50000");
+    assert_eq!(event_attr(trade, "liquidation"), "false");
+    let snapshot = &res.events[1];
+    assert_eq!(event_attr(snapshot, "market_index"), "1");
+    assert_eq!(
+        event_attr(snapshot, "base_asset_amount"),
+        event_attr(trade, "base_asset_amount")
+    );
+
+    let msg = ExecuteMsg::ClosePosition {
+        market_index: 1,
+        subaccount: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    assert_eq!(event_types(&res), vec!["trade"]);
+    assert_eq!(event_attr(&res.events[0], "direction"), "short");
+
+    set_vault_balances(&mut deps, 1_000_000_000, 0);
+    let res = withdraw(&mut deps, "alice", 10_000_000);
+    assert_eq!(event_types(&res), vec!["withdraw"]);
+    let withdrawal = &res.events[0];
+    assert_eq!(event_attr(withdrawal, "amount"), "10000000");
+    assert_eq!(
+        event_attr(withdrawal, "cumulative_deposits_before"),
+        "100000000"
+    );
+}
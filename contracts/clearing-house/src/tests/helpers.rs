@@ -0,0 +1,274 @@
+use crate::contract::{execute, instantiate, query};
+use crate::error::ContractError;
+use crate::package::execute::{ExecuteMsg, InstantiateMsg};
+use crate::package::helper::{VaultBalanceResponse, VaultInterface, VaultQueryInterface};
+use crate::package::history::{HistoryExecuteMsg, HistoryRecord};
+use crate::package::oracle::{OracleQueryMsg, PriceResponse};
+use crate::package::queries::QueryMsg;
+use crate::package::response::SubaccountResponse;
+use crate::states::constants::{AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION};
+use crate::states::user::POSITIONS;
+
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    coins, from_binary, from_slice, to_binary, Addr, ContractResult, CosmosMsg, Empty, OwnedDeps,
+    Querier, QuerierResult, QueryRequest, Response, SystemResult, Uint128, WasmMsg, WasmQuery,
+};
+use cw_storage_plus::U64Key;
+use std::cell::RefCell;
+
+pub const ADMIN: &str = "admin";
+pub const COLLATERAL_VAULT: &str = "cvault";
+pub const INSURANCE_VAULT: &str = "ivault";
+pub const ORACLE: &str = "oracle";
+pub const HISTORY: &str = "history";
+pub const LIQUIDATOR: &str = "liquidator";
+
+// answers the vault balance queries with `balances` and the oracle with `price`
+pub struct TestQuerier {
+    pub base: MockQuerier,
+    pub balances: RefCell<(u128, u128)>,
+    pub price: RefCell<u128>,
+}
+
+impl Querier for TestQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+        let (contract_addr, msg) = match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => (contract_addr, msg),
+            _ => return self.base.raw_query(bin_request),
+        };
+        let res = match contract_addr.as_str() {
+            COLLATERAL_VAULT | INSURANCE_VAULT => {
+                let _: VaultQueryInterface = from_binary(msg).unwrap();
+                let (collateral, insurance) = *self.balances.borrow();
+                let balance = if contract_addr == COLLATERAL_VAULT {
+                    collateral
+                } else {
+                    insurance
+                };
+                to_binary(&VaultBalanceResponse {
+                    balance: Uint128::from(balance),
+                })
+            }
+            ORACLE => {
+                let OracleQueryMsg::Price { asset } = from_binary(msg).unwrap();
+                to_binary(&PriceResponse {
+                    asset,
+                    price: Uint128::from(*self.price.borrow()),
+                    last_updated: mock_env().block.time.seconds(),
+                })
+            }
+            _ => return self.base.raw_query(bin_request),
+        };
+        SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+    }
+}
+
+pub type TestDeps = OwnedDeps<MockStorage, MockApi, TestQuerier>;
+
+pub fn setup() -> TestDeps {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: TestQuerier {
+            base: MockQuerier::new(&[]),
+            balances: RefCell::new((0, 0)),
+            price: RefCell::new(MARK_PRICE_PRECISION.u128()),
+        },
+    };
+    let msg = InstantiateMsg {
+        collateral_vault: COLLATERAL_VAULT.to_string(),
+        insurance_vault: INSURANCE_VAULT.to_string(),
+        history_contract: HISTORY.to_string(),
+        admin_controls_prices: true,
+        oracle: ORACLE.to_string(),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    deps
+}
+
+pub fn set_vault_balances(deps: &mut TestDeps, collateral: u128, insurance: u128) {
+    *deps.querier.balances.borrow_mut() = (collateral, insurance);
+}
+
+// a market priced at 1 on both the amm and the oracle
+pub fn init_market(deps: &mut TestDeps, market_index: u64) {
+    let reserve = AMM_RESERVE_PRECISION * Uint128::from(1_000_000u64);
+    let msg = ExecuteMsg::InitializeMarket {
+        market_index,
+        market_name: format!("market{}", market_index),
+        amm_base_asset_reserve: reserve,
+        amm_quote_asset_reserve: reserve,
+        amm_periodicity: 3600,
+        amm_peg_multiplier: PEG_PRECISION,
+        oracle_source_code: 0,
+        margin_ratio_initial: 2000,
+        margin_ratio_partial: 625,
+        margin_ratio_maintenance: 500,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+}
+
+// moves the amm of the market and the oracle to `price`, given in MARK_PRICE_PRECISION
+pub fn set_price(deps: &mut TestDeps, market_index: u64, price: u128) {
+    let reserve = AMM_RESERVE_PRECISION * Uint128::from(1_000_000u64);
+    let msg = ExecuteMsg::MoveAMMPrice {
+        base_asset_reserve: reserve,
+        quote_asset_reserve: reserve.multiply_ratio(price, MARK_PRICE_PRECISION),
+        market_index,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    set_oracle_price(deps, price);
+}
+
+pub fn set_oracle_price(deps: &mut TestDeps, price: u128) {
+    *deps.querier.price.borrow_mut() = price;
+}
+
+pub fn open_position(
+    deps: &mut TestDeps,
+    user: &str,
+    is_direction_long: bool,
+    quote_asset_amount: u128,
+    market_index: u64,
+) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::OpenPosition {
+        is_direction_long,
+        quote_asset_amount: Uint128::from(quote_asset_amount),
+        market_index,
+        limit_price: None,
+        subaccount: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(user, &[]), msg)
+}
+
+pub fn liquidate(
+    deps: &mut TestDeps,
+    user: &str,
+    market_index: u64,
+) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::Liquidate {
+        user: user.to_string(),
+        market_index,
+        subaccount: None,
+        liquidator_subaccount: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(LIQUIDATOR, &[]), msg)
+}
+
+pub fn deposit(deps: &mut TestDeps, user: &str, amount: u64) -> Response {
+    let msg = ExecuteMsg::DepositCollateral {
+        amount,
+        referrer: None,
+        subaccount: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(user, &coins(amount as u128, "uusd")),
+        msg,
+    )
+    .unwrap()
+}
+
+pub fn withdraw(deps: &mut TestDeps, user: &str, amount: u64) -> Response {
+    let msg = ExecuteMsg::WithdrawCollateral {
+        amount,
+        subaccount: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(user, &[]), msg).unwrap()
+}
+
+// books the vault withdrawals and deposits in the response on the sub-ledgers the querier
+// answers with, like the vaults a deposit only credits the funds it carries
+pub fn apply_vault_messages(deps: &mut TestDeps, res: &Response) {
+    let mut balances = deps.querier.balances.borrow_mut();
+    for m in &res.messages {
+        if let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds,
+        }) = &m.msg
+        {
+            let ledger = match contract_addr.as_str() {
+                COLLATERAL_VAULT => &mut balances.0,
+                INSURANCE_VAULT => &mut balances.1,
+                _ => continue,
+            };
+            match from_binary(msg) {
+                Ok(VaultInterface::Withdraw { amount, .. }) => *ledger -= amount.u128(),
+                Ok(VaultInterface::Deposit {}) => {
+                    *ledger += funds.iter().map(|c| c.amount.u128()).sum::<u128>()
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub fn vault_balances(deps: &TestDeps) -> (u128, u128) {
+    *deps.querier.balances.borrow()
+}
+
+// (recipient, amount) of every collateral vault withdrawal in the response
+pub fn vault_withdrawals(res: &Response) -> Vec<(Addr, Uint128)> {
+    res.messages
+        .iter()
+        .filter_map(|m| match &m.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) if contract_addr == COLLATERAL_VAULT => match from_binary(msg) {
+                Ok(VaultInterface::Withdraw { to_address, amount }) => Some((to_address, amount)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+// (subaccount, collateral) of every subaccount of the wallet
+pub fn subaccounts(deps: &TestDeps, user: &str) -> Vec<(u64, u128)> {
+    let msg = QueryMsg::GetUserSubaccounts {
+        user_address: user.to_string(),
+        start_after: None,
+        limit: None,
+    };
+    let res: Vec<SubaccountResponse> =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    res.iter()
+        .map(|s| (s.subaccount, s.collateral.u128()))
+        .collect()
+}
+
+// the records of the history batch in the response, in the order they were written
+pub fn history_records(res: &Response) -> Vec<HistoryRecord> {
+    res.messages
+        .iter()
+        .find_map(|m| match &m.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) if contract_addr == HISTORY => match from_binary(msg) {
+                Ok(HistoryExecuteMsg::RecordBatch { records }) => Some(records),
+                _ => None,
+            },
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+// the base asset amount of the position, 0 once it is closed
+pub fn base_asset_amount(deps: &TestDeps, user: &str, market_index: u64) -> i128 {
+    let key = (&Addr::unchecked(user), U64Key::new(market_index));
+    POSITIONS
+        .may_load(&deps.storage, key)
+        .unwrap()
+        .map_or(0, |p| p.base_asset_amount.i128())
+}
+
+pub fn attr<'a>(res: &'a Response, key: &str) -> Option<&'a str> {
+    res.attributes
+        .iter()
+        .find(|a| a.key == key)
+        .map(|a| a.value.as_str())
+}
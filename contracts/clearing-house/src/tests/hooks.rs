@@ -0,0 +1,151 @@
+use super::helpers::*;
+use crate::contract::{execute, reply};
+use crate::error::ContractError;
+use crate::package::execute::ExecuteMsg;
+use crate::package::hooks::{HookExecuteMsg, HookPayload, HOOK_REPLY_ID};
+use crate::states::constants::{DEFAULT_HOOK_GAS_LIMIT, MAX_HOOKS};
+use crate::states::hooks::HookEvent;
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{
+    from_binary, ContractResult, CosmosMsg, Reply, ReplyOn, Response, SubMsg, WasmMsg,
+};
+
+fn add_hook(
+    deps: &mut TestDeps,
+    sender: &str,
+    contract: &str,
+    events: Vec<HookEvent>,
+    revert_on_error: bool,
+) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::AddHook {
+        contract: contract.to_string(),
+        events,
+        revert_on_error,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+}
+
+// the hook submessages of a response, the history batch goes to HISTORY
+fn hook_calls(res: &Response) -> Vec<(&str, &SubMsg, HookPayload)> {
+    res.messages
+        .iter()
+        .filter_map(|sub| match &sub.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) if contract_addr != HISTORY => {
+                let HookExecuteMsg::ClearingHouseHook(payload) = from_binary(msg).unwrap();
+                Some((contract_addr.as_str(), sub, payload))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn hooks_get_each_subscribed_record_and_only_soft_hooks_are_capped() {
+    let mut deps = setup();
+    init_market(&mut deps, 1);
+    deposit(&mut deps, "alice", 100_000_000);
+
+    let res = add_hook(&mut deps, "alice", "strict", vec![HookEvent::Trade], true);
+    assert!(matches!(res, Err(ContractError::Unauthorized {})));
+    add_hook(&mut deps, ADMIN, "strict", vec![HookEvent::Trade], true).unwrap();
+    let events = vec![HookEvent::Trade, HookEvent::Trade, HookEvent::Liquidation];
+    add_hook(&mut deps, ADMIN, "soft", events, false).unwrap();
+    add_hook(
+        &mut deps,
+        ADMIN,
+        "funding",
+        vec![HookEvent::FundingPayment],
+        false,
+    )
+    .unwrap();
+
+    let res = open_position(&mut deps, "alice", true, 50_000_000, 1).unwrap();
+    let calls = hook_calls(&res);
+    assert_eq!(calls.len(), 2);
+    // hooks are called in address order, a soft one replies on error and gets the gas cap
+    let (contract, sub, payload) = &calls[0];
+    assert_eq!(*contract, "soft");
+    assert_eq!(sub.reply_on, ReplyOn::Error);
+    assert_eq!(sub.id, HOOK_REPLY_ID);
+    assert_eq!(sub.gas_limit, Some(DEFAULT_HOOK_GAS_LIMIT));
+    assert!(matches!(
+        payload,
+        HookPayload::Trade { user, market_index: 1, liquidation: false, .. } if user.as_str() == "alice"
+    ));
+    // a reverting hook runs inside the transaction with no reply and no gas cap
+    let (contract, sub, _) = &calls[1];
+    assert_eq!(*contract, "strict");
+    assert_eq!(sub.reply_on, ReplyOn::Never);
+    assert_eq!(sub.gas_limit, None);
+
+    let msg = ExecuteMsg::UpdateHookGasLimit { gas_limit: 50_000 };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    );
+    assert!(matches!(res, Err(ContractError::Unauthorized {})));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let res = open_position(&mut deps, "alice", false, 10_000_000, 1).unwrap();
+    let calls = hook_calls(&res);
+    assert_eq!(calls[0].1.gas_limit, Some(50_000));
+    assert_eq!(calls[1].1.gas_limit, None);
+
+    // a failed soft hook is logged by the reply and the transaction goes on
+    let failed = Reply {
+        id: HOOK_REPLY_ID,
+        result: ContractResult::Err("out of gas".to_string()),
+    };
+    let res = reply(deps.as_mut(), mock_env(), failed).unwrap();
+    assert_eq!(attr(&res, "method"), Some("hook_skipped"));
+    assert_eq!(attr(&res, "error"), Some("out of gas"));
+    let unknown = Reply {
+        id: HOOK_REPLY_ID + 1,
+        result: ContractResult::Err("error".to_string()),
+    };
+    let res = reply(deps.as_mut(), mock_env(), unknown);
+    assert!(matches!(res, Err(ContractError::UnknownReplyId { .. })));
+}
+
+#[test]
+fn at_most_max_hooks_can_be_registered() {
+    let mut deps = setup();
+    for i in 0..MAX_HOOKS {
+        let contract = format!("hook{}", i);
+        add_hook(&mut deps, ADMIN, &contract, vec![HookEvent::Trade], false).unwrap();
+    }
+    let res = add_hook(&mut deps, ADMIN, "extra", vec![HookEvent::Trade], false);
+    assert!(matches!(res, Err(ContractError::TooManyHooks { max }) if max == MAX_HOOKS));
+    // replacing a registration doesn't take a new slot
+    add_hook(
+        &mut deps,
+        ADMIN,
+        "hook0",
+        vec![HookEvent::Liquidation],
+        true,
+    )
+    .unwrap();
+
+    let remove = |contract: &str| ExecuteMsg::RemoveHook {
+        contract: contract.to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        remove("hook0"),
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        remove("hook0"),
+    );
+    assert!(matches!(res, Err(ContractError::HookNotFound {})));
+    add_hook(&mut deps, ADMIN, "extra", vec![HookEvent::Trade], false).unwrap();
+}
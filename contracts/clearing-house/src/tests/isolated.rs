@@ -0,0 +1,166 @@
+use super::helpers::*;
+use crate::contract::execute;
+use crate::error::ContractError;
+use crate::package::execute::ExecuteMsg;
+use crate::package::history::HistoryRecord;
+use crate::states::constants::{AMM_TO_QUOTE_PRECISION_RATIO_I128, MARK_PRICE_PRECISION};
+use crate::states::user::{ISOLATED_COLLATERAL, USERS};
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{Addr, Env, Response};
+use cw_storage_plus::U64Key;
+
+fn add_margin(
+    deps: &mut TestDeps,
+    user: &str,
+    market_index: u64,
+    amount: u64,
+) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::AddIsolatedMargin {
+        market_index,
+        amount,
+        subaccount: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(user, &[]), msg)
+}
+
+fn remove_margin(
+    deps: &mut TestDeps,
+    user: &str,
+    market_index: u64,
+    amount: u64,
+) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::RemoveIsolatedMargin {
+        market_index,
+        amount,
+        subaccount: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(user, &[]), msg)
+}
+
+fn later(seconds: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(seconds);
+    env
+}
+
+fn collateral(deps: &TestDeps, user: &str) -> u128 {
+    USERS
+        .load(&deps.storage, &Addr::unchecked(user))
+        .unwrap()
+        .collateral
+        .u128()
+}
+
+fn isolated(deps: &TestDeps, user: &str, market_index: u64) -> Option<u128> {
+    let key = (&Addr::unchecked(user), U64Key::new(market_index));
+    ISOLATED_COLLATERAL
+        .may_load(&deps.storage, key)
+        .unwrap()
+        .map(|c| c.u128())
+}
+
+// the funding settled on the market, in quote precision
+fn funding_paid(res: &Response, market_index: u64) -> i128 {
+    history_records(res)
+        .iter()
+        .find_map(|r| match r {
+            HistoryRecord::FundingPayment(f) if f.market_index == market_index => {
+                Some(f.funding_payment.i128() / AMM_TO_QUOTE_PRECISION_RATIO_I128.u128() as i128)
+            }
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn setup_markets() -> TestDeps {
+    let mut deps = setup();
+    // margin checks walk markets 1..markets_length, so market 2 needs a successor
+    for market_index in 1..=3 {
+        init_market(&mut deps, market_index);
+    }
+    deps
+}
+
+#[test]
+fn isolated_margin_is_added_and_removed_against_its_own_bucket() {
+    let mut deps = setup_markets();
+    deposit(&mut deps, "alice", 200_000_000);
+    add_margin(&mut deps, "alice", 1, 50_000_000).unwrap();
+    assert_eq!(collateral(&deps, "alice"), 150_000_000);
+    assert_eq!(isolated(&deps, "alice", 1), Some(50_000_000));
+
+    // fees are paid by the bucket the market belongs to
+    open_position(&mut deps, "alice", true, 100_000_000, 1).unwrap();
+    assert_eq!(isolated(&deps, "alice", 1), Some(49_900_000));
+    assert_eq!(collateral(&deps, "alice"), 150_000_000);
+    open_position(&mut deps, "alice", true, 100_000_000, 2).unwrap();
+    assert_eq!(isolated(&deps, "alice", 1), Some(49_900_000));
+    assert_eq!(collateral(&deps, "alice"), 149_900_000);
+
+    // an open cross position can't be moved into a bucket of its own
+    let res = add_margin(&mut deps, "alice", 2, 1_000_000);
+    assert!(matches!(res, Err(ContractError::CrossPositionOpen)));
+    let res = remove_margin(&mut deps, "alice", 2, 1_000_000);
+    assert!(matches!(res, Err(ContractError::PositionNotIsolated)));
+
+    remove_margin(&mut deps, "alice", 1, 20_000_000).unwrap();
+    assert_eq!(isolated(&deps, "alice", 1), Some(29_900_000));
+    assert_eq!(collateral(&deps, "alice"), 169_900_000);
+    // the isolated position is margined against its bucket alone
+    let res = remove_margin(&mut deps, "alice", 1, 10_000_000);
+    assert!(matches!(res, Err(ContractError::InsufficientCollateral)));
+}
+
+#[test]
+fn funding_is_settled_into_the_bucket_of_each_market() {
+    let mut deps = setup_markets();
+    deposit(&mut deps, "alice", 200_000_000);
+    add_margin(&mut deps, "alice", 1, 50_000_000).unwrap();
+    open_position(&mut deps, "alice", true, 100_000_000, 1).unwrap();
+    open_position(&mut deps, "alice", true, 100_000_000, 2).unwrap();
+
+    // with the oracle under the mark longs pay funding on both markets, resetting the oracle twap
+    // to the mark keeps the oracle from reading as too volatile
+    set_oracle_price(&mut deps, MARK_PRICE_PRECISION.u128() * 99 / 100);
+    for market_index in 1..=2 {
+        let msg = ExecuteMsg::ResetAMMOracleTwap { market_index };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        let msg = ExecuteMsg::UpdateFundingRate { market_index };
+        execute(deps.as_mut(), later(7_200), mock_info(ADMIN, &[]), msg).unwrap();
+    }
+    let msg = ExecuteMsg::SettleFundingPayment { subaccount: None };
+    let res = execute(deps.as_mut(), later(7_200), mock_info("alice", &[]), msg).unwrap();
+    let isolated_funding = funding_paid(&res, 1);
+    let cross_funding = funding_paid(&res, 2);
+    assert!(isolated_funding < 0 && cross_funding < 0);
+    assert_eq!(
+        isolated(&deps, "alice", 1),
+        Some((49_900_000 + isolated_funding) as u128)
+    );
+    assert_eq!(
+        collateral(&deps, "alice"),
+        (149_900_000 + cross_funding) as u128
+    );
+}
+
+#[test]
+fn liquidation_closes_only_the_bucket_that_is_under_margin() {
+    let mut deps = setup_markets();
+    deposit(&mut deps, "alice", 200_000_000);
+    deposit(&mut deps, LIQUIDATOR, 1_000_000);
+    add_margin(&mut deps, "alice", 1, 30_000_000).unwrap();
+    open_position(&mut deps, "alice", true, 100_000_000, 1).unwrap();
+    open_position(&mut deps, "alice", true, 100_000_000, 2).unwrap();
+
+    // a 30% drop wipes out the isolated collateral, the cross bucket has plenty to spare
+    set_price(&mut deps, 1, MARK_PRICE_PRECISION.u128() * 70 / 100);
+    let res = liquidate(&mut deps, "alice", 2);
+    assert!(matches!(res, Err(ContractError::SufficientCollateral)));
+    liquidate(&mut deps, "alice", 1).unwrap();
+    assert_eq!(base_asset_amount(&deps, "alice", 1), 0);
+    assert!(base_asset_amount(&deps, "alice", 2) > 0);
+    // the loss and the liquidation fee come out of the bucket alone
+    assert_eq!(isolated(&deps, "alice", 1), Some(0));
+    assert_eq!(collateral(&deps, "alice"), 169_900_000);
+}
@@ -0,0 +1,24 @@
+use super::helpers::*;
+use crate::states::constants::MARK_PRICE_PRECISION;
+
+#[test]
+fn full_liquidation_closes_every_market_of_the_account() {
+    let mut deps = setup();
+    // margin checks walk markets 1..markets_length, so market 2 needs a successor
+    for market_index in 1..=3 {
+        init_market(&mut deps, market_index);
+    }
+    deposit(&mut deps, "bob", 50_000_000);
+    deposit(&mut deps, LIQUIDATOR, 1_000_000);
+    open_position(&mut deps, "bob", true, 100_000_000, 1).unwrap();
+    open_position(&mut deps, "bob", true, 100_000_000, 2).unwrap();
+
+    // a 25% drop on both markets takes the whole collateral
+    for market_index in 1..=2 {
+        set_price(&mut deps, market_index, MARK_PRICE_PRECISION.u128() * 75 / 100);
+    }
+    // the liquidator names one market, each position is closed in its own market
+    liquidate(&mut deps, "bob", 2).unwrap();
+    assert_eq!(base_asset_amount(&deps, "bob", 1), 0);
+    assert_eq!(base_asset_amount(&deps, "bob", 2), 0);
+}
@@ -0,0 +1,134 @@
+use super::helpers::*;
+use crate::contract::{execute, migrate};
+use crate::error::ContractError;
+use crate::package::execute::{ExecuteMsg, MigrateMsg};
+use crate::package::number::Number128;
+use crate::states::user::{Position, User, POSITIONS, POSITIONS_MIGRATION, POSITIONS_V020, USERS};
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{coins, Addr, Uint128};
+use cw_storage_plus::U64Key;
+
+fn legacy_user() -> User {
+    User {
+        collateral: Uint128::from(1_000u64),
+        cumulative_deposits: Uint128::from(1_000u64),
+        total_fee_paid: Uint128::zero(),
+        total_token_discount: Uint128::zero(),
+        total_referral_reward: Uint128::zero(),
+        total_referee_discount: Uint128::zero(),
+        referrer: None,
+    }
+}
+
+fn legacy_position(market_index: u64) -> Position {
+    Position {
+        market_index,
+        base_asset_amount: Number128::new(10),
+        quote_asset_amount: Uint128::from(10u64),
+        last_cumulative_funding_rate: Number128::zero(),
+        last_cumulative_repeg_rebate: Uint128::zero(),
+        last_funding_rate_ts: 0,
+        order_length: 0,
+    }
+}
+
+// `users` accounts with a position in markets 2 and 10, stored the way 0.2.0 left them
+fn setup_v020(users: usize) -> (TestDeps, Vec<Addr>) {
+    let mut deps = setup();
+    let addrs = (0..users)
+        .map(|i| Addr::unchecked(format!("user{:02}", i)))
+        .collect::<Vec<Addr>>();
+    for user in addrs.iter() {
+        USERS
+            .save(deps.as_mut().storage, user, &legacy_user())
+            .unwrap();
+        for market_index in [2u64, 10].iter().copied() {
+            POSITIONS_V020
+                .save(
+                    deps.as_mut().storage,
+                    (user, market_index.to_string()),
+                    &legacy_position(market_index),
+                )
+                .unwrap();
+        }
+    }
+    cw2::set_contract_version(deps.as_mut().storage, "crates.io:clearing-house", "0.2.0").unwrap();
+    (deps, addrs)
+}
+
+// the markets the user's positions are stored under with the big-endian key
+fn migrated(deps: &TestDeps, user: &Addr) -> Vec<u64> {
+    [2u64, 10]
+        .iter()
+        .copied()
+        .filter(|market_index| {
+            let key = (user, U64Key::new(*market_index));
+            POSITIONS.has(deps.as_ref().storage, key)
+        })
+        .collect()
+}
+
+fn migrate_positions(deps: &mut TestDeps, limit: Option<u32>) -> Result<String, ContractError> {
+    let msg = ExecuteMsg::MigratePositions { limit };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg)?;
+    Ok(attr(&res, "done").unwrap().to_string())
+}
+
+#[test]
+fn migrate_from_0_2_0_rekeys_positions_in_batches() {
+    let (mut deps, users) = setup_v020(35);
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(attr(&res, "from_version"), Some("0.2.0"));
+    assert_eq!(attr(&res, "to_version"), Some("0.4.0"));
+
+    // the first batch moves with the migration, the rest waits behind the cursor
+    for user in users[..30].iter() {
+        assert_eq!(migrated(&deps, user), vec![2, 10]);
+    }
+    for user in users[30..].iter() {
+        assert!(migrated(&deps, user).is_empty());
+    }
+    assert_eq!(
+        POSITIONS_MIGRATION.load(deps.as_ref().storage).unwrap(),
+        Some(users[29].clone())
+    );
+    // every existing account is listed as its wallet's subaccount 0
+    for user in users.iter() {
+        assert_eq!(subaccounts(&deps, user.as_str()), vec![(0, 1_000)]);
+    }
+
+    // positions can't be touched until the migration is done
+    let msg = ExecuteMsg::DepositCollateral {
+        amount: 100,
+        referrer: None,
+        subaccount: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user34", &coins(100, "uusd")),
+        msg,
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::MigrationPending));
+
+    assert_eq!(migrate_positions(&mut deps, Some(3)).unwrap(), "false");
+    assert_eq!(migrated(&deps, &users[32]), vec![2, 10]);
+    assert!(migrated(&deps, &users[33]).is_empty());
+    assert_eq!(migrate_positions(&mut deps, None).unwrap(), "true");
+    for user in users.iter() {
+        assert_eq!(migrated(&deps, user), vec![2, 10]);
+        let legacy = POSITIONS_V020
+            .may_load(deps.as_ref().storage, (user, "2".to_string()))
+            .unwrap();
+        assert!(legacy.is_none());
+    }
+    let err = migrate_positions(&mut deps, None).unwrap_err();
+    assert!(matches!(err, ContractError::NothingToMigrate));
+
+    deposit(&mut deps, "user34", 100);
+    let user = USERS.load(deps.as_ref().storage, &users[34]).unwrap();
+    assert_eq!(user.collateral, Uint128::from(1_100u64));
+    assert_eq!(subaccounts(&deps, "user34"), vec![(0, 1_100)]);
+}
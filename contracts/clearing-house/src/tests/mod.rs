@@ -1 +1,9 @@
-mod test;
\ No newline at end of file
+mod events;
+mod helpers;
+mod hooks;
+mod isolated;
+mod liquidation;
+mod migration;
+mod subaccounts;
+mod vaults;
+mod withdrawal;
@@ -0,0 +1,167 @@
+use super::helpers::*;
+use crate::contract::{execute, query};
+use crate::error::ContractError;
+use crate::package::execute::ExecuteMsg;
+use crate::package::queries::QueryMsg;
+use crate::package::response::{PositionResponse, SubaccountResponse, UserResponse};
+use crate::states::user::{User, USERS};
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{coins, from_binary, Addr, Response, Uint128};
+
+fn deposit_to(deps: &mut TestDeps, user: &str, subaccount: u64, amount: u64) -> Response {
+    let msg = ExecuteMsg::DepositCollateral {
+        amount,
+        referrer: None,
+        subaccount: Some(subaccount),
+    };
+    let info = mock_info(user, &coins(amount as u128, "uusd"));
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap()
+}
+
+fn transfer(
+    deps: &mut TestDeps,
+    user: &str,
+    amount: u64,
+    from_subaccount: u64,
+    to_subaccount: u64,
+) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::TransferCollateral {
+        amount,
+        from_subaccount,
+        to_subaccount,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(user, &[]), msg)
+}
+
+fn user(deps: &TestDeps, user: &str, subaccount: u64) -> Option<UserResponse> {
+    let msg = QueryMsg::GetUser {
+        user_address: user.to_string(),
+        subaccount: Some(subaccount),
+    };
+    from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+}
+
+#[test]
+fn deposits_open_subaccounts_and_transfers_move_collateral_between_them() {
+    let mut deps = setup();
+    init_market(&mut deps, 1);
+    // margin checks walk markets 1..markets_length, so market 1 needs a successor
+    init_market(&mut deps, 2);
+    deposit(&mut deps, "alice", 100_000_000);
+    let res = deposit_to(&mut deps, "alice", 2, 50_000_000);
+    assert_eq!(attr(&res, "method"), Some("try_deposit_collateral"));
+    // a subaccount is its own margin account, named `<wallet>/<n>` in events
+    let event = &res.events[0];
+    assert!(event
+        .attributes
+        .iter()
+        .any(|a| a.key == "user" && a.value == "alice/2"));
+    assert_eq!(
+        subaccounts(&deps, "alice"),
+        vec![(0, 100_000_000), (2, 50_000_000)]
+    );
+    assert!(subaccounts(&deps, "bob").is_empty());
+
+    // transfers open the receiving subaccount and carry the deposits along
+    transfer(&mut deps, "alice", 30_000_000, 0, 5).unwrap();
+    let to = user(&deps, "alice", 5).unwrap();
+    assert_eq!(to.collateral, Uint128::from(30_000_000u64));
+    assert_eq!(to.cumulative_deposits, Uint128::from(30_000_000u64));
+    assert_eq!(
+        subaccounts(&deps, "alice"),
+        vec![(0, 70_000_000), (2, 50_000_000), (5, 30_000_000)]
+    );
+
+    let res = transfer(&mut deps, "alice", 1, 2, 2);
+    assert!(matches!(res, Err(ContractError::SameSubaccount)));
+    let res = transfer(&mut deps, "alice", 0, 0, 2);
+    assert!(matches!(res, Err(ContractError::InsufficientDeposit)));
+    let res = transfer(&mut deps, "alice", 1, 9, 0);
+    assert!(matches!(res, Err(ContractError::UserDoesNotExist)));
+    let res = transfer(&mut deps, "alice", 50_000_001, 2, 0);
+    assert!(matches!(res, Err(ContractError::InsufficientCollateral)));
+    // only the wallet can move its subaccounts' collateral
+    let res = transfer(&mut deps, "bob", 1, 0, 1);
+    assert!(matches!(res, Err(ContractError::UserDoesNotExist)));
+
+    // positions are kept per subaccount and margined against its own collateral
+    let msg = ExecuteMsg::OpenPosition {
+        is_direction_long: true,
+        quote_asset_amount: Uint128::from(100_000_000u64),
+        market_index: 1,
+        limit_price: None,
+        subaccount: Some(2),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    let position = |subaccount: u64| {
+        let msg = QueryMsg::GetUserMarketPosition {
+            user_address: "alice".to_string(),
+            index: 1,
+            subaccount: Some(subaccount),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<Option<PositionResponse>>(&res).unwrap()
+    };
+    assert!(position(0).is_none());
+    assert!(position(2).is_some());
+    transfer(&mut deps, "alice", 25_000_000, 2, 0).unwrap();
+    assert_eq!(subaccounts(&deps, "alice")[0], (0, 95_000_000));
+    let res = transfer(&mut deps, "alice", 10_000_000, 2, 0);
+    assert!(matches!(res, Err(ContractError::InsufficientCollateral)));
+
+    // withdrawals from a subaccount are paid to the wallet
+    set_vault_balances(&mut deps, 1_000_000_000, 0);
+    let msg = ExecuteMsg::WithdrawCollateral {
+        amount: 10_000_000,
+        subaccount: Some(5),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    assert_eq!(
+        vault_withdrawals(&res),
+        vec![(Addr::unchecked("alice"), Uint128::from(10_000_000u64))]
+    );
+    assert_eq!(
+        user(&deps, "alice", 5).unwrap().collateral,
+        Uint128::from(20_000_000u64)
+    );
+}
+
+#[test]
+fn subaccount_listing_pages_and_includes_legacy_accounts() {
+    let mut deps = setup();
+    // an account opened before subaccounts has no listing entry of its own
+    let legacy = User {
+        collateral: Uint128::from(7u64),
+        cumulative_deposits: Uint128::from(7u64),
+        total_fee_paid: Uint128::zero(),
+        total_token_discount: Uint128::zero(),
+        total_referral_reward: Uint128::zero(),
+        total_referee_discount: Uint128::zero(),
+        referrer: None,
+    };
+    USERS
+        .save(deps.as_mut().storage, &Addr::unchecked("alice"), &legacy)
+        .unwrap();
+    for subaccount in 1..=3 {
+        deposit_to(&mut deps, "alice", subaccount, 10);
+    }
+    assert_eq!(
+        subaccounts(&deps, "alice"),
+        vec![(0, 7), (1, 10), (2, 10), (3, 10)]
+    );
+
+    let page = |start_after: Option<u64>, limit: u32| {
+        let msg = QueryMsg::GetUserSubaccounts {
+            user_address: "alice".to_string(),
+            start_after,
+            limit: Some(limit),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let page: Vec<SubaccountResponse> = from_binary(&res).unwrap();
+        page.iter().map(|s| s.subaccount).collect::<Vec<u64>>()
+    };
+    assert_eq!(page(None, 2), vec![0, 1]);
+    assert_eq!(page(Some(1), 2), vec![2, 3]);
+    assert!(page(None, 0).is_empty());
+}
@@ -0,0 +1,54 @@
+use super::helpers::*;
+use crate::contract::execute;
+use crate::package::execute::ExecuteMsg;
+use crate::package::history::HistoryRecord;
+use crate::states::constants::MARK_PRICE_PRECISION;
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+
+#[test]
+fn funds_moved_between_the_vaults_are_credited_to_the_receiving_ledger() {
+    let mut deps = setup();
+    // margin checks walk markets 1..markets_length, so market 2 needs a successor
+    for market_index in 1..=3 {
+        init_market(&mut deps, market_index);
+    }
+    let res = deposit(&mut deps, "bob", 50_000_000);
+    apply_vault_messages(&mut deps, &res);
+    let res = deposit(&mut deps, LIQUIDATOR, 1_000_000);
+    apply_vault_messages(&mut deps, &res);
+    assert_eq!(vault_balances(&deps), (51_000_000, 0));
+    open_position(&mut deps, "bob", true, 100_000_000, 1).unwrap();
+    open_position(&mut deps, "bob", true, 100_000_000, 2).unwrap();
+
+    // the insurance fund's share of the liquidation fee leaves one ledger and lands in the other
+    for market_index in 1..=2 {
+        set_price(
+            &mut deps,
+            market_index,
+            MARK_PRICE_PRECISION.u128() * 78 / 100,
+        );
+    }
+    let res = liquidate(&mut deps, "bob", 1).unwrap();
+    apply_vault_messages(&mut deps, &res);
+    let fee_to_insurance_fund = history_records(&res)
+        .iter()
+        .find_map(|r| match r {
+            HistoryRecord::Liquidation(l) => Some(l.fee_to_insurance_fund as u128),
+            _ => None,
+        })
+        .unwrap();
+    assert!(fee_to_insurance_fund > 0);
+    let (collateral, insurance) = vault_balances(&deps);
+    assert_eq!(insurance, fee_to_insurance_fund);
+    assert_eq!(collateral, 51_000_000 - fee_to_insurance_fund);
+
+    // a market top-up moves the funds back the same way
+    let msg = ExecuteMsg::WithdrawFromInsuranceVaultToMarket {
+        market_index: 1,
+        amount: insurance as u64,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    apply_vault_messages(&mut deps, &res);
+    assert_eq!(vault_balances(&deps), (51_000_000, 0));
+}
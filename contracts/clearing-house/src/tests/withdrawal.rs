@@ -0,0 +1,180 @@
+use crate::contract::{execute, query};
+use crate::controller::withdrawal::{process_withdrawal_queue, queue_withdrawal_claim};
+use crate::package::execute::ExecuteMsg;
+use crate::package::queries::QueryMsg;
+use crate::package::response::WithdrawalClaimResponse;
+use crate::states::state::STATE;
+use crate::states::withdrawal::WITHDRAWAL_QUEUE;
+use crate::tests::helpers::*;
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{from_binary, Addr, Uint128};
+
+fn claims(deps: &TestDeps, user: &str) -> Vec<WithdrawalClaimResponse> {
+    let msg = QueryMsg::GetUserWithdrawalClaims {
+        user_address: user.to_string(),
+        start_after: None,
+        limit: None,
+    };
+    from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+}
+
+fn user_name(i: usize) -> String {
+    format!("user{:02}", i)
+}
+
+#[test]
+fn withdrawal_is_paid_directly_when_queue_is_empty() {
+    let mut deps = setup();
+    deposit(&mut deps, "alice", 100);
+    set_vault_balances(&mut deps, 1_000, 0);
+
+    let res = withdraw(&mut deps, "alice", 60);
+    assert_eq!(
+        vault_withdrawals(&res),
+        vec![(Addr::unchecked("alice"), Uint128::from(60u128))]
+    );
+    assert_eq!(attr(&res, "claim_id"), None);
+}
+
+#[test]
+fn withdrawal_waits_behind_claims_left_after_the_per_tx_limit() {
+    let mut deps = setup();
+    for i in 0..13 {
+        deposit(&mut deps, &user_name(i), 100);
+    }
+
+    // the vaults are empty, so the first twelve withdrawals are queued in full
+    for i in 0..12 {
+        let res = withdraw(&mut deps, &user_name(i), 100);
+        assert_eq!(attr(&res, "claim_id"), Some(i.to_string().as_str()));
+        assert!(vault_withdrawals(&res).is_empty());
+    }
+
+    // enough liquidity for everyone, but only ten claims are paid per transaction
+    set_vault_balances(&mut deps, 10_000, 0);
+    let res = withdraw(&mut deps, &user_name(12), 100);
+    let paid = vault_withdrawals(&res);
+    assert_eq!(paid.len(), 10);
+    for (i, (to, amount)) in paid.iter().enumerate() {
+        assert_eq!(to, &Addr::unchecked(user_name(i)));
+        assert_eq!(amount, &Uint128::from(100u128));
+    }
+    // claims 10 and 11 are still waiting, so the new withdrawal queues behind them
+    assert_eq!(attr(&res, "claim_id"), Some("12"));
+    assert_eq!(attr(&res, "queued_amount"), Some("100"));
+    assert_eq!(
+        claims(&deps, &user_name(12))[0].outstanding,
+        Uint128::from(100u128)
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::ProcessWithdrawalQueue {},
+    )
+    .unwrap();
+    let paid: Vec<Addr> = vault_withdrawals(&res)
+        .into_iter()
+        .map(|(to, _)| to)
+        .collect();
+    assert_eq!(
+        paid,
+        vec![
+            Addr::unchecked(user_name(10)),
+            Addr::unchecked(user_name(11)),
+            Addr::unchecked(user_name(12)),
+        ]
+    );
+    assert!(claims(&deps, &user_name(12)).is_empty());
+
+    // with the queue drained withdrawals are paid directly again
+    deposit(&mut deps, &user_name(12), 50);
+    let res = withdraw(&mut deps, &user_name(12), 50);
+    assert_eq!(attr(&res, "claim_id"), None);
+    assert_eq!(vault_withdrawals(&res).len(), 1);
+}
+
+#[test]
+fn insurance_fund_claims_are_not_paid_from_the_insurance_vault() {
+    let mut deps = setup();
+    let state = STATE.load(&deps.storage).unwrap();
+    queue_withdrawal_claim(
+        &mut deps.storage,
+        &state.insurance_vault,
+        Uint128::from(50u128),
+        0,
+    )
+    .unwrap();
+
+    let (messages, collateral_left, insurance_left) = process_withdrawal_queue(
+        &mut deps.storage,
+        &state,
+        &mock_env().contract.address,
+        Uint128::zero(),
+        Uint128::from(1_000u128),
+    )
+    .unwrap();
+    assert!(messages.is_empty());
+    assert_eq!(collateral_left, Uint128::zero());
+    assert_eq!(insurance_left, Uint128::from(1_000u128));
+
+    let (messages, collateral_left, _) = process_withdrawal_queue(
+        &mut deps.storage,
+        &state,
+        &mock_env().contract.address,
+        Uint128::from(80u128),
+        Uint128::from(1_000u128),
+    )
+    .unwrap();
+    // withdrawn to the clearing house and deposited into the insurance vault
+    assert_eq!(messages.len(), 2);
+    assert_eq!(collateral_left, Uint128::from(30u128));
+}
+
+#[test]
+fn user_claims_are_not_held_up_by_an_unpayable_insurance_fund_claim() {
+    let mut deps = setup();
+    let state = STATE.load(&deps.storage).unwrap();
+    let alice = Addr::unchecked("alice");
+    queue_withdrawal_claim(
+        &mut deps.storage,
+        &state.insurance_vault,
+        Uint128::from(50u128),
+        0,
+    )
+    .unwrap();
+    queue_withdrawal_claim(&mut deps.storage, &alice, Uint128::from(40u128), 0).unwrap();
+
+    // the collateral vault is empty, so alice is paid by the insurance vault past the fee
+    let (messages, _, insurance_left) = process_withdrawal_queue(
+        &mut deps.storage,
+        &state,
+        &mock_env().contract.address,
+        Uint128::zero(),
+        Uint128::from(1_000u128),
+    )
+    .unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(insurance_left, Uint128::from(960u128));
+    assert!(claims(&deps, "alice").is_empty());
+    // the fee keeps its place at the head of the queue
+    let queue = WITHDRAWAL_QUEUE.load(&deps.storage).unwrap();
+    assert_eq!(
+        (queue.head, queue.total_outstanding),
+        (0, Uint128::from(50u128))
+    );
+
+    let (messages, _, _) = process_withdrawal_queue(
+        &mut deps.storage,
+        &state,
+        &mock_env().contract.address,
+        Uint128::from(80u128),
+        Uint128::zero(),
+    )
+    .unwrap();
+    assert_eq!(messages.len(), 2);
+    let queue = WITHDRAWAL_QUEUE.load(&deps.storage).unwrap();
+    assert_eq!((queue.head, queue.total_outstanding), (2, Uint128::zero()));
+}
@@ -1,26 +1,31 @@
 use crate::controller;
 use crate::helpers;
-use crate::package::history::HistoryExecuteMsg;
+use crate::package::events::history_events;
+use crate::package::history::{history_batch_message, HistoryRecord};
 use crate::states::constants::*;
 use crate::states::history::*;
 use crate::ContractError;
 
+use crate::states::hooks::{Hook, HookEvent, HOOKS, HOOK_GAS_LIMIT};
 use crate::states::market::{Amm, Market, MARKETS};
 use crate::states::state::OrderState;
 use crate::states::state::State;
 use crate::states::state::FEESTRUCTURE;
 use crate::states::state::ORACLEGUARDRAILS;
 use crate::states::state::ORDERSTATE;
+use crate::states::state::AMM_SNAPSHOT_INTERVAL;
 use crate::states::state::STATE;
 
 use crate::package::helper::addr_validate_to_lower;
+use crate::package::helper::query_vault_balance;
 use crate::package::helper::VaultInterface;
 use crate::package::number::Number128;
 use crate::package::types::OraclePriceData;
 use crate::package::types::{FeeStructure, OracleGuardRails, OracleSource};
 use cosmwasm_std::{
-    to_binary, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, Uint128, WasmMsg,
+    to_binary, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Order, Response, Uint128, WasmMsg,
 };
+use cw_storage_plus::U64Key;
 
 pub fn try_initialize_market(
     mut deps: DepsMut,
@@ -43,7 +48,7 @@ pub fn try_initialize_market(
     if state.admin != _info.sender.clone() {
         return Err(ContractError::Unauthorized {});
     }
-    let existing_market = MARKETS.load(deps.storage, market_index.to_string());
+    let existing_market = MARKETS.load(deps.storage, U64Key::new(market_index));
     if existing_market.is_ok() {
         return Err(ContractError::MarketIndexAlreadyInitialized {});
     }
@@ -111,7 +116,7 @@ pub fn try_initialize_market(
         margin_ratio_maintenance,
         amm: a.clone(),
     };
-    MARKETS.save(deps.storage, market_index.to_string(), &market)?;
+    MARKETS.save(deps.storage, U64Key::new(market_index), &market)?;
     STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
         state.markets_length += 1;
         Ok(state)
@@ -148,7 +153,7 @@ pub fn try_withdraw_fees(
     if state.admin != info.sender.clone() {
         return Err(ContractError::Unauthorized {});
     }
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     // A portion of fees must always remain in protocol to be used to keep markets optimal
     let max_withdraw = market
@@ -179,7 +184,7 @@ pub fn try_withdraw_fees(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
@@ -190,6 +195,7 @@ pub fn try_withdraw_fees(
 
 pub fn try_withdraw_from_insurance_vault_to_market(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     market_index: u64,
     amount: u64,
@@ -199,27 +205,46 @@ pub fn try_withdraw_from_insurance_vault_to_market(
         return Err(ContractError::Unauthorized {});
     }
 
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     market.amm.total_fee_minus_distributions = market
         .amm
         .total_fee_minus_distributions
         .checked_add(Uint128::from(amount))?;
 
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.insurance_vault.to_string(),
-        msg: to_binary(&VaultInterface::Withdraw {
-            to_address: state.collateral_vault.clone(),
-            amount: Uint128::from(amount),
-        })?,
-        funds: vec![],
-    });
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
+
+    let mut messages = controller::withdrawal::market_top_up_messages(
+        &state,
+        &env.contract.address,
+        Uint128::from(amount),
+        market_index,
+    )?;
+    if controller::withdrawal::has_outstanding_claims(deps.storage)? {
+        let collateral_balance = query_vault_balance(
+            &deps.querier,
+            state.collateral_vault.clone(),
+            &env.contract.address,
+        )?;
+        let insurance_balance = query_vault_balance(
+            &deps.querier,
+            state.insurance_vault.clone(),
+            &env.contract.address,
+        )?;
+        let (queue_messages, _, _) = controller::withdrawal::process_withdrawal_queue(
+            deps.storage,
+            &state,
+            &env.contract.address,
+            Uint128::from(collateral_balance).checked_add(Uint128::from(amount))?,
+            Uint128::from(insurance_balance).saturating_sub(Uint128::from(amount)),
+        )?;
+        messages.extend(queue_messages);
+    }
     Ok(Response::new()
-        .add_message(message)
+        .add_messages(messages)
         .add_attribute("method", "try_withdraw_from_insurance_vault_to_market"))
 }
 
@@ -230,13 +255,13 @@ pub fn try_repeg_amm_curve(
     market_index: u64,
 ) -> Result<Response, ContractError> {
     let now = env.block.time.seconds();
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     
     let OraclePriceData {
         price: oracle_price,
         ..
     } = market.amm.get_oracle_price(&mut deps, market_index)?;
-    market = MARKETS.load(deps.storage, market_index.to_string())?;
+    market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     
     let peg_multiplier_before = market.amm.peg_multiplier;
     let base_asset_reserve_before = market.amm.base_asset_reserve;
@@ -274,13 +299,12 @@ pub fn try_repeg_amm_curve(
         oracle_price,
     };
     let state = STATE.load(deps.storage)?;
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordCurve { c })?,
-        funds: vec![],
-    });
+    let records = vec![HistoryRecord::Curve(c)];
+    let events = history_events(&records);
+    let messages = history_batch_message(&state.history_contract, records)?;
     Ok(Response::new()
-        .add_message(message)
+        .add_messages(messages)
+        .add_events(events)
         .add_attribute("method", "try_repeg_amm_curve"))
 }
 
@@ -290,7 +314,7 @@ pub fn try_update_amm_oracle_twap(
     market_index: u64,
 ) -> Result<Response, ContractError> {
     let now = env.block.time.seconds();
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     // todo get_oracle_twap is not defined yet
     let oracle_twap = market.amm.get_oracle_twap()?;
 
@@ -321,7 +345,7 @@ pub fn try_update_amm_oracle_twap(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
 
@@ -334,10 +358,10 @@ pub fn try_reset_amm_oracle_twap(
     market_index: u64,
 ) -> Result<Response, ContractError> {
     let now = env.block.time.seconds();
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let oracle_guard_rails = ORACLEGUARDRAILS.load(deps.storage)?;
     let oracle_price_data = market.amm.get_oracle_price(&mut deps, market_index)?;
-    market = MARKETS.load(deps.storage, market_index.to_string())?;
+    market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
 
     let is_oracle_valid =
         helpers::amm::is_oracle_valid(&market.amm, &oracle_price_data, &oracle_guard_rails)?;
@@ -349,7 +373,7 @@ pub fn try_reset_amm_oracle_twap(
     }
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
     Ok(Response::new().add_attribute("method", "try_reset_amm_oracle_twap"))
@@ -363,7 +387,7 @@ pub fn try_update_funding_rate(
     let now = env.block.time.seconds();
     let funding_paused = STATE.load(deps.storage).unwrap().funding_paused;
     let state = STATE.load(deps.storage)?;
-    let f = controller::funding::update_funding_rate(
+    let (f, snapshot) = controller::funding::update_funding_rate(
         &mut deps,
         market_index,
         now,
@@ -371,13 +395,14 @@ pub fn try_update_funding_rate(
         None,
     )?;
 
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordFundingRate { f: f.unwrap() })?,
-        funds: vec![],
-    });
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.map(HistoryRecord::FundingRate));
+    records.extend(snapshot.map(HistoryRecord::AmmSnapshot));
+    let events = history_events(&records);
+    let messages = history_batch_message(&state.history_contract, records)?;
     Ok(Response::new()
-        .add_message(message)
+        .add_messages(messages)
+        .add_events(events)
         .add_attribute("method", "try_update_funding_rate"))
 }
 
@@ -388,7 +413,7 @@ pub fn try_update_k(
     sqrt_k: Uint128,
 ) -> Result<Response, ContractError> {
     let now = env.block.time.seconds();
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let state = STATE.load(deps.storage)?;
 
     let base_asset_amount_long = Uint128::from(market.base_asset_amount_long.i128().unsigned_abs());
@@ -457,7 +482,7 @@ pub fn try_update_k(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market.clone()) },
     )?;
 
@@ -487,14 +512,13 @@ pub fn try_update_k(
         oracle_price,
     };
 
-    let message = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.to_string(),
-        funds: vec![],
-        msg: to_binary(&HistoryExecuteMsg::RecordCurve { c: c })?,
-    });
+    let records = vec![HistoryRecord::Curve(c)];
+    let events = history_events(&records);
+    let messages = history_batch_message(&state.history_contract, records)?;
 
     Ok(Response::new()
-        .add_message(message)
+        .add_messages(messages)
+        .add_events(events)
         .add_attribute("method", "try_update_k"))
 }
 
@@ -516,10 +540,10 @@ pub fn try_update_margin_ratio(
         margin_ratio_partial,
         margin_ratio_maintenance,
     )?;
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> {
             market.margin_ratio_initial = margin_ratio_initial;
             market.margin_ratio_partial = margin_ratio_partial;
@@ -693,12 +717,12 @@ pub fn try_update_market_oracle(
     if state.admin != info.sender.clone() {
         return Err(ContractError::Unauthorized {});
     }
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     market.amm.oracle = addr_validate_to_lower(deps.api, &oracle)?;
     market.amm.oracle_source = oracle_source;
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
     Ok(Response::new().add_attribute("method", "try_update_market_oracle"))
@@ -748,6 +772,97 @@ pub fn try_update_max_deposit(
     Ok(Response::new().add_attribute("method", "try_max_deposit"))
 }
 
+pub fn try_update_amm_snapshot_interval(
+    deps: DepsMut,
+    info: MessageInfo,
+    interval: u64,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.admin != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    AMM_SNAPSHOT_INTERVAL.save(deps.storage, &interval)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_update_amm_snapshot_interval")
+        .add_attribute("interval", interval.to_string()))
+}
+
+pub fn try_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+    events: Vec<HookEvent>,
+    revert_on_error: bool,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.admin != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let contract = addr_validate_to_lower(deps.api, &contract)?;
+    if !HOOKS.has(deps.storage, &contract) {
+        let registered = HOOKS
+            .keys(deps.storage, None, None, Order::Ascending)
+            .take(MAX_HOOKS as usize)
+            .count();
+        if registered >= MAX_HOOKS as usize {
+            return Err(ContractError::TooManyHooks { max: MAX_HOOKS });
+        }
+    }
+    let mut subscribed: Vec<HookEvent> = vec![];
+    for event in events {
+        if !subscribed.contains(&event) {
+            subscribed.push(event);
+        }
+    }
+    HOOKS.save(
+        deps.storage,
+        &contract,
+        &Hook {
+            contract: contract.clone(),
+            events: subscribed,
+            revert_on_error,
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("method", "try_add_hook")
+        .add_attribute("contract", contract.to_string())
+        .add_attribute("revert_on_error", revert_on_error.to_string()))
+}
+
+pub fn try_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.admin != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let contract = addr_validate_to_lower(deps.api, &contract)?;
+    if !HOOKS.has(deps.storage, &contract) {
+        return Err(ContractError::HookNotFound {});
+    }
+    HOOKS.remove(deps.storage, &contract);
+    Ok(Response::new()
+        .add_attribute("method", "try_remove_hook")
+        .add_attribute("contract", contract.to_string()))
+}
+
+pub fn try_update_hook_gas_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    gas_limit: u64,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.admin != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    HOOK_GAS_LIMIT.save(deps.storage, &gas_limit)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_update_hook_gas_limit")
+        .add_attribute("gas_limit", gas_limit.to_string()))
+}
+
 pub fn try_update_admin(
     deps: DepsMut,
     info: MessageInfo,
@@ -818,10 +933,10 @@ pub fn try_update_market_minimum_quote_asset_trade_size(
     if state.admin != info.sender.clone() {
         return Err(ContractError::Unauthorized {});
     }
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> {
             market.amm.minimum_quote_asset_trade_size = minimum_trade_size;
             Ok(market)
@@ -843,7 +958,7 @@ pub fn try_update_market_minimum_base_asset_trade_size(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |m| -> Result<_, ContractError> {
             match m {
                 Some(mut mr) => {
@@ -885,17 +1000,39 @@ pub fn try_feeding_price(
         return Err(ContractError::Unauthorized {});
     }
 
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     market.amm.last_oracle_price = Number128::new(price.u128() as i128);
     market.amm.last_oracle_price_twap = Number128::new(price.u128() as i128);
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market) },
     )?;
     Ok(Response::new().add_attribute("method", "try_update_oracle_address"))
 }
 
+pub fn try_accept_vaults_clearing_house(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.admin != info.sender.clone() {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for vault in [state.collateral_vault, state.insurance_vault] {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: vault.to_string(),
+            msg: to_binary(&VaultInterface::AcceptClearingHouse {})?,
+            funds: vec![],
+        }));
+    }
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "try_accept_vaults_clearing_house"))
+}
+
 pub fn try_update_history_contract(
     deps: DepsMut,
     info: MessageInfo,
@@ -3,7 +3,9 @@ use std::ops::Div;
 use crate::controller;
 use crate::helpers;
 use crate::helpers::position::calculate_withdrawal_amounts;
-use crate::package::history::HistoryExecuteMsg;
+use crate::package::events::{history_events, liquidation_skip_event};
+use crate::package::history::{history_batch_message, HistoryRecord};
+use crate::package::hooks::hook_messages;
 use crate::states::constants::*;
 use crate::states::history::*;
 use crate::ContractError;
@@ -14,17 +16,20 @@ use crate::states::market::{Market, MARKETS};
 use crate::states::state::FEESTRUCTURE;
 use crate::states::state::ORACLEGUARDRAILS;
 use crate::states::state::STATE;
-use crate::states::user::{User, POSITIONS, USERS};
+use crate::states::user::{
+    subaccount_address, User, ISOLATED_COLLATERAL, POSITIONS, SUBACCOUNTS, USERS,
+};
 
 use crate::package::helper::addr_validate_to_lower;
 use crate::package::helper::assert_sent_uusd_balance;
-use crate::package::helper::query_balance;
+use crate::package::helper::query_vault_balance;
 use crate::package::helper::VaultInterface;
 use crate::package::number::Number128;
 use crate::package::types::{DepositDirection, PositionDirection};
 use cosmwasm_std::{
     coins, to_binary, CosmosMsg, DepsMut, Env, Fraction, MessageInfo, Response, Uint128, WasmMsg,
 };
+use cw_storage_plus::U64Key;
 
 pub fn try_deposit_collateral(
     mut deps: DepsMut,
@@ -32,8 +37,9 @@ pub fn try_deposit_collateral(
     info: MessageInfo,
     amount: u64,
     referrer: Option<String>,
+    subaccount: u64,
 ) -> Result<Response, ContractError> {
-    let user_address = info.sender.clone();
+    let user_address = subaccount_address(&info.sender, subaccount);
     let existing_user = USERS.may_load(deps.storage, &user_address)?;
     let now = env.block.time.seconds();
     let mut user: User;
@@ -83,15 +89,16 @@ pub fn try_deposit_collateral(
         &user_address.clone(),
         |_m| -> Result<User, ContractError> { Ok(user) },
     )?;
+    SUBACCOUNTS.save(
+        deps.storage,
+        (&info.sender, U64Key::new(subaccount)),
+        &user_address,
+    )?;
 
     let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
     let mut messages: Vec<CosmosMsg> = vec![];
-    let fm: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordFundingPaymentsMultiple { vecf: f })?,
-        funds: vec![],
-    });
-    messages.push(fm);
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
     let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: state.collateral_vault.to_string(),
         msg: to_binary(&VaultInterface::Deposit {})?,
@@ -99,23 +106,43 @@ pub fn try_deposit_collateral(
     });
     messages.push(message);
 
-    let message_h = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordDeposit {
-            d: DepositRecord {
-                ts: now,
-                user: user_address.clone(),
-                direction: DepositDirection::DEPOSIT,
-                collateral_before,
-                cumulative_deposits_before,
-                amount: amount,
-            },
-        })?,
-        funds: vec![],
-    });
-    messages.push(message_h);
+    // fresh liquidity goes to queued withdrawal claims first
+    if controller::withdrawal::has_outstanding_claims(deps.storage)? {
+        let collateral_balance = query_vault_balance(
+            &deps.querier,
+            state.collateral_vault.clone(),
+            &env.contract.address,
+        )?;
+        let insurance_balance = query_vault_balance(
+            &deps.querier,
+            state.insurance_vault.clone(),
+            &env.contract.address,
+        )?;
+        let (queue_messages, _, _) = controller::withdrawal::process_withdrawal_queue(
+            deps.storage,
+            &state,
+            &env.contract.address,
+            Uint128::from(collateral_balance).checked_add(Uint128::from(amount as u128))?,
+            Uint128::from(insurance_balance),
+        )?;
+        messages.extend(queue_messages);
+    }
+
+    records.push(HistoryRecord::Deposit(DepositRecord {
+        ts: now,
+        user: user_address.clone(),
+        direction: DepositDirection::DEPOSIT,
+        collateral_before,
+        cumulative_deposits_before,
+        amount: amount,
+    }));
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
         .add_attribute("method", "try_deposit_collateral"))
 }
 
@@ -124,8 +151,9 @@ pub fn try_withdraw_collateral(
     env: Env,
     info: MessageInfo,
     amount: u64,
+    subaccount: u64,
 ) -> Result<Response, ContractError> {
-    let user_address = info.sender.clone();
+    let user_address = subaccount_address(&info.sender, subaccount);
     let existing_user = USERS.may_load(deps.storage, &user_address)?;
     let now = env.block.time.seconds();
     let mut user;
@@ -139,88 +167,336 @@ pub fn try_withdraw_collateral(
     let state = STATE.load(deps.storage)?;
     let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
     let mut messages: Vec<CosmosMsg> = vec![];
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordFundingPaymentsMultiple { vecf: f })?,
-        funds: vec![],
-    });
-    messages.push(message);
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
     user = USERS.may_load(deps.storage, &user_address)?.unwrap();
 
     if (amount as u128) > user.collateral.u128() {
         return Err(ContractError::InsufficientCollateral.into());
     }
 
-    let collateral_balance = query_balance(&deps.querier, state.collateral_vault.clone())?;
-    let insurance_balance = query_balance(&deps.querier, state.insurance_vault.clone())?;
-    let (collateral_account_withdrawal, insurance_account_withdrawal) =
-        calculate_withdrawal_amounts(
-            Uint128::from(amount as u128),
+    let collateral_balance = query_vault_balance(
+        &deps.querier,
+        state.collateral_vault.clone(),
+        &env.contract.address,
+    )?;
+    let insurance_balance = query_vault_balance(
+        &deps.querier,
+        state.insurance_vault.clone(),
+        &env.contract.address,
+    )?;
+
+    // claims queued by earlier withdrawals are paid out first
+    let (queue_messages, collateral_balance, insurance_balance) =
+        controller::withdrawal::process_withdrawal_queue(
+            deps.storage,
+            &state,
+            &env.contract.address,
             Uint128::from(collateral_balance),
             Uint128::from(insurance_balance),
         )?;
+    messages.extend(queue_messages);
 
-    // amount_withdrawn can be less than amount if there is an insufficient balance in collateral and insurance vault
+    // claims still waiting after this transaction's share of the queue go first, so the whole
+    // withdrawal joins the back of the queue
+    let (collateral_account_withdrawal, insurance_account_withdrawal) =
+        if controller::withdrawal::has_outstanding_claims(deps.storage)? {
+            (Uint128::zero(), Uint128::zero())
+        } else {
+            calculate_withdrawal_amounts(
+                Uint128::from(amount as u128),
+                collateral_balance,
+                insurance_balance,
+            )?
+        };
+
+    // amount_withdrawn can be less than amount if there is an insufficient balance in collateral and insurance vault,
+    // the rest is queued as a claim for the user
     let amount_withdraw =
         collateral_account_withdrawal.checked_add(insurance_account_withdrawal)?;
+    let shortfall = Uint128::from(amount as u128).checked_sub(amount_withdraw)?;
 
     user.cumulative_deposits = user
         .cumulative_deposits
-        .checked_sub(Uint128::from(amount_withdraw))?;
+        .checked_sub(Uint128::from(amount as u128))?;
 
     user.collateral = user
         .collateral
-        .checked_sub(Uint128::from(collateral_account_withdrawal))?
-        .checked_sub(Uint128::from(insurance_account_withdrawal))?;
+        .checked_sub(Uint128::from(amount as u128))?;
 
-    if !controller::margin::meets_initial_margin_requirement(&mut deps, &info.sender.clone())? {
+    if !controller::margin::meets_initial_margin_requirement(&mut deps, &user_address, None)? {
         return Err(ContractError::InsufficientCollateral.into());
     }
 
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.collateral_vault.clone().to_string(),
-        msg: to_binary(&VaultInterface::Withdraw {
-            to_address: info.sender.clone(),
-            amount: collateral_account_withdrawal,
-        })?,
-        funds: vec![],
-    }));
+    messages.extend(controller::withdrawal::vault_withdraw_messages(
+        &state,
+        &info.sender,
+        collateral_account_withdrawal,
+        insurance_account_withdrawal,
+    )?);
 
-    if insurance_account_withdrawal.gt(&Uint128::zero()) {
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: state.insurance_vault.to_string(),
-            msg: to_binary(&VaultInterface::Withdraw {
-                to_address: info.sender.clone(),
-                amount: insurance_account_withdrawal,
-            })?,
-            funds: vec![],
-        }));
+    let mut response = Response::new();
+    if !shortfall.is_zero() {
+        let claim_id = controller::withdrawal::queue_withdrawal_claim(
+            deps.storage,
+            &info.sender,
+            shortfall,
+            now,
+        )?;
+        response = response
+            .add_attribute("claim_id", claim_id.to_string())
+            .add_attribute("queued_amount", shortfall);
     }
 
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordDeposit {
-            d: DepositRecord {
-                ts: now,
-                user: user_address.clone(),
-                direction: DepositDirection::WITHDRAW,
-                collateral_before,
-                cumulative_deposits_before,
-                amount: amount_withdraw.u128() as u64,
-            },
-        })?,
-        funds: vec![],
+    records.push(HistoryRecord::Deposit(DepositRecord {
+        ts: now,
+        user: user_address.clone(),
+        direction: DepositDirection::WITHDRAW,
+        collateral_before,
+        cumulative_deposits_before,
+        amount,
     }));
     USERS.update(
         deps.storage,
         &user_address.clone(),
         |_u| -> Result<User, ContractError> { Ok(user) },
     )?;
-    Ok(Response::new()
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
+    Ok(response
         .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
         .add_attribute("method", "try_withdraw_collateral"))
 }
 
+pub fn try_transfer_collateral(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: u64,
+    from_subaccount: u64,
+    to_subaccount: u64,
+) -> Result<Response, ContractError> {
+    if from_subaccount == to_subaccount {
+        return Err(ContractError::SameSubaccount);
+    }
+    if amount == 0 {
+        return Err(ContractError::InsufficientDeposit);
+    }
+    let now = env.block.time.seconds();
+    let state = STATE.load(deps.storage)?;
+    let from_address = subaccount_address(&info.sender, from_subaccount);
+    let to_address = subaccount_address(&info.sender, to_subaccount);
+    if !USERS.has(deps.storage, &from_address) {
+        return Err(ContractError::UserDoesNotExist);
+    }
+
+    let f = controller::funding::settle_funding_payment(&mut deps, &from_address, now)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
+
+    let amount = Uint128::from(amount as u128);
+    let mut from = USERS.load(deps.storage, &from_address)?;
+    if amount > from.collateral {
+        return Err(ContractError::InsufficientCollateral);
+    }
+    // deposits move along with the collateral so the receiving account can withdraw it
+    let deposits_moved = amount.min(from.cumulative_deposits);
+    from.collateral = from.collateral.checked_sub(amount)?;
+    from.cumulative_deposits = from.cumulative_deposits.checked_sub(deposits_moved)?;
+    USERS.save(deps.storage, &from_address, &from)?;
+
+    if !controller::margin::meets_initial_margin_requirement(&mut deps, &from_address, None)? {
+        return Err(ContractError::InsufficientCollateral);
+    }
+
+    let mut to = match USERS.may_load(deps.storage, &to_address)? {
+        Some(user) => user,
+        None => User {
+            collateral: Uint128::zero(),
+            cumulative_deposits: Uint128::zero(),
+            total_fee_paid: Uint128::zero(),
+            total_token_discount: Uint128::zero(),
+            total_referral_reward: Uint128::zero(),
+            total_referee_discount: Uint128::zero(),
+            referrer: None,
+        },
+    };
+    to.collateral = to.collateral.checked_add(amount)?;
+    to.cumulative_deposits = to.cumulative_deposits.checked_add(deposits_moved)?;
+    USERS.save(deps.storage, &to_address, &to)?;
+    SUBACCOUNTS.save(
+        deps.storage,
+        (&info.sender, U64Key::new(to_subaccount)),
+        &to_address,
+    )?;
+
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
+        .add_attribute("method", "try_transfer_collateral")
+        .add_attribute("from_subaccount", from_subaccount.to_string())
+        .add_attribute("to_subaccount", to_subaccount.to_string())
+        .add_attribute("amount", amount))
+}
+
+pub fn try_add_isolated_margin(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_index: u64,
+    amount: u64,
+    subaccount: u64,
+) -> Result<Response, ContractError> {
+    if amount == 0 {
+        return Err(ContractError::InsufficientDeposit);
+    }
+    let now = env.block.time.seconds();
+    let state = STATE.load(deps.storage)?;
+    let user_address = subaccount_address(&info.sender, subaccount);
+    if !USERS.has(deps.storage, &user_address) {
+        return Err(ContractError::UserDoesNotExist);
+    }
+    MARKETS.load(deps.storage, U64Key::new(market_index))?;
+
+    let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
+
+    let key = (&user_address, U64Key::new(market_index));
+    let isolated_collateral = match ISOLATED_COLLATERAL.may_load(deps.storage, key.clone())? {
+        Some(isolated_collateral) => isolated_collateral,
+        None => {
+            if matches!(
+                POSITIONS.may_load(deps.storage, key.clone())?,
+                Some(position) if position.is_open_position()
+            ) {
+                return Err(ContractError::CrossPositionOpen);
+            }
+            Uint128::zero()
+        }
+    };
+
+    let amount = Uint128::from(amount as u128);
+    let mut user = USERS.load(deps.storage, &user_address)?;
+    if amount > user.collateral {
+        return Err(ContractError::InsufficientCollateral);
+    }
+    user.collateral = user.collateral.checked_sub(amount)?;
+    USERS.save(deps.storage, &user_address, &user)?;
+    ISOLATED_COLLATERAL.save(deps.storage, key, &isolated_collateral.checked_add(amount)?)?;
+
+    if !controller::margin::meets_initial_margin_requirement(&mut deps, &user_address, None)? {
+        return Err(ContractError::InsufficientCollateral);
+    }
+
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
+        .add_attribute("method", "try_add_isolated_margin")
+        .add_attribute("market_index", market_index.to_string())
+        .add_attribute("amount", amount))
+}
+
+pub fn try_remove_isolated_margin(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_index: u64,
+    amount: u64,
+    subaccount: u64,
+) -> Result<Response, ContractError> {
+    let now = env.block.time.seconds();
+    let state = STATE.load(deps.storage)?;
+    let user_address = subaccount_address(&info.sender, subaccount);
+
+    let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
+
+    let key = (&user_address, U64Key::new(market_index));
+    let isolated_collateral = ISOLATED_COLLATERAL
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::PositionNotIsolated)?;
+    let amount = Uint128::from(amount as u128);
+    if amount > isolated_collateral {
+        return Err(ContractError::InsufficientCollateral);
+    }
+    let remaining = isolated_collateral.checked_sub(amount)?;
+    let position_open = matches!(
+        POSITIONS.may_load(deps.storage, key.clone())?,
+        Some(position) if position.is_open_position()
+    );
+    if remaining.is_zero() && !position_open {
+        ISOLATED_COLLATERAL.remove(deps.storage, key);
+    } else {
+        ISOLATED_COLLATERAL.save(deps.storage, key, &remaining)?;
+    }
+
+    let mut user = USERS.load(deps.storage, &user_address)?;
+    user.collateral = user.collateral.checked_add(amount)?;
+    USERS.save(deps.storage, &user_address, &user)?;
+
+    if position_open
+        && !controller::margin::meets_initial_margin_requirement(
+            &mut deps,
+            &user_address,
+            Some(market_index),
+        )?
+    {
+        return Err(ContractError::InsufficientCollateral);
+    }
+
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
+        .add_attribute("method", "try_remove_isolated_margin")
+        .add_attribute("market_index", market_index.to_string())
+        .add_attribute("amount", amount))
+}
+
+pub fn try_process_withdrawal_queue(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let collateral_balance = query_vault_balance(
+        &deps.querier,
+        state.collateral_vault.clone(),
+        &env.contract.address,
+    )?;
+    let insurance_balance = query_vault_balance(
+        &deps.querier,
+        state.insurance_vault.clone(),
+        &env.contract.address,
+    )?;
+    let (messages, _, _) = controller::withdrawal::process_withdrawal_queue(
+        deps.storage,
+        &state,
+        &env.contract.address,
+        Uint128::from(collateral_balance),
+        Uint128::from(insurance_balance),
+    )?;
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "try_process_withdrawal_queue"))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn try_open_position(
     mut deps: DepsMut,
     env: Env,
@@ -229,8 +505,9 @@ pub fn try_open_position(
     quote_asset_amount: Uint128,
     market_index: u64,
     limit_price: Option<Uint128>,
+    subaccount: u64,
 ) -> Result<Response, ContractError> {
-    let user_address = info.sender.clone();
+    let user_address = subaccount_address(&info.sender, subaccount);
 
     let now = env.block.time.seconds();
     let state = STATE.load(deps.storage)?;
@@ -247,22 +524,20 @@ pub fn try_open_position(
     }
     let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
     let mut messages: Vec<CosmosMsg> = vec![];
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordFundingPaymentsMultiple { vecf: f })?,
-        funds: vec![],
-    });
-    messages.push(message);
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
     let position_index = market_index.clone();
+    let isolated_market =
+        controller::margin::isolated_market(deps.storage, &user_address, market_index)?;
     let mark_price_before: Uint128;
     let oracle_mark_spread_pct_before: i128;
     let is_oracle_valid: bool;
 
     {
-        let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+        let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
         mark_price_before = market.amm.mark_price()?;
         let oracle_price_data = market.amm.get_oracle_price(&mut deps, market_index)?;
-        market = MARKETS.load(deps.storage, market_index.to_string())?;
+        market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
         oracle_mark_spread_pct_before = helpers::amm::calculate_oracle_mark_spread_pct(
             &market.amm,
             &oracle_price_data,
@@ -310,10 +585,10 @@ pub fn try_open_position(
     let oracle_price_after: i128;
     let oracle_mark_spread_pct_after: i128;
     {
-        let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+        let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
         mark_price_after = market.amm.mark_price()?;
         let oracle_price_data = market.amm.get_oracle_price(&mut deps, market_index)?;
-        market = MARKETS.load(deps.storage, market_index.to_string())?;
+        market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
         oracle_mark_spread_pct_after = helpers::amm::calculate_oracle_mark_spread_pct(
             &market.amm,
             &oracle_price_data,
@@ -322,8 +597,11 @@ pub fn try_open_position(
         oracle_price_after = oracle_price_data.price.i128();
     }
 
-    let meets_initial_margin_requirement =
-        controller::margin::meets_initial_margin_requirement(&mut deps, &user_address)?;
+    let meets_initial_margin_requirement = controller::margin::meets_initial_margin_requirement(
+        &mut deps,
+        &user_address,
+        isolated_market,
+    )?;
     if !meets_initial_margin_requirement && potentially_risk_increasing {
         return Err(ContractError::InsufficientCollateral.into());
     }
@@ -340,7 +618,7 @@ pub fn try_open_position(
         )?;
 
     {
-        let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+        let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
         market.amm.total_fee = market.amm.total_fee.checked_add(fee_to_market)?;
         market.amm.total_fee_minus_distributions = market
             .amm
@@ -348,16 +626,18 @@ pub fn try_open_position(
             .checked_add(fee_to_market)?;
         MARKETS.update(
             deps.storage,
-            market_index.to_string(),
+            U64Key::new(market_index),
             |_m| -> Result<Market, ContractError> { Ok(market) },
         )?;
     }
 
-    if user.collateral.ge(&user_fee) {
-        user.collateral = user.collateral.checked_sub(user_fee)?;
-    } else {
-        user.collateral = Uint128::zero();
-    }
+    controller::margin::settle_collateral(
+        deps.storage,
+        &user_address,
+        &mut user,
+        isolated_market,
+        -(user_fee.u128() as i128),
+    )?;
 
     // Increment the user's total fee variables
     user.total_fee_paid = user.total_fee_paid.checked_add(user_fee)?;
@@ -393,29 +673,22 @@ pub fn try_open_position(
         return Err(ContractError::OracleMarkSpreadLimit.into());
     }
 
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordTrade {
-            t: TradeRecord {
-                ts: now,
-                user: user_address.clone(),
-                direction,
-                base_asset_amount,
-                quote_asset_amount,
-                mark_price_before,
-                mark_price_after,
-                fee: user_fee,
-                referrer_reward,
-                referee_discount,
-                token_discount,
-                liquidation: false,
-                market_index,
-                oracle_price: Number128::new(oracle_price_after),
-            },
-        })?,
-        funds: vec![],
-    });
-    messages.push(message);
+    records.push(HistoryRecord::Trade(TradeRecord {
+        ts: now,
+        user: user_address.clone(),
+        direction,
+        base_asset_amount,
+        quote_asset_amount,
+        mark_price_before,
+        mark_price_after,
+        fee: user_fee,
+        referrer_reward,
+        referee_discount,
+        token_discount,
+        liquidation: false,
+        market_index,
+        oracle_price: Number128::new(oracle_price_after),
+    }));
     if limit_price.is_some()
         && !helpers::order::limit_price_satisfied(
             limit_price.unwrap(),
@@ -428,7 +701,7 @@ pub fn try_open_position(
     }
 
     {
-        let f = controller::funding::update_funding_rate(
+        let (f, snapshot) = controller::funding::update_funding_rate(
             &mut deps,
             market_index,
             now,
@@ -437,15 +710,11 @@ pub fn try_open_position(
         )?;
         match f {
             Some(fr) => {
-                let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: state.history_contract.clone().to_string(),
-                    msg: to_binary(&HistoryExecuteMsg::RecordFundingRate { f: fr })?,
-                    funds: vec![],
-                });
-                messages.push(message);
+                records.push(HistoryRecord::FundingRate(fr));
             }
             None => {}
         }
+        records.extend(snapshot.map(HistoryRecord::AmmSnapshot));
     }
 
     USERS.update(
@@ -454,8 +723,13 @@ pub fn try_open_position(
         |_m| -> Result<User, ContractError> { Ok(user) },
     )?;
 
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
         .add_attribute("method", "try_open_position"))
 }
 
@@ -464,29 +738,26 @@ pub fn try_close_position(
     env: Env,
     info: MessageInfo,
     market_index: u64,
+    subaccount: u64,
 ) -> Result<Response, ContractError> {
-    let user_address = info.sender.clone();
+    let user_address = subaccount_address(&info.sender, subaccount);
     let now = env.block.time.seconds();
     let state = STATE.load(deps.storage)?;
     let oracle_guard_rails = ORACLEGUARDRAILS.load(deps.storage)?;
     let fee_structure = FEESTRUCTURE.load(deps.storage)?;
     let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
     let mut messages: Vec<CosmosMsg> = vec![];
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordFundingPaymentsMultiple { vecf: f })?,
-        funds: vec![],
-    });
-    messages.push(message);
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
     let position_index = market_index.clone();
     let market_position = POSITIONS.load(
         deps.storage,
-        (&user_address.clone(), market_index.to_string()),
+        (&user_address.clone(), U64Key::new(market_index)),
     )?;
-    let mut market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let mut market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let mark_price_before = market.amm.mark_price()?;
     let oracle_price_data = market.amm.get_oracle_price(&mut deps, market_index)?;
-    market = MARKETS.load(deps.storage, market_index.to_string())?;
+    market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let oracle_mark_spread_pct_before = helpers::amm::calculate_oracle_mark_spread_pct(
         &market.amm,
         &oracle_price_data,
@@ -507,7 +778,7 @@ pub fn try_close_position(
 
     let mut user = USERS.load(deps.storage, &user_address)?;
 
-    market = MARKETS.load(deps.storage, market_index.to_string())?;
+    market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let base_asset_amount = Uint128::from(base_asset_amount.unsigned_abs());
     let referrer = user.referrer.clone();
     let discount_token = Uint128::zero();
@@ -526,11 +797,15 @@ pub fn try_close_position(
         .total_fee_minus_distributions
         .checked_add(fee_to_market)?;
 
-    if user.collateral.gt(&user_fee) {
-        user.collateral = user.collateral.checked_sub(user_fee)?;
-    } else {
-        user.collateral = Uint128::zero();
-    }
+    let isolated_market =
+        controller::margin::isolated_market(deps.storage, &user_address, market_index)?;
+    controller::margin::settle_collateral(
+        deps.storage,
+        &user_address,
+        &mut user,
+        isolated_market,
+        -(user_fee.u128() as i128),
+    )?;
 
     user.total_fee_paid = user.total_fee_paid.checked_add(user_fee)?;
     user.total_token_discount = user.total_token_discount.checked_add(token_discount)?;
@@ -563,7 +838,7 @@ pub fn try_close_position(
 
     MARKETS.update(
         deps.storage,
-        market_index.to_string(),
+        U64Key::new(market_index),
         |_m| -> Result<Market, ContractError> { Ok(market.clone()) },
     )?;
 
@@ -601,7 +876,6 @@ pub fn try_close_position(
     {
         return Err(ContractError::OracleMarkSpreadLimit.into());
     }
-    let mut messages: Vec<CosmosMsg> = vec![];
     let t = TradeRecord {
         ts: now,
         user: user_address.clone(),
@@ -618,13 +892,8 @@ pub fn try_close_position(
         market_index,
         oracle_price: oracle_price_after,
     };
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordTrade { t })?,
-        funds: vec![],
-    });
-    messages.push(message);
-    let f = controller::funding::update_funding_rate(
+    records.push(HistoryRecord::Trade(t));
+    let (f, snapshot) = controller::funding::update_funding_rate(
         &mut deps,
         market_index,
         now,
@@ -633,18 +902,19 @@ pub fn try_close_position(
     )?;
     match f {
         Some(fr) => {
-            let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.history_contract.clone().to_string(),
-                msg: to_binary(&HistoryExecuteMsg::RecordFundingRate { f: fr })?,
-                funds: vec![],
-            });
-            messages.push(message);
+            records.push(HistoryRecord::FundingRate(fr));
         }
         None => {},
     }
+    records.extend(snapshot.map(HistoryRecord::AmmSnapshot));
 
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
         .add_attribute("method", "try_close_position"))
 }
 
@@ -733,14 +1003,27 @@ pub fn try_liquidate(
     info: MessageInfo,
     user: String,
     market_index: u64,
+    subaccount: u64,
+    liquidator_subaccount: u64,
 ) -> Result<Response, ContractError> {
     let state = STATE.load(deps.storage)?;
-    let user_address = addr_validate_to_lower(deps.api, &user)?;
+    let user_address = subaccount_address(&addr_validate_to_lower(deps.api, &user)?, subaccount);
+    let liquidator_address = subaccount_address(&info.sender, liquidator_subaccount);
     let now = env.block.time.seconds();
     let mut messages: Vec<CosmosMsg> = vec![];
+    let mut records: Vec<HistoryRecord> = vec![];
     let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
 
     let mut user = USERS.load(deps.storage, &user_address)?;
+    // an isolated position is liquidated on its own, any other market liquidates the cross bucket
+    let isolated_market =
+        controller::margin::isolated_market(deps.storage, &user_address, market_index)?;
+    let collateral = match isolated_market {
+        Some(market_index) => {
+            ISOLATED_COLLATERAL.load(deps.storage, (&user_address, U64Key::new(market_index)))?
+        }
+        None => user.collateral,
+    };
 
     let LiquidationStatus {
         liquidation_type,
@@ -751,19 +1034,14 @@ pub fn try_liquidate(
         market_statuses,
         mut margin_requirement,
         margin_ratio,
-    } = controller::margin::calculate_liquidation_status(&mut deps, &user_address)?;
+    } = controller::margin::calculate_liquidation_status(
+        &mut deps,
+        &user_address,
+        isolated_market,
+    )?;
 
-    let res: Response = Response::new().add_attribute("method", "try_liquidate");
-    let collateral = user.collateral;
+    let mut res: Response = Response::new().add_attribute("method", "try_liquidate");
     if liquidation_type == LiquidationType::NONE {
-        res.clone()
-            .add_attribute("total_collateral {}", total_collateral.to_string());
-        res.clone().add_attribute(
-            "adjusted_total_collateral {}",
-            adjusted_total_collateral.to_string(),
-        );
-        res.clone()
-            .add_attribute("margin_requirement {}", margin_requirement.to_string());
         return Err(ContractError::SufficientCollateral.into());
     }
 
@@ -787,8 +1065,10 @@ pub fn try_liquidate(
             if market_status.base_asset_value.is_zero() {
                 continue;
             }
+            // close the market of this status, not the one the liquidator named
+            let market_index = market_status.market_index;
 
-            let market = MARKETS.load(deps.storage, market_status.market_index.to_string())?;
+            let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
             let mark_price_before = market_status.mark_price_before;
             let oracle_status = &market_status.oracle_status;
 
@@ -801,16 +1081,18 @@ pub fn try_liquidate(
                     mark_twap_divergence.unsigned_abs() >= MAX_MARK_TWAP_DIVERGENCE.u128();
 
                 if mark_twap_too_divergent {
-                    res.clone().add_attribute(
-                        "mark_twap_divergence {} for market {}",
-                        mark_twap_divergence.to_string(),
-                    );
+                    res = res.add_event(liquidation_skip_event(
+                        &user_address,
+                        market_status.market_index,
+                        "mark_twap_divergence",
+                        mark_twap_divergence,
+                    ));
                     continue;
                 }
             }
 
             let market_position =
-                POSITIONS.load(deps.storage, (&user_address, market_index.to_string()))?;
+                POSITIONS.load(deps.storage, (&user_address, U64Key::new(market_index)))?;
             // todo initialize position
 
             let mark_price_before_i128 = mark_price_before.u128() as i128;
@@ -866,10 +1148,12 @@ pub fn try_liquidate(
                 if oracle_status.oracle_mark_spread_pct.i128().unsigned_abs()
                     < oracle_mark_divergence_after_close.unsigned_abs()
                 {
-                    res.clone().add_attribute(
-                        "oracle_mark_divergence_after_close ",
-                        oracle_mark_divergence_after_close.to_string(),
-                    );
+                    res = res.add_event(liquidation_skip_event(
+                        &user_address,
+                        market_status.market_index,
+                        "oracle_mark_divergence_after_close",
+                        oracle_mark_divergence_after_close,
+                    ));
                     continue;
                 }
             }
@@ -914,29 +1198,26 @@ pub fn try_liquidate(
             let base_asset_amount = Uint128::from(base_asset_amount.unsigned_abs());
             base_asset_value_closed = base_asset_value_closed.checked_add(quote_asset_amount)?;
             let mark_price_after = market.amm.mark_price()?;
-            let message_h = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.history_contract.clone().to_string(),
-                msg: to_binary(&HistoryExecuteMsg::RecordTrade {
-                    t: TradeRecord {
-                        ts: now,
-                        user: user_address.clone(),
-                        direction: direction_to_close,
-                        base_asset_amount,
-                        quote_asset_amount,
-                        mark_price_before,
-                        mark_price_after,
-                        fee: Uint128::zero(),
-                        referrer_reward: Uint128::zero(),
-                        referee_discount: Uint128::zero(),
-                        token_discount: Uint128::zero(),
-                        liquidation: true,
-                        market_index,
-                        oracle_price: market_status.oracle_status.price_data.price,
-                    },
-                })?,
-                funds: vec![],
-            });
-            messages.push(message_h);
+            records.push(HistoryRecord::Trade(TradeRecord {
+                ts: now,
+                user: user_address.clone(),
+                direction: direction_to_close,
+                base_asset_amount,
+                quote_asset_amount,
+                mark_price_before,
+                mark_price_after,
+                fee: Uint128::zero(),
+                referrer_reward: Uint128::zero(),
+                referee_discount: Uint128::zero(),
+                token_discount: Uint128::zero(),
+                liquidation: true,
+                market_index,
+                oracle_price: market_status.oracle_status.price_data.price,
+            }));
+            records.extend(
+                controller::amm::take_amm_snapshot(&mut deps, market_index, now)?
+                    .map(HistoryRecord::AmmSnapshot),
+            );
             margin_requirement = margin_requirement.checked_sub(
                 market_status
                     .maintenance_margin_requirement
@@ -976,9 +1257,11 @@ pub fn try_liquidate(
             if market_status.base_asset_value.is_zero() {
                 continue;
             }
+            // close the market of this status, not the one the liquidator named
+            let market_index = market_status.market_index;
 
             let oracle_status = &market_status.oracle_status;
-            let market = MARKETS.load(deps.storage, market_index.to_string())?;
+            let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
             let mark_price_before = market_status.mark_price_before;
 
             let oracle_is_valid = oracle_status.is_valid;
@@ -989,14 +1272,18 @@ pub fn try_liquidate(
                     mark_twap_divergence.unsigned_abs() >= MAX_MARK_TWAP_DIVERGENCE.u128();
 
                 if mark_twap_too_divergent {
-                    res.clone()
-                        .add_attribute("mark_twap_divergence", mark_twap_divergence.to_string());
+                    res = res.add_event(liquidation_skip_event(
+                        &user_address,
+                        market_status.market_index,
+                        "mark_twap_divergence",
+                        mark_twap_divergence,
+                    ));
                     continue;
                 }
             }
 
             let market_position =
-                POSITIONS.load(deps.storage, (&user_address, market_index.to_string()))?;
+                POSITIONS.load(deps.storage, (&user_address, U64Key::new(market_index)))?;
 
             let mut quote_asset_amount = market_status
                 .base_asset_value
@@ -1023,7 +1310,7 @@ pub fn try_liquidate(
                 mark_price_before_i128,
             )?;
 
-            res.clone().add_attribute(
+            res = res.add_attribute(
                 "reduce_position_slippage_pct",
                 reduce_position_slippage_pct.to_string(),
             );
@@ -1067,10 +1354,6 @@ pub fn try_liquidate(
                 if oracle_status.oracle_mark_spread_pct.i128().unsigned_abs()
                     < oracle_mark_divergence_after_reduce.unsigned_abs()
                 {
-                    res.clone().add_attribute(
-                        "oracle_mark_spread_pct_after_reduce",
-                        oracle_mark_divergence_after_reduce.to_string(),
-                    );
                     return Err(ContractError::OracleMarkSpreadLimit.into());
                 }
             }
@@ -1100,29 +1383,26 @@ pub fn try_liquidate(
             .unsigned_abs();
 
             let mark_price_after = market.amm.mark_price()?;
-            let message_h = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.history_contract.clone().to_string(),
-                msg: to_binary(&HistoryExecuteMsg::RecordTrade {
-                    t: TradeRecord {
-                        ts: now,
-                        user: user_address.clone(),
-                        direction: direction_to_reduce,
-                        base_asset_amount: Uint128::from(base_asset_amount),
-                        quote_asset_amount,
-                        mark_price_before,
-                        mark_price_after,
-                        fee: Uint128::zero(),
-                        referrer_reward: Uint128::zero(),
-                        referee_discount: Uint128::zero(),
-                        token_discount: Uint128::zero(),
-                        liquidation: true,
-                        market_index,
-                        oracle_price: market_status.oracle_status.price_data.price,
-                    },
-                })?,
-                funds: vec![],
-            });
-            messages.push(message_h);
+            records.push(HistoryRecord::Trade(TradeRecord {
+                ts: now,
+                user: user_address.clone(),
+                direction: direction_to_reduce,
+                base_asset_amount: Uint128::from(base_asset_amount),
+                quote_asset_amount,
+                mark_price_before,
+                mark_price_after,
+                fee: Uint128::zero(),
+                referrer_reward: Uint128::zero(),
+                referee_discount: Uint128::zero(),
+                token_discount: Uint128::zero(),
+                liquidation: true,
+                market_index,
+                oracle_price: market_status.oracle_status.price_data.price,
+            }));
+            records.extend(
+                controller::amm::take_amm_snapshot(&mut deps, market_index, now)?
+                    .map(HistoryRecord::AmmSnapshot),
+            );
 
             margin_requirement = margin_requirement.checked_sub(
                 market_status
@@ -1149,9 +1429,17 @@ pub fn try_liquidate(
         return Err(ContractError::NoPositionsLiquidatable);
     }
 
-    let balance_collateral = query_balance(&deps.querier, state.collateral_vault.clone())?;
+    let balance_collateral = query_vault_balance(
+        &deps.querier,
+        state.collateral_vault.clone(),
+        &env.contract.address,
+    )?;
 
-    let balance_insurance = query_balance(&deps.querier, state.insurance_vault.clone())?;
+    let balance_insurance = query_vault_balance(
+        &deps.querier,
+        state.insurance_vault.clone(),
+        &env.contract.address,
+    )?;
 
     let (withdrawal_amount, _) = calculate_withdrawal_amounts(
         liquidation_fee,
@@ -1159,8 +1447,25 @@ pub fn try_liquidate(
         Uint128::from(balance_insurance),
     )?;
 
+    // queued withdrawal claims are paid before the insurance fund's share of the fee
+    let (queue_messages, balance_collateral, _) =
+        controller::withdrawal::process_withdrawal_queue(
+            deps.storage,
+            &state,
+            &env.contract.address,
+            Uint128::from(balance_collateral),
+            Uint128::from(balance_insurance),
+        )?;
+    messages.extend(queue_messages);
+
     user = USERS.load(deps.storage, &user_address)?;
-    user.collateral = user.collateral.checked_sub(liquidation_fee)?;
+    controller::margin::settle_collateral(
+        deps.storage,
+        &user_address,
+        &mut user,
+        isolated_market,
+        -(liquidation_fee.u128() as i128),
+    )?;
     USERS.update(
         deps.storage,
         &user_address,
@@ -1180,75 +1485,92 @@ pub fn try_liquidate(
     let fee_to_insurance_fund = withdrawal_amount.checked_sub(fee_to_liquidator)?;
 
     if fee_to_liquidator.gt(&Uint128::zero()) {
-        let mut liquidator = USERS.load(deps.storage, &info.sender.clone())?;
+        let mut liquidator = USERS.load(deps.storage, &liquidator_address)?;
         liquidator.collateral = liquidator
             .collateral
             .checked_add(Uint128::from(fee_to_liquidator))?;
 
         USERS.update(
             deps.storage,
-            &info.sender.clone(),
+            &liquidator_address,
             |_m| -> Result<User, ContractError> { Ok(liquidator) },
         )?;
     }
 
     if fee_to_insurance_fund.gt(&Uint128::zero()) {
-        let message = CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: state.collateral_vault.to_string(),
-            msg: to_binary(&VaultInterface::Withdraw {
-                to_address: state.insurance_vault.clone(),
-                amount: fee_to_insurance_fund,
-            })?,
-            funds: vec![],
-        });
-        messages.push(message);
+        let (fee_paid, _) =
+            if controller::withdrawal::has_outstanding_claims(deps.storage)? {
+                (Uint128::zero(), Uint128::zero())
+            } else {
+                calculate_withdrawal_amounts(
+                    fee_to_insurance_fund,
+                    balance_collateral,
+                    Uint128::zero(),
+                )?
+            };
+        messages.extend(controller::withdrawal::insurance_fee_messages(
+            &state,
+            &env.contract.address,
+            fee_paid,
+        )?);
+        let fee_queued = fee_to_insurance_fund.checked_sub(fee_paid)?;
+        if !fee_queued.is_zero() {
+            controller::withdrawal::queue_withdrawal_claim(
+                deps.storage,
+                &state.insurance_vault,
+                fee_queued,
+                now,
+            )?;
+        }
     }
 
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordFundingPaymentsMultiple { vecf: f })?,
-        funds: vec![],
-    });
-    messages.push(message);
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
 
-    LiquidationRecord {
+    records.push(HistoryRecord::Liquidation(LiquidationRecord {
         ts: now,
         user: user_address,
         partial: !is_full_liquidation,
         base_asset_value,
         base_asset_value_closed,
         liquidation_fee,
-        liquidator: info.sender.clone(),
+        liquidator: liquidator_address,
         total_collateral,
         collateral,
         unrealized_pnl: Number128::new(unrealized_pnl),
         margin_ratio,
         fee_to_liquidator: fee_to_liquidator.u128() as u64,
         fee_to_insurance_fund: fee_to_insurance_fund.u128() as u64,
-    };
+    }));
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
     Ok(res
-        .add_messages(messages))
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events))
 }
 
 pub fn try_settle_funding_payment(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    subaccount: u64,
 ) -> Result<Response, ContractError> {
     let now = env.block.time.seconds();
-    let user_address = info.sender;
+    let user_address = subaccount_address(&info.sender, subaccount);
 
     let f = controller::funding::settle_funding_payment(&mut deps, &user_address, now)?;
     let state = STATE.load(deps.storage)?;
     let mut messages: Vec<CosmosMsg> = vec![];
-    let message: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: state.history_contract.clone().to_string(),
-        msg: to_binary(&HistoryExecuteMsg::RecordFundingPaymentsMultiple { vecf: f })?,
-        funds: vec![],
-    });
-    messages.push(message);
+    let mut records: Vec<HistoryRecord> = vec![];
+    records.extend(f.into_iter().map(HistoryRecord::FundingPayment));
 
+    let events = history_events(&records);
+    let hooks = hook_messages(deps.storage, &records)?;
+    messages.extend(history_batch_message(&state.history_contract, records)?);
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(hooks)
+        .add_events(events)
         .add_attribute("method", "try_settle_funding_payment"))
 }
@@ -1,9 +1,17 @@
 use crate::helpers::position::calculate_base_asset_value_and_pnl;
 use crate::helpers::position::direction_to_close_position;
-use crate::states::constants::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::states::constants::{
+    DEFAULT_AMM_SNAPSHOT_INTERVAL, DEFAULT_HOOK_GAS_LIMIT, DEFAULT_LIMIT, MAX_LIMIT,
+};
+use crate::states::hooks::{HOOKS, HOOK_GAS_LIMIT};
 use crate::states::market::MARKETS;
-use crate::states::state::{FEESTRUCTURE, ORACLEGUARDRAILS, ORDERSTATE, STATE};
-use crate::states::user::{Position, POSITIONS, USERS};
+use crate::states::state::{
+    AMM_SNAPSHOT_INTERVAL, FEESTRUCTURE, ORACLEGUARDRAILS, ORDERSTATE, STATE,
+};
+use crate::states::user::{
+    subaccount_address, Position, ISOLATED_COLLATERAL, POSITIONS, SUBACCOUNTS, USERS,
+};
+use crate::states::withdrawal::WITHDRAWAL_CLAIMS;
 use crate::ContractError;
 
 use crate::package::helper::addr_validate_to_lower;
@@ -12,13 +20,18 @@ use crate::package::number::Number128;
 use crate::package::response::*;
 
 use crate::package::types::PositionDirection;
-use cosmwasm_std::{Deps, Order, Uint128};
-use cw_storage_plus::{Bound, PrimaryKey};
+use cosmwasm_std::{Deps, Order, StdError, StdResult, Uint128};
+use std::convert::TryInto;
+use cw_storage_plus::{Bound, PrimaryKey, U64Key};
 
-pub fn get_user(deps: Deps, user_address: String) -> Result<Option<UserResponse>, ContractError> {
+pub fn get_user(
+    deps: Deps,
+    user_address: String,
+    subaccount: u64,
+) -> Result<Option<UserResponse>, ContractError> {
     let user = USERS.may_load(
         deps.storage,
-        &addr_validate_to_lower(deps.api, &user_address)?,
+        &subaccount_address(&addr_validate_to_lower(deps.api, &user_address)?, subaccount),
     )?;
     match user {
         Some(user) => {
@@ -47,14 +60,13 @@ pub fn get_user_position(
     deps: Deps,
     user_address: String,
     index: u64,
+    subaccount: u64,
 ) -> Result<Option<PositionResponse>, ContractError> {
-    let position = POSITIONS.may_load(
-        deps.storage,
-        (
-            &addr_validate_to_lower(deps.api, &user_address)?,
-            index.to_string(),
-        ),
-    )?;
+    let user_addr =
+        subaccount_address(&addr_validate_to_lower(deps.api, &user_address)?, subaccount);
+    let position = POSITIONS.may_load(deps.storage, (&user_addr, U64Key::new(index)))?;
+    let isolated_collateral =
+        ISOLATED_COLLATERAL.may_load(deps.storage, (&user_addr, U64Key::new(index)))?;
     match position{
         Some(position) => {
             if position.base_asset_amount.i128().unsigned_abs() == 0 {
@@ -70,6 +82,7 @@ pub fn get_user_position(
             let entry_notional = position.quote_asset_amount;
             let unrealized_pnl = calculate_unrealized_pnl(&deps, position.clone()).unwrap();
             let upr = PositionResponse {
+                market_index: position.market_index,
                 direction,
                 initial_size: Uint128::from(position.base_asset_amount.i128().unsigned_abs()),
                 entry_notional: Number128::new(entry_notional.u128() as i128),
@@ -79,6 +92,7 @@ pub fn get_user_position(
                 last_cumulative_funding_rate: position.last_cumulative_funding_rate,
                 last_cumulative_repeg_rebate: position.last_cumulative_repeg_rebate,
                 last_funding_rate_ts: position.last_funding_rate_ts,
+                isolated_collateral,
             };
             Ok(Some(upr))
         },
@@ -138,7 +152,7 @@ pub fn get_fee_structure(deps: Deps) -> Result<FeeStructureResponse, ContractErr
 }
 
 pub fn get_market_info(deps: Deps, market_index: u64) -> Result<MarketInfoResponse, ContractError> {
-    let market = MARKETS.load(deps.storage, market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(market_index))?;
     let market_info = MarketInfoResponse {
         market_name: market.market_name,
         initialized: market.initialized,
@@ -178,14 +192,16 @@ pub fn get_market_info(deps: Deps, market_index: u64) -> Result<MarketInfoRespon
 pub fn get_active_positions(
     deps: Deps,
     user_address: String,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    subaccount: u64,
 ) -> Result<Vec<PositionResponse>, ContractError> {
-    let user_addr = addr_validate_to_lower(deps.api, user_address.as_str())?;
+    let user_addr =
+        subaccount_address(&addr_validate_to_lower(deps.api, user_address.as_str())?, subaccount);
 
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
     let start = start_after
-        .map(|start| start.joined_key())
+        .map(|start| U64Key::new(start).joined_key())
         .map(Bound::Exclusive);
 
     let active_positions: Vec<PositionResponse> = POSITIONS
@@ -193,6 +209,7 @@ pub fn get_active_positions(
         .range(deps.storage, start, None, Order::Ascending)
         .filter_map(|positions| {
             positions.ok().map(|position| PositionResponse {
+                market_index: position.1.market_index,
                 base_asset_amount: position.1.base_asset_amount,
                 quote_asset_amount: position.1.quote_asset_amount,
                 last_cumulative_funding_rate: position.1.last_cumulative_funding_rate,
@@ -205,6 +222,9 @@ pub fn get_active_positions(
                 },
                 initial_size: Uint128::from(position.1.base_asset_amount.i128().unsigned_abs()),
                 entry_notional: Number128::new(position.1.quote_asset_amount.u128() as i128),
+                isolated_collateral: ISOLATED_COLLATERAL
+                    .may_load(deps.storage, (&user_addr, U64Key::new(position.1.market_index)))
+                    .unwrap_or_default(),
                 pnl: calculate_unrealized_pnl(&deps, position.1).unwrap(),
             })
         })
@@ -214,11 +234,116 @@ pub fn get_active_positions(
     Ok(active_positions)
 }
 
+// outstanding withdrawal claims of the user, oldest first
+pub fn get_hooks(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<HookResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(start) => Some(Bound::exclusive(
+            addr_validate_to_lower(deps.api, &start)?.as_bytes(),
+        )),
+        None => None,
+    };
+
+    let hooks: Vec<HookResponse> = HOOKS
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter_map(|hooks| {
+            hooks.ok().map(|hook| HookResponse {
+                contract: hook.1.contract.to_string(),
+                events: hook.1.events,
+                revert_on_error: hook.1.revert_on_error,
+            })
+        })
+        .take(limit)
+        .collect();
+
+    Ok(hooks)
+}
+
+pub fn get_user_subaccounts(
+    deps: Deps,
+    user_address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Vec<SubaccountResponse>, ContractError> {
+    let user_addr = addr_validate_to_lower(deps.api, user_address.as_str())?;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|start| U64Key::new(start).joined_key())
+        .map(Bound::Exclusive);
+
+    let mut responses = vec![];
+    // accounts opened before 0.4.0 have no entry until they are touched again
+    let listed = SUBACCOUNTS.has(deps.storage, (&user_addr, U64Key::new(0)));
+    if start_after.is_none() && limit > 0 && !listed {
+        if let Some(user) = USERS.may_load(deps.storage, &user_addr)? {
+            responses.push(SubaccountResponse {
+                subaccount: 0,
+                collateral: user.collateral,
+            });
+        }
+    }
+
+    let subaccounts = SUBACCOUNTS
+        .prefix(&user_addr)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit - responses.len())
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (key, account) in subaccounts {
+        let subaccount = u64::from_be_bytes(key.as_slice().try_into().map_err(|_| {
+            StdError::parse_err("u64", "invalid subaccount key")
+        })?);
+        let user = USERS.load(deps.storage, &account)?;
+        responses.push(SubaccountResponse {
+            subaccount,
+            collateral: user.collateral,
+        });
+    }
+
+    Ok(responses)
+}
+
+pub fn get_user_withdrawal_claims(
+    deps: Deps,
+    user_address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Vec<WithdrawalClaimResponse>, ContractError> {
+    let user_addr = addr_validate_to_lower(deps.api, user_address.as_str())?;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|start| U64Key::new(start).joined_key())
+        .map(Bound::Exclusive);
+
+    let claims: Vec<WithdrawalClaimResponse> = WITHDRAWAL_CLAIMS
+        .prefix(&user_addr)
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter_map(|claims| {
+            claims.ok().map(|claim| WithdrawalClaimResponse {
+                claim_id: claim.1.claim_id,
+                ts: claim.1.ts,
+                amount: claim.1.amount,
+                amount_paid: claim.1.amount_paid,
+                outstanding: claim.1.outstanding(),
+            })
+        })
+        .take(limit)
+        .collect();
+
+    Ok(claims)
+}
+
 pub fn calculate_unrealized_pnl(deps: &Deps, m: Position) -> Result<Number128, ContractError> {
     let mut unrealized_pnl: i128 = 0;
-    // let m = POSITIONS.load(deps.storage, (user_addr, n.to_string()))?;
+    // let m = POSITIONS.load(deps.storage, (user_addr, U64Key::new(n)))?;
 
-    let market = MARKETS.load(deps.storage, m.market_index.to_string())?;
+    let market = MARKETS.load(deps.storage, U64Key::new(m.market_index))?;
     let a = &market.amm;
     let (_, amm_position_unrealized_pnl) = calculate_base_asset_value_and_pnl(&m, a)?;
     unrealized_pnl = unrealized_pnl
@@ -250,6 +375,12 @@ pub fn get_global_state(deps: Deps) -> Result<StateResponse, ContractError> {
             .full_liquidation_liquidator_share_denominator,
         max_deposit: state.max_deposit,
         markets_length: state.markets_length,
+        amm_snapshot_interval: AMM_SNAPSHOT_INTERVAL
+            .may_load(deps.storage)?
+            .unwrap_or(DEFAULT_AMM_SNAPSHOT_INTERVAL),
+        hook_gas_limit: HOOK_GAS_LIMIT
+            .may_load(deps.storage)?
+            .unwrap_or(DEFAULT_HOOK_GAS_LIMIT),
     };
     Ok(os)
 }
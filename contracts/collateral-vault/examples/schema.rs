@@ -2,7 +2,7 @@ use std::env::current_dir;
 use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use collateral_vault::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, ConfigResponse, BalanceResponse};
+use collateral_vault::msg::{InstantiateMsg, ExecuteMsg, MigrateMsg, QueryMsg, ConfigResponse, BalanceResponse, ClearingHouseResponse, ReconciliationResponse, WindowUsageResponse, HeldWithdrawalResponse, StrategyResponse};
 use collateral_vault::state::State;
 
 fn main() {
@@ -13,8 +13,14 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(BalanceResponse), &out_dir);
+    export_schema(&schema_for!(ClearingHouseResponse), &out_dir);
+    export_schema(&schema_for!(ReconciliationResponse), &out_dir);
+    export_schema(&schema_for!(WindowUsageResponse), &out_dir);
+    export_schema(&schema_for!(HeldWithdrawalResponse), &out_dir);
+    export_schema(&schema_for!(StrategyResponse), &out_dir);
 }
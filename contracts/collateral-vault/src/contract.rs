@@ -1,19 +1,35 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, to_binary, Addr, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
-    Uint128,
+    coins, to_binary, Addr, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
+    Order, Response, StdResult, Storage, Uint128,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::{Bound, PrimaryKey, U64Key};
 
 use crate::error::ContractError;
-use crate::msg::{BalanceResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{State, ADMIN, STATE};
+use crate::msg::{
+    BalanceResponse, ClearingHouseResponse, ConfigResponse, ExecuteMsg, HeldWithdrawalResponse,
+    InstantiateMsg, MigrateMsg, QueryMsg, ReconciliationResponse, StrategyResponse,
+    WindowUsageResponse,
+};
+use crate::state::{
+    ClearingHouseLedger, HeldWithdrawal, OutflowWindow, Pause, RateLimit, State, Strategy, ADMIN,
+    CLEARING_HOUSES, HELD_WITHDRAWALS, HELD_WITHDRAWALS_V020, OUTFLOW_WINDOW, PAUSE, PENDING_ADMIN,
+    PENDING_CLEARING_HOUSE, RATE_LIMIT, STATE, STRATEGY, WINDOW_SECONDS,
+};
+use crate::strategy::{
+    insurance_donate_msg, query_strategy_balance, strategy_deposit_msg, strategy_withdraw_msg,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:collateral-funds";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// iterator limits
+const MAX_LIMIT: u32 = 20;
+const DEFAULT_LIMIT: u32 = 10;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     mut deps: DepsMut,
@@ -39,50 +55,277 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateAdmin { new_admin } => {
-            let addr = Some(deps.api.addr_validate(&new_admin)?);
-            Ok(ADMIN.execute_update_admin(deps, info, addr)?)
+        ExecuteMsg::ProposeAdmin { new_admin } => propose_admin(deps, info, new_admin),
+        ExecuteMsg::AcceptAdmin {} => accept_admin(deps, info),
+        ExecuteMsg::ProposeClearingHouse { new_clearing_house } => {
+            propose_clearing_house(deps, info, new_clearing_house)
         }
-        ExecuteMsg::UpdateClearingHouse { new_clearing_house } => {
-            change_clearing_house(deps, info, new_clearing_house)
+        ExecuteMsg::AcceptClearingHouse {} => accept_clearing_house(deps, info),
+        ExecuteMsg::AuthorizeClearingHouse {
+            clearing_house,
+            deposit_cap,
+        } => authorize_clearing_house(deps, info, clearing_house, deposit_cap),
+        ExecuteMsg::RevokeClearingHouse { clearing_house } => {
+            revoke_clearing_house(deps, info, clearing_house)
         }
         ExecuteMsg::Deposit {} => deposit(deps, info),
-        ExecuteMsg::Withdraw { to_address, amount } => withdraw(deps, info, to_address, amount),
+        ExecuteMsg::Withdraw { to_address, amount } => {
+            withdraw(deps, env, info, to_address, amount)
+        }
+        ExecuteMsg::UpdateRateLimit {
+            max_per_window,
+            max_per_withdrawal,
+        } => update_rate_limit(deps, info, max_per_window, max_per_withdrawal),
+        ExecuteMsg::ReleaseHeldWithdrawal { id } => release_held_withdrawal(deps, info, id),
+        ExecuteMsg::UpdateGuardian { guardian } => update_guardian(deps, info, guardian),
+        ExecuteMsg::Pause { deposit, withdraw } => pause(deps, info, deposit, withdraw),
+        ExecuteMsg::Unpause { deposit, withdraw } => unpause(deps, info, deposit, withdraw),
+        ExecuteMsg::SetStrategy {
+            contract,
+            insurance_fund,
+            target_ratio,
+        } => set_strategy(deps, info, contract, insurance_fund, target_ratio),
+        ExecuteMsg::RemoveStrategy {} => remove_strategy(deps, info),
+        ExecuteMsg::Rebalance {} => rebalance(deps),
+        ExecuteMsg::Recall { amount } => recall(deps, info, amount),
+        ExecuteMsg::HarvestYield {} => harvest_yield(deps, env),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    // every step moves the stored structs one version forward
+    let mut version = stored.version.clone();
+    if version == "0.1.0" {
+        migrate_from_0_1_0(deps.storage)?;
+        version = "0.2.0".to_string();
+    }
+    if version == "0.2.0" {
+        migrate_from_0_2_0(deps.storage)?;
+        version = "0.3.0".to_string();
+    }
+    if version != CONTRACT_VERSION {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+// 0.2.0 adds the rate limit, outflow window and pause items
+fn migrate_from_0_1_0(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    if RATE_LIMIT.may_load(storage)?.is_none() {
+        RATE_LIMIT.save(storage, &RateLimit::default())?;
+    }
+    if OUTFLOW_WINDOW.may_load(storage)?.is_none() {
+        OUTFLOW_WINDOW.save(storage, &OutflowWindow::default())?;
     }
+    if PAUSE.may_load(storage)?.is_none() {
+        PAUSE.save(storage, &Pause::default())?;
+    }
+    Ok(())
+}
+
+// 0.3.0 moves the deposits into a sub-ledger of the primary clearing house
+// and records the clearing house of every held withdrawal
+fn migrate_from_0_2_0(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let state = STATE.load(storage)?;
+    if !state.clearing_house.as_str().is_empty() {
+        CLEARING_HOUSES.save(
+            storage,
+            &state.clearing_house,
+            &ClearingHouseLedger {
+                balance: state.total_deposit,
+                deposit_cap: Uint128::zero(),
+            },
+        )?;
+    }
+    let held_withdrawals = HELD_WITHDRAWALS_V020
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (k, held) in held_withdrawals {
+        HELD_WITHDRAWALS.save(
+            storage,
+            U64Key::from(k),
+            &HeldWithdrawal {
+                clearing_house: state.clearing_house.clone(),
+                to_address: held.to_address,
+                amount: held.amount,
+                ts: held.ts,
+            },
+        )?;
+    }
+    Ok(())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetConfig {} => to_binary(&query_config(deps)?),
         QueryMsg::GetBalance {} => to_binary(&query_balance(deps)?),
+        QueryMsg::GetClearingHouseBalance { clearing_house } => {
+            to_binary(&query_clearing_house_balance(deps, clearing_house)?)
+        }
+        QueryMsg::GetClearingHouses { start_after, limit } => {
+            to_binary(&query_clearing_houses(deps, start_after, limit)?)
+        }
+        QueryMsg::GetReconciliation {} => to_binary(&query_reconciliation(deps, env)?),
+        QueryMsg::GetWindowUsage {} => to_binary(&query_window_usage(deps, env)?),
+        QueryMsg::GetHeldWithdrawals { start_after, limit } => {
+            to_binary(&query_held_withdrawals(deps, start_after, limit)?)
+        }
+        QueryMsg::GetStrategy {} => to_binary(&query_strategy(deps, env)?),
     }
 }
 
-pub fn change_clearing_house(
+pub fn propose_admin(
     deps: DepsMut,
     info: MessageInfo,
-    clearing_house: Addr,
+    new_admin: String,
 ) -> Result<Response, ContractError> {
-    ADMIN.assert_admin(deps.as_ref(), &info.sender.clone())?;
-    STATE.update(deps.storage, |mut state| -> Result<State, ContractError> {
-        state.clearing_house = clearing_house.clone();
-        Ok(state)
-    })?;
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let new_admin = deps.api.addr_validate(&new_admin)?;
+    PENDING_ADMIN.save(deps.storage, &new_admin)?;
+    Ok(Response::new()
+        .add_attribute("method", "propose_admin")
+        .add_attribute("pending_admin", new_admin))
+}
+
+pub fn accept_admin(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending_admin = PENDING_ADMIN.may_load(deps.storage)?;
+    if pending_admin.as_ref() != Some(&info.sender) {
+        return Err(ContractError::UnauthorizedPending {});
+    }
+    PENDING_ADMIN.remove(deps.storage);
+    ADMIN.set(deps, pending_admin)?;
+    Ok(Response::new()
+        .add_attribute("method", "accept_admin")
+        .add_attribute("admin", info.sender))
+}
+
+pub fn propose_clearing_house(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_clearing_house: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let new_clearing_house = deps.api.addr_validate(&new_clearing_house)?;
+    PENDING_CLEARING_HOUSE.save(deps.storage, &new_clearing_house)?;
+    Ok(Response::new()
+        .add_attribute("method", "propose_clearing_house")
+        .add_attribute("pending_clearing_house", new_clearing_house))
+}
+
+pub fn accept_clearing_house(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending_clearing_house = PENDING_CLEARING_HOUSE.may_load(deps.storage)?;
+    if pending_clearing_house.as_ref() != Some(&info.sender) {
+        return Err(ContractError::UnauthorizedPending {});
+    }
+    PENDING_CLEARING_HOUSE.remove(deps.storage);
+    let mut state = STATE.load(deps.storage)?;
+
+    // the new clearing house takes over the sub-ledger of the one it replaces
+    let mut ledger = CLEARING_HOUSES
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    if state.clearing_house != info.sender {
+        if let Some(previous) = CLEARING_HOUSES.may_load(deps.storage, &state.clearing_house)? {
+            ledger.balance = ledger.balance.checked_add(previous.balance)?;
+            CLEARING_HOUSES.remove(deps.storage, &state.clearing_house);
+        }
+    }
+    CLEARING_HOUSES.save(deps.storage, &info.sender, &ledger)?;
+    state.clearing_house = info.sender.clone();
+    STATE.save(deps.storage, &state)?;
+    Ok(Response::new()
+        .add_attribute("method", "accept_clearing_house")
+        .add_attribute("clearing_house", info.sender))
+}
+
+// a secondary clearing house can only withdraw what it deposited itself,
+// so unlike the primary one it is authorized without an accept step
+pub fn authorize_clearing_house(
+    deps: DepsMut,
+    info: MessageInfo,
+    clearing_house: String,
+    deposit_cap: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let clearing_house = deps.api.addr_validate(&clearing_house)?;
+    CLEARING_HOUSES.update(
+        deps.storage,
+        &clearing_house,
+        |ledger| -> Result<ClearingHouseLedger, ContractError> {
+            let mut ledger = ledger.unwrap_or_default();
+            ledger.deposit_cap = deposit_cap;
+            Ok(ledger)
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("method", "authorize_clearing_house")
+        .add_attribute("clearing_house", clearing_house)
+        .add_attribute("deposit_cap", deposit_cap))
+}
+
+pub fn revoke_clearing_house(
+    deps: DepsMut,
+    info: MessageInfo,
+    clearing_house: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let clearing_house = deps.api.addr_validate(&clearing_house)?;
+    let state = STATE.load(deps.storage)?;
+    if clearing_house == state.clearing_house {
+        return Err(ContractError::PrimaryClearingHouse {});
+    }
+    let ledger = CLEARING_HOUSES.load(deps.storage, &clearing_house)?;
+    if !ledger.balance.is_zero() {
+        return Err(ContractError::ClearingHouseNotEmpty {});
+    }
+    CLEARING_HOUSES.remove(deps.storage, &clearing_house);
     Ok(Response::new()
-        .add_attribute("method", "update_clearing_house")
-        .add_attribute("new_clearing_house", clearing_house.clone()))
+        .add_attribute("method", "revoke_clearing_house")
+        .add_attribute("clearing_house", clearing_house))
+}
+
+fn load_clearing_house(
+    storage: &dyn Storage,
+    clearing_house: &Addr,
+) -> Result<ClearingHouseLedger, ContractError> {
+    CLEARING_HOUSES
+        .may_load(storage, clearing_house)?
+        .ok_or(ContractError::UnauthorizedClearingHouse {})
 }
 
 pub fn deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
     let mut state: State = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
+    let mut ledger = load_clearing_house(deps.storage, &info.sender)?;
+
+    if PAUSE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .deposit_paused
+    {
+        return Err(ContractError::DepositPaused {});
     }
 
     if info.funds.len() != 1 {
@@ -93,6 +336,12 @@ pub fn deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractErr
         return Err(ContractError::InvalidIncomingAsset {});
     }
 
+    ledger.balance = ledger.balance.checked_add(info.funds[0].amount)?;
+    if !ledger.deposit_cap.is_zero() && ledger.balance > ledger.deposit_cap {
+        return Err(ContractError::DepositCapExceeded {});
+    }
+    CLEARING_HOUSES.save(deps.storage, &info.sender, &ledger)?;
+
     state.total_deposit = state.total_deposit.checked_add(info.funds[0].amount)?;
     STATE.update(deps.storage, |_s| -> Result<State, ContractError> {
         Ok(state)
@@ -104,45 +353,348 @@ pub fn deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractErr
 
 pub fn withdraw(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     to: String,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let mut state: State = STATE.load(deps.storage)?;
     let amount = Uint128::from(amount);
+    let now = env.block.time.seconds();
+    let mut ledger = load_clearing_house(deps.storage, &info.sender)?;
 
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
+    if PAUSE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .withdraw_paused
+    {
+        return Err(ContractError::WithdrawPaused {});
     }
 
-    if amount.gt(&state.total_deposit) {
+    // a clearing house can only withdraw from its own sub-ledger
+    if amount.gt(&ledger.balance) {
         return Err(ContractError::InsufficientFunds {});
     };
+    ledger.balance = ledger.balance.checked_sub(amount)?;
+    CLEARING_HOUSES.save(deps.storage, &info.sender, &ledger)?;
 
+    // whatever goes over the per withdrawal or the rolling window cap is held for the admin to release
+    let mut rate_limit = RATE_LIMIT.may_load(deps.storage)?.unwrap_or_default();
+    let tracked = state.total_deposit.checked_add(rate_limit.total_held)?;
     state.total_deposit = state.total_deposit.checked_sub(amount)?;
 
-    let send_tx_msg = BankMsg::Send {
-        to_address: to,
-        amount: coins(amount.u128(), state.denom_stable.clone()),
-    };
+    let mut window = OUTFLOW_WINDOW.may_load(deps.storage)?.unwrap_or_default();
+    window.prune(now);
+
+    let mut amount_sent = amount;
+    if !rate_limit.max_per_withdrawal.is_zero() {
+        amount_sent = amount_sent.min(rate_limit.max_per_withdrawal);
+    }
+    if !rate_limit.max_per_window.is_zero() {
+        amount_sent = amount_sent.min(rate_limit.max_per_window.saturating_sub(window.used()));
+    }
+    let amount_held = amount.checked_sub(amount_sent)?;
+
+    let mut res = Response::new();
+    if !amount_sent.is_zero() {
+        window.record(now, amount_sent);
+        if let Some(recall_msg) = recall_for_payout(deps.storage, tracked, amount_sent)? {
+            res = res.add_message(recall_msg);
+        }
+        res = res.add_message(BankMsg::Send {
+            to_address: to.clone(),
+            amount: coins(amount_sent.u128(), state.denom_stable.clone()),
+        });
+    }
+    if !amount_held.is_zero() {
+        let id = rate_limit.next_held_id;
+        HELD_WITHDRAWALS.save(
+            deps.storage,
+            U64Key::new(id),
+            &HeldWithdrawal {
+                clearing_house: info.sender.clone(),
+                to_address: to,
+                amount: amount_held,
+                ts: now,
+            },
+        )?;
+        rate_limit.next_held_id = id + 1;
+        rate_limit.total_held = rate_limit.total_held.checked_add(amount_held)?;
+        res = res
+            .add_attribute("held_id", id.to_string())
+            .add_attribute("held_amount", amount_held);
+    }
 
     STATE.update(deps.storage, |_s| -> Result<State, ContractError> {
         Ok(state)
     })?;
+    RATE_LIMIT.save(deps.storage, &rate_limit)?;
+    OUTFLOW_WINDOW.save(deps.storage, &window)?;
 
-    Ok(Response::new()
-        .add_message(send_tx_msg)
+    Ok(res
         .add_attribute("method", "withdraw_collateral")
+        .add_attribute("amount", amount_sent))
+}
+
+pub fn update_rate_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_per_window: Uint128,
+    max_per_withdrawal: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut rate_limit = RATE_LIMIT.may_load(deps.storage)?.unwrap_or_default();
+    rate_limit.max_per_window = max_per_window;
+    rate_limit.max_per_withdrawal = max_per_withdrawal;
+    RATE_LIMIT.save(deps.storage, &rate_limit)?;
+    Ok(Response::new()
+        .add_attribute("method", "update_rate_limit")
+        .add_attribute("max_per_window", max_per_window)
+        .add_attribute("max_per_withdrawal", max_per_withdrawal))
+}
+
+// held withdrawals are released as is and do not count towards the window
+pub fn release_held_withdrawal(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let state = STATE.load(deps.storage)?;
+    let held = HELD_WITHDRAWALS.load(deps.storage, U64Key::new(id))?;
+    HELD_WITHDRAWALS.remove(deps.storage, U64Key::new(id));
+    let rate_limit = RATE_LIMIT.update(
+        deps.storage,
+        |mut rate_limit| -> Result<RateLimit, ContractError> {
+            rate_limit.total_held = rate_limit.total_held.checked_sub(held.amount)?;
+            Ok(rate_limit)
+        },
+    )?;
+    let tracked = state
+        .total_deposit
+        .checked_add(rate_limit.total_held)?
+        .checked_add(held.amount)?;
+    let mut res = Response::new();
+    if let Some(recall_msg) = recall_for_payout(deps.storage, tracked, held.amount)? {
+        res = res.add_message(recall_msg);
+    }
+    Ok(res
+        .add_message(BankMsg::Send {
+            to_address: held.to_address.clone(),
+            amount: coins(held.amount.u128(), state.denom_stable),
+        })
+        .add_attribute("method", "release_held_withdrawal")
+        .add_attribute("to_address", held.to_address)
+        .add_attribute("amount", held.amount))
+}
+
+pub fn update_guardian(
+    deps: DepsMut,
+    info: MessageInfo,
+    guardian: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let guardian = deps.api.addr_validate(&guardian)?;
+    let mut pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    pause.guardian = Some(guardian.clone());
+    PAUSE.save(deps.storage, &pause)?;
+    Ok(Response::new()
+        .add_attribute("method", "update_guardian")
+        .add_attribute("guardian", guardian))
+}
+
+// the guardian (or the admin) can only freeze, lifting a pause is left to the admin
+pub fn pause(
+    deps: DepsMut,
+    info: MessageInfo,
+    deposit: bool,
+    withdraw: bool,
+) -> Result<Response, ContractError> {
+    let mut pause: Pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    if pause.guardian.as_ref() != Some(&info.sender)
+        && !(ADMIN.is_admin(deps.as_ref(), &info.sender)?)
+    {
+        return Err(ContractError::UnauthorizedGuardian {});
+    }
+    pause.deposit_paused = pause.deposit_paused || deposit;
+    pause.withdraw_paused = pause.withdraw_paused || withdraw;
+    PAUSE.save(deps.storage, &pause)?;
+    Ok(Response::new()
+        .add_attribute("method", "pause")
+        .add_attribute("deposit_paused", pause.deposit_paused.to_string())
+        .add_attribute("withdraw_paused", pause.withdraw_paused.to_string()))
+}
+
+pub fn unpause(
+    deps: DepsMut,
+    info: MessageInfo,
+    deposit: bool,
+    withdraw: bool,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut pause: Pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    pause.deposit_paused = pause.deposit_paused && !deposit;
+    pause.withdraw_paused = pause.withdraw_paused && !withdraw;
+    PAUSE.save(deps.storage, &pause)?;
+    Ok(Response::new()
+        .add_attribute("method", "unpause")
+        .add_attribute("deposit_paused", pause.deposit_paused.to_string())
+        .add_attribute("withdraw_paused", pause.withdraw_paused.to_string()))
+}
+
+// recalls from the strategy whatever the vault is missing to pay out `amount`,
+// `tracked` is everything the vault owes (deposits and held withdrawals) before the payout
+fn recall_for_payout(
+    storage: &mut dyn Storage,
+    tracked: Uint128,
+    amount: Uint128,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    let mut strategy = match STRATEGY.may_load(storage)? {
+        Some(strategy) => strategy,
+        None => return Ok(None),
+    };
+    let liquid = tracked.saturating_sub(strategy.deployed);
+    if amount <= liquid {
+        return Ok(None);
+    }
+    let recalled = amount.checked_sub(liquid)?.min(strategy.deployed);
+    strategy.deployed = strategy.deployed.checked_sub(recalled)?;
+    STRATEGY.save(storage, &strategy)?;
+    Ok(Some(strategy_withdraw_msg(&strategy.contract, recalled)?))
+}
+
+pub fn set_strategy(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+    insurance_fund: String,
+    target_ratio: Decimal,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if target_ratio > Decimal::one() {
+        return Err(ContractError::InvalidStrategyRatio {});
+    }
+    let contract = deps.api.addr_validate(&contract)?;
+    let insurance_fund = deps.api.addr_validate(&insurance_fund)?;
+
+    // funds have to be recalled before switching to another yield contract
+    let deployed = match STRATEGY.may_load(deps.storage)? {
+        Some(strategy) if strategy.contract != contract && !strategy.deployed.is_zero() => {
+            return Err(ContractError::StrategyNotEmpty {})
+        }
+        Some(strategy) if strategy.contract == contract => strategy.deployed,
+        _ => Uint128::zero(),
+    };
+    STRATEGY.save(
+        deps.storage,
+        &Strategy {
+            contract: contract.clone(),
+            insurance_fund,
+            target_ratio,
+            deployed,
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("method", "set_strategy")
+        .add_attribute("contract", contract)
+        .add_attribute("target_ratio", target_ratio.to_string()))
+}
+
+pub fn remove_strategy(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let strategy = STRATEGY.load(deps.storage)?;
+    if !strategy.deployed.is_zero() {
+        return Err(ContractError::StrategyNotEmpty {});
+    }
+    STRATEGY.remove(deps.storage);
+    Ok(Response::new().add_attribute("method", "remove_strategy"))
+}
+
+// moves the deployed principal towards target_ratio of total_deposit, anyone can call it
+pub fn rebalance(deps: DepsMut) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let mut strategy = STRATEGY
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoStrategy {})?;
+    let target = state.total_deposit * strategy.target_ratio;
+
+    let mut res = Response::new();
+    if target > strategy.deployed {
+        let amount = target.checked_sub(strategy.deployed)?;
+        strategy.deployed = target;
+        res = res
+            .add_message(strategy_deposit_msg(
+                &strategy.contract,
+                amount,
+                &state.denom_stable,
+            )?)
+            .add_attribute("deployed_amount", amount);
+    } else if target < strategy.deployed {
+        let amount = strategy.deployed.checked_sub(target)?;
+        strategy.deployed = target;
+        res = res
+            .add_message(strategy_withdraw_msg(&strategy.contract, amount)?)
+            .add_attribute("recalled_amount", amount);
+    }
+    STRATEGY.save(deps.storage, &strategy)?;
+    Ok(res.add_attribute("method", "rebalance"))
+}
+
+pub fn recall(
+    deps: DepsMut,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut strategy = STRATEGY
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoStrategy {})?;
+    let amount = amount.min(strategy.deployed);
+    strategy.deployed = strategy.deployed.checked_sub(amount)?;
+    STRATEGY.save(deps.storage, &strategy)?;
+    Ok(Response::new()
+        .add_message(strategy_withdraw_msg(&strategy.contract, amount)?)
+        .add_attribute("method", "recall")
         .add_attribute("amount", amount))
 }
 
+// sends everything the strategy earned above the deployed principal to the insurance fund
+pub fn harvest_yield(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let strategy = STRATEGY
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoStrategy {})?;
+    let balance = query_strategy_balance(&deps.querier, &strategy.contract, &env.contract.address)?;
+    let earned = balance.saturating_sub(strategy.deployed);
+
+    let mut res = Response::new();
+    if !earned.is_zero() {
+        res = res
+            .add_message(strategy_withdraw_msg(&strategy.contract, earned)?)
+            .add_message(insurance_donate_msg(
+                &strategy.insurance_fund,
+                earned,
+                &state.denom_stable,
+            )?);
+    }
+    Ok(res
+        .add_attribute("method", "harvest_yield")
+        .add_attribute("amount", earned))
+}
+
 fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let state = STATE.load(deps.storage)?;
+    let pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
     let res = ADMIN.query_admin(deps).unwrap();
     Ok(ConfigResponse {
         clearing_house: state.clearing_house,
         admin: res.admin.unwrap(),
+        pending_admin: PENDING_ADMIN.may_load(deps.storage)?,
+        pending_clearing_house: PENDING_CLEARING_HOUSE.may_load(deps.storage)?,
         denom: state.denom_stable,
+        guardian: pause.guardian,
+        deposit_paused: pause.deposit_paused,
+        withdraw_paused: pause.withdraw_paused,
     })
 }
 
@@ -152,3 +704,138 @@ fn query_balance(deps: Deps) -> StdResult<BalanceResponse> {
         balance: state.total_deposit,
     })
 }
+
+fn query_clearing_house_balance(deps: Deps, clearing_house: String) -> StdResult<BalanceResponse> {
+    let clearing_house = deps.api.addr_validate(&clearing_house)?;
+    let ledger = CLEARING_HOUSES
+        .may_load(deps.storage, &clearing_house)?
+        .unwrap_or_default();
+    Ok(BalanceResponse {
+        balance: ledger.balance,
+    })
+}
+
+fn query_clearing_houses(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ClearingHouseResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(start) => Some(Bound::exclusive(deps.api.addr_validate(&start)?.as_str())),
+        None => None,
+    };
+
+    CLEARING_HOUSES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, ledger) = item?;
+            Ok(ClearingHouseResponse {
+                clearing_house: Addr::unchecked(String::from_utf8(k)?),
+                balance: ledger.balance,
+                deposit_cap: ledger.deposit_cap,
+            })
+        })
+        .collect()
+}
+
+/// Compares the deposits tracked by the vault with the denom balance actually held by the contract.
+/// Anything sent to the vault outside of `Deposit` shows up as surplus and is never paid out.
+/// Withdrawals held by the rate limit are still in the contract and count as tracked,
+/// principal deployed into the yield strategy does not.
+fn query_reconciliation(deps: Deps, env: Env) -> StdResult<ReconciliationResponse> {
+    let state = STATE.load(deps.storage)?;
+    let total_held = RATE_LIMIT
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .total_held;
+    let deployed = STRATEGY
+        .may_load(deps.storage)?
+        .map(|strategy| strategy.deployed)
+        .unwrap_or_default();
+    let tracked = state
+        .total_deposit
+        .checked_add(total_held)?
+        .saturating_sub(deployed);
+    let bank_balance = deps
+        .querier
+        .query_balance(env.contract.address, state.denom_stable)?
+        .amount;
+    let (surplus, deficit) = if bank_balance >= tracked {
+        (bank_balance.checked_sub(tracked)?, Uint128::zero())
+    } else {
+        (Uint128::zero(), tracked.checked_sub(bank_balance)?)
+    };
+    Ok(ReconciliationResponse {
+        total_deposit: state.total_deposit,
+        total_held,
+        deployed,
+        bank_balance,
+        surplus,
+        deficit,
+    })
+}
+
+fn query_window_usage(deps: Deps, env: Env) -> StdResult<WindowUsageResponse> {
+    let rate_limit = RATE_LIMIT.may_load(deps.storage)?.unwrap_or_default();
+    let mut window = OUTFLOW_WINDOW.may_load(deps.storage)?.unwrap_or_default();
+    window.prune(env.block.time.seconds());
+    let used = window.used();
+    Ok(WindowUsageResponse {
+        window_seconds: WINDOW_SECONDS,
+        max_per_window: rate_limit.max_per_window,
+        max_per_withdrawal: rate_limit.max_per_withdrawal,
+        used,
+        remaining: if rate_limit.max_per_window.is_zero() {
+            None
+        } else {
+            Some(rate_limit.max_per_window.saturating_sub(used))
+        },
+        total_held: rate_limit.total_held,
+    })
+}
+
+fn query_held_withdrawals(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<HeldWithdrawalResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|start| U64Key::new(start).joined_key())
+        .map(Bound::Exclusive);
+
+    HELD_WITHDRAWALS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, held) = item?;
+            let mut id = [0u8; 8];
+            id.copy_from_slice(&k);
+            Ok(HeldWithdrawalResponse {
+                id: u64::from_be_bytes(id),
+                to_address: held.to_address,
+                amount: held.amount,
+                ts: held.ts,
+            })
+        })
+        .collect()
+}
+
+fn query_strategy(deps: Deps, env: Env) -> StdResult<Option<StrategyResponse>> {
+    match STRATEGY.may_load(deps.storage)? {
+        Some(strategy) => Ok(Some(StrategyResponse {
+            strategy_balance: query_strategy_balance(
+                &deps.querier,
+                &strategy.contract,
+                &env.contract.address,
+            )?,
+            contract: strategy.contract,
+            insurance_fund: strategy.insurance_fund,
+            target_ratio: strategy.target_ratio,
+            deployed: strategy.deployed,
+        })),
+        None => Ok(None),
+    }
+}
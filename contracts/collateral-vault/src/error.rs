@@ -16,6 +16,30 @@ pub enum ContractError {
     #[error("Clearing house only")]
     UnauthorizedClearingHouse {},
 
+    #[error("Clearing house deposit cap exceeded")]
+    DepositCapExceeded {},
+
+    #[error("Clearing house still holds deposits")]
+    ClearingHouseNotEmpty {},
+
+    #[error("The primary clearing house can only be replaced")]
+    PrimaryClearingHouse {},
+
+    #[error("Proposed address only")]
+    UnauthorizedPending {},
+
+    #[error("Guardian only")]
+    UnauthorizedGuardian {},
+
+    #[error("Deposits are paused")]
+    DepositPaused {},
+
+    #[error("Withdrawals are paused")]
+    WithdrawPaused {},
+
+    #[error("Cannot migrate from {contract} {version}")]
+    InvalidMigration { contract: String, version: String },
+
     #[error("Math error")]
     MathError {},
 
@@ -24,6 +48,15 @@ pub enum ContractError {
 
     #[error("Insufficient funds")]
     InsufficientFunds {},
+
+    #[error("Strategy ratio must not exceed 1")]
+    InvalidStrategyRatio {},
+
+    #[error("Strategy still holds deployed funds")]
+    StrategyNotEmpty {},
+
+    #[error("No strategy configured")]
+    NoStrategy {},
 }
 
 impl From<OverflowError> for ContractError {
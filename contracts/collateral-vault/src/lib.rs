@@ -2,6 +2,7 @@ pub mod contract;
 mod error;
 pub mod msg;
 pub mod state;
+pub mod strategy;
 pub mod test;
 
 pub use crate::error::ContractError;
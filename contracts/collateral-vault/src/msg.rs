@@ -1,4 +1,4 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -10,18 +10,41 @@ pub struct InstantiateMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    UpdateAdmin { new_admin : String },
-    UpdateClearingHouse { new_clearing_house: Addr },
+    ProposeAdmin { new_admin: String },
+    AcceptAdmin {},
+    ProposeClearingHouse { new_clearing_house: String },
+    AcceptClearingHouse {},
+    AuthorizeClearingHouse { clearing_house: String, deposit_cap: Uint128 },
+    RevokeClearingHouse { clearing_house: String },
     Deposit {},
     Withdraw { to_address: String, amount: Uint128 },
+    UpdateRateLimit { max_per_window: Uint128, max_per_withdrawal: Uint128 },
+    ReleaseHeldWithdrawal { id: u64 },
+    UpdateGuardian { guardian: String },
+    Pause { deposit: bool, withdraw: bool },
+    Unpause { deposit: bool, withdraw: bool },
+    SetStrategy { contract: String, insurance_fund: String, target_ratio: Decimal },
+    RemoveStrategy {},
+    Rebalance {},
+    Recall { amount: Uint128 },
+    HarvestYield {},
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     // GetCount returns the current count as a json-encoded number
     GetConfig {},
     GetBalance {},
+    GetClearingHouseBalance { clearing_house: String },
+    GetClearingHouses { start_after: Option<String>, limit: Option<u32> },
+    GetReconciliation {},
+    GetWindowUsage {},
+    GetHeldWithdrawals { start_after: Option<u64>, limit: Option<u32> },
+    GetStrategy {},
 }
 
 // We define a custom struct for each query response
@@ -29,10 +52,62 @@ pub enum QueryMsg {
 pub struct ConfigResponse {
     pub clearing_house: Addr,
     pub admin: String,
-    pub denom: String
+    pub pending_admin: Option<Addr>,
+    pub pending_clearing_house: Option<Addr>,
+    pub denom: String,
+    pub guardian: Option<Addr>,
+    pub deposit_paused: bool,
+    pub withdraw_paused: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BalanceResponse {
     pub balance : Uint128,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClearingHouseResponse {
+    pub clearing_house: Addr,
+    pub balance: Uint128,
+    // zero means no cap
+    pub deposit_cap: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReconciliationResponse {
+    pub total_deposit: Uint128,
+    pub total_held: Uint128,
+    pub deployed: Uint128,
+    pub bank_balance: Uint128,
+    pub surplus: Uint128,
+    pub deficit: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WindowUsageResponse {
+    pub window_seconds: u64,
+    pub max_per_window: Uint128,
+    pub max_per_withdrawal: Uint128,
+    pub used: Uint128,
+    // None when there is no window cap
+    pub remaining: Option<Uint128>,
+    pub total_held: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HeldWithdrawalResponse {
+    pub id: u64,
+    pub to_address: String,
+    pub amount: Uint128,
+    pub ts: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StrategyResponse {
+    pub contract: Addr,
+    pub insurance_fund: Addr,
+    pub target_ratio: Decimal,
+    pub deployed: Uint128,
+    // principal plus yield as reported by the strategy
+    pub strategy_balance: Uint128,
+}
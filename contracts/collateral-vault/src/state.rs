@@ -1,16 +1,110 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Uint128, Addr};
+use cosmwasm_std::{Addr, Decimal, Uint128};
 use cw_controllers::Admin;
-use cw_storage_plus::Item;
+use cw_storage_plus::{Item, Map, U64Key};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
+    // primary clearing house, the one handed over through propose/accept
     pub clearing_house: Addr,
+    // sum of the clearing house sub-ledgers
     pub total_deposit: Uint128,
     pub denom_stable: String
 }
 
+// sub-ledger of an authorized clearing house, a zero deposit_cap means no cap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ClearingHouseLedger {
+    pub balance: Uint128,
+    pub deposit_cap: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Pause {
+    pub guardian: Option<Addr>,
+    pub deposit_paused: bool,
+    pub withdraw_paused: bool,
+}
+
+// zero means no cap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct RateLimit {
+    pub max_per_window: Uint128,
+    pub max_per_withdrawal: Uint128,
+    pub total_held: Uint128,
+    pub next_held_id: u64,
+}
+
+// outflow of the rolling window, bucketed per hour
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct OutflowWindow {
+    pub buckets: Vec<(u64, Uint128)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HeldWithdrawal {
+    // the sub-ledger the withdrawal was taken from
+    pub clearing_house: Addr,
+    pub to_address: String,
+    pub amount: Uint128,
+    pub ts: u64,
+}
+
+// idle collateral deployed into an external yield contract, the yield goes to the insurance fund
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Strategy {
+    pub contract: Addr,
+    pub insurance_fund: Addr,
+    // fraction of total_deposit to deploy, the rest stays liquid for withdrawals
+    pub target_ratio: Decimal,
+    // principal currently deployed
+    pub deployed: Uint128,
+}
+
+// HeldWithdrawal as stored up to 0.2.0, before the vault kept a sub-ledger per clearing house
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HeldWithdrawalV020 {
+    pub to_address: String,
+    pub amount: Uint128,
+    pub ts: u64,
+}
+
+pub const WINDOW_SECONDS: u64 = 86400;
+pub const BUCKET_SECONDS: u64 = 3600;
+
 pub const STATE: Item<State> = Item::new("state");
-pub const ADMIN: Admin = Admin::new("admin");
\ No newline at end of file
+pub const ADMIN: Admin = Admin::new("admin");
+// proposed addresses, they take over once they accept
+pub const PENDING_ADMIN: Item<Addr> = Item::new("pending_admin");
+pub const PENDING_CLEARING_HOUSE: Item<Addr> = Item::new("pending_clearing_house");
+pub const CLEARING_HOUSES: Map<&Addr, ClearingHouseLedger> = Map::new("clearing_houses");
+pub const PAUSE: Item<Pause> = Item::new("pause");
+pub const RATE_LIMIT: Item<RateLimit> = Item::new("rate_limit");
+pub const OUTFLOW_WINDOW: Item<OutflowWindow> = Item::new("outflow_window");
+pub const STRATEGY: Item<Strategy> = Item::new("strategy");
+pub const HELD_WITHDRAWALS: Map<U64Key, HeldWithdrawal> = Map::new("held_withdrawals");
+pub const HELD_WITHDRAWALS_V020: Map<U64Key, HeldWithdrawalV020> = Map::new("held_withdrawals");
+
+impl OutflowWindow {
+    // drops the buckets that fell out of the window ending at `now`
+    pub fn prune(&mut self, now: u64) {
+        let window_start = now.saturating_sub(WINDOW_SECONDS);
+        self.buckets.retain(|(bucket_ts, _)| bucket_ts + BUCKET_SECONDS > window_start);
+    }
+
+    pub fn used(&self) -> Uint128 {
+        self.buckets
+            .iter()
+            .fold(Uint128::zero(), |acc, (_, amount)| acc + *amount)
+    }
+
+    pub fn record(&mut self, now: u64, amount: Uint128) {
+        let bucket_ts = now - now % BUCKET_SECONDS;
+        match self.buckets.last_mut() {
+            Some((ts, bucket_amount)) if *ts == bucket_ts => *bucket_amount += amount,
+            _ => self.buckets.push((bucket_ts, amount)),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use cosmwasm_std::{
+    coins, to_binary, Addr, CosmosMsg, QuerierWrapper, QueryRequest, StdResult, Uint128, WasmMsg,
+    WasmQuery,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// interface the yield contract behind the strategy adapter has to expose
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyExecuteMsg {
+    // deposits the attached funds
+    Deposit {},
+    // sends `amount` back to the caller
+    Withdraw { amount: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyQueryMsg {
+    // principal plus accrued yield held for `address`
+    Balance { address: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StrategyBalanceResponse {
+    pub balance: Uint128,
+}
+
+// insurance vault message the harvested yield is sent with
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InsuranceInterface {
+    Donate {},
+}
+
+pub fn strategy_deposit_msg(strategy: &Addr, amount: Uint128, denom: &str) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: strategy.to_string(),
+        msg: to_binary(&StrategyExecuteMsg::Deposit {})?,
+        funds: coins(amount.u128(), denom),
+    }))
+}
+
+pub fn strategy_withdraw_msg(strategy: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: strategy.to_string(),
+        msg: to_binary(&StrategyExecuteMsg::Withdraw { amount })?,
+        funds: vec![],
+    }))
+}
+
+pub fn insurance_donate_msg(
+    insurance_fund: &Addr,
+    amount: Uint128,
+    denom: &str,
+) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: insurance_fund.to_string(),
+        msg: to_binary(&InsuranceInterface::Donate {})?,
+        funds: coins(amount.u128(), denom),
+    }))
+}
+
+pub fn query_strategy_balance(
+    querier: &QuerierWrapper,
+    strategy: &Addr,
+    address: &Addr,
+) -> StdResult<Uint128> {
+    let res: StrategyBalanceResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: strategy.to_string(),
+        msg: to_binary(&StrategyQueryMsg::Balance {
+            address: address.to_string(),
+        })?,
+    }))?;
+    Ok(res.balance)
+}
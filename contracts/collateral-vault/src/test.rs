@@ -1,11 +1,25 @@
 #[cfg(test)]
 mod tests {
-    use crate::contract::{instantiate, query, deposit, change_clearing_house, execute, withdraw};
-    use crate::msg::{InstantiateMsg, ConfigResponse, QueryMsg, BalanceResponse, ExecuteMsg};
+    use crate::contract::{instantiate, query, deposit, accept_clearing_house, propose_clearing_house, execute, withdraw, migrate};
+    use crate::msg::{
+        BalanceResponse, ClearingHouseResponse, ConfigResponse, ExecuteMsg, HeldWithdrawalResponse,
+        InstantiateMsg, MigrateMsg, QueryMsg, ReconciliationResponse, StrategyResponse,
+        WindowUsageResponse,
+    };
+    use crate::strategy::{
+        InsuranceInterface, StrategyBalanceResponse, StrategyExecuteMsg, StrategyQueryMsg,
+    };
 
     
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary, Uint128, Addr};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        coins, from_binary, from_slice, to_binary, Addr, ContractResult, CosmosMsg, Decimal, Empty,
+        OwnedDeps, Querier, QuerierResult, QueryRequest, SystemResult, Uint128, WasmMsg, WasmQuery,
+    };
+    use std::cell::RefCell;
+    use cw2::{get_contract_version, set_contract_version};
 
     // initlization and verify data
     // #[test]
@@ -74,22 +88,30 @@ mod tests {
         assert_eq!(Addr::unchecked("testaddr"), value.clearing_house);
         let dep_info = mock_info("creator", &coins(1000000, "uusd"));
 
-        change_clearing_house(
+        propose_clearing_house(
             deps.as_mut(),
             dep_info.clone(),
-            Addr::unchecked("newclearing"),
+            "newclearing".to_string(),
         )
         .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
 
         execute(
             deps.as_mut(),
             mock_env(),
             dep_info.clone(),
-            ExecuteMsg::UpdateAdmin {
+            ExecuteMsg::ProposeAdmin {
                 new_admin: "newadmin".to_string(),
             },
         )
         .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newadmin", &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap();
         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
         let value: ConfigResponse = from_binary(&res).unwrap();
         assert_eq!(Addr::unchecked("newclearing"), value.clearing_house);
@@ -112,12 +134,13 @@ mod tests {
         // we can just call .unwrap() to assert this was a success
         instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        change_clearing_house(
+        propose_clearing_house(
             deps.as_mut(),
             mock_info("creator", &coins(0, "uusd")),
-            Addr::unchecked("newclearing"),
+            "newclearing".to_string(),
         )
         .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
         let value: BalanceResponse = from_binary(&res).unwrap();
         assert_eq!(Uint128::from(0u64), value.balance);
@@ -136,10 +159,599 @@ mod tests {
         let d_info = mock_info("newclearing", &coins(1000000, "uusd"));
         withdraw(
             deps.as_mut(),
+            mock_env(),
             d_info,
             "testaddr".to_string(),
             Uint128::from(1000000u64),
         )
         .unwrap();
     }
+
+    #[test]
+    fn proper_reconciliation() {
+        // the vault holds 500000 uusd more than was deposited through the clearing house
+        let mut deps = mock_dependencies(&coins(1500000, "uusd"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &coins(0, "uusd")),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000000, "uusd"))).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReconciliation {}).unwrap();
+        let value: ReconciliationResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000000u64), value.total_deposit);
+        assert_eq!(Uint128::from(1500000u64), value.bank_balance);
+        assert_eq!(Uint128::from(500000u64), value.surplus);
+        assert_eq!(Uint128::zero(), value.deficit);
+
+        // stray funds are not counted as withdrawable balance
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000000u64), value.balance);
+    }
+
+    #[test]
+    fn proper_withdraw_rate_limit() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &coins(0, "uusd")),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000000, "uusd"))).unwrap();
+
+        // only the admin can set the caps
+        let rate_limit_msg = ExecuteMsg::UpdateRateLimit {
+            max_per_window: Uint128::from(500000u64),
+            max_per_withdrawal: Uint128::from(300000u64),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("newclearing", &[]), rate_limit_msg.clone())
+            .unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), rate_limit_msg).unwrap();
+
+        // 300000 goes out, 100000 is over the per withdrawal cap
+        let res = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(400000u64),
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+
+        // only 200000 is left in the window
+        let res = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(300000u64),
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetWindowUsage {}).unwrap();
+        let value: WindowUsageResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(500000u64), value.used);
+        assert_eq!(Some(Uint128::zero()), value.remaining);
+        assert_eq!(Uint128::from(200000u64), value.total_held);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetHeldWithdrawals {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: Vec<HeldWithdrawalResponse> = from_binary(&res).unwrap();
+        assert_eq!(2, value.len());
+        assert_eq!(Uint128::from(100000u64), value[0].amount);
+        assert_eq!(1, value[1].id);
+
+        // held funds are not withdrawable by the clearing house
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(300000u64), value.balance);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::ReleaseHeldWithdrawal { id: 0 },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::ReleaseHeldWithdrawal { id: 1 },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+
+        // released funds leave the vault, the clearing house balance is untouched
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(300000u64), value.balance);
+
+        // the window rolls over after 24h
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400 + 3600);
+        let res = query(deps.as_ref(), env, QueryMsg::GetWindowUsage {}).unwrap();
+        let value: WindowUsageResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::zero(), value.used);
+        assert_eq!(Uint128::zero(), value.total_held);
+    }
+
+    #[test]
+    fn proper_guardian_pause() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &coins(0, "uusd")),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::UpdateGuardian {
+                guardian: "guardian".to_string(),
+            },
+        )
+        .unwrap();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000000, "uusd"))).unwrap();
+
+        // the guardian freezes withdrawals only
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("guardian", &[]),
+            ExecuteMsg::Pause {
+                deposit: false,
+                withdraw: true,
+            },
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("guardian")), value.guardian);
+        assert!(value.withdraw_paused);
+        assert!(!value.deposit_paused);
+
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(1000u64),
+        )
+        .unwrap_err();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000, "uusd"))).unwrap();
+
+        // only the admin lifts the pause
+        let unpause_msg = ExecuteMsg::Unpause {
+            deposit: false,
+            withdraw: true,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), unpause_msg.clone())
+            .unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), unpause_msg).unwrap();
+
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(1000u64),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn proper_two_step_transfer() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::ProposeAdmin {
+                new_admin: "newadmin".to_string(),
+            },
+        )
+        .unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+
+        // nothing changes until the proposed addresses accept
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("creator", value.admin);
+        assert_eq!(Some(Addr::unchecked("newadmin")), value.pending_admin);
+        assert_eq!(Some(Addr::unchecked("newclearing")), value.pending_clearing_house);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap_err();
+        accept_clearing_house(deps.as_mut(), mock_info("creator", &[])).unwrap_err();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newadmin", &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("newadmin", value.admin);
+        assert_eq!(Addr::unchecked("newclearing"), value.clearing_house);
+        assert_eq!(None, value.pending_admin);
+        assert_eq!(None, value.pending_clearing_house);
+    }
+
+    #[test]
+    fn proper_multiple_clearing_houses() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "clearinghouse".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("clearinghouse", &[])).unwrap();
+
+        // only the admin authorizes further clearing houses
+        let authorize = ExecuteMsg::AuthorizeClearingHouse {
+            clearing_house: "isolated".to_string(),
+            deposit_cap: Uint128::from(1000u64),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("isolated", &[]), authorize.clone())
+            .unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), authorize).unwrap();
+
+        deposit(deps.as_mut(), mock_info("clearinghouse", &coins(500, "uusd"))).unwrap();
+        deposit(deps.as_mut(), mock_info("isolated", &coins(800, "uusd"))).unwrap();
+        deposit(deps.as_mut(), mock_info("stranger", &coins(800, "uusd"))).unwrap_err();
+
+        // the cap applies to the sub-ledger of each clearing house
+        deposit(deps.as_mut(), mock_info("isolated", &coins(300, "uusd"))).unwrap_err();
+
+        // a clearing house cannot withdraw deposits of another one
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("isolated", &[]),
+            "user".to_string(),
+            Uint128::from(900u64),
+        )
+        .unwrap_err();
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("isolated", &[]),
+            "user".to_string(),
+            Uint128::from(800u64),
+        )
+        .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetClearingHouseBalance {
+                clearing_house: "clearinghouse".to_string(),
+            },
+        )
+        .unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(500u64), value.balance);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetClearingHouses {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: Vec<ClearingHouseResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                ClearingHouseResponse {
+                    clearing_house: Addr::unchecked("clearinghouse"),
+                    balance: Uint128::from(500u64),
+                    deposit_cap: Uint128::zero(),
+                },
+                ClearingHouseResponse {
+                    clearing_house: Addr::unchecked("isolated"),
+                    balance: Uint128::zero(),
+                    deposit_cap: Uint128::from(1000u64),
+                },
+            ],
+            value
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(500u64), value.balance);
+
+        // the primary clearing house is only replaced, an emptied one can be revoked
+        let revoke = |clearing_house: &str| ExecuteMsg::RevokeClearingHouse {
+            clearing_house: clearing_house.to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), revoke("clearinghouse"))
+            .unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), revoke("isolated"))
+            .unwrap();
+        deposit(deps.as_mut(), mock_info("isolated", &coins(100, "uusd"))).unwrap_err();
+
+        // the new primary takes over the sub-ledger of the old one
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("clearinghouse", &coins(100, "uusd"))).unwrap_err();
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetClearingHouseBalance {
+                clearing_house: "newclearing".to_string(),
+            },
+        )
+        .unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(500u64), value.balance);
+    }
+
+    #[test]
+    fn proper_migrate_from_0_1_0() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        set_contract_version(deps.as_mut().storage, "crates.io:collateral-funds", "0.1.0").unwrap();
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(
+            env!("CARGO_PKG_VERSION"),
+            get_contract_version(deps.as_ref().storage).unwrap().version
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("creator", value.admin);
+
+        // unknown versions are refused
+        set_contract_version(deps.as_mut().storage, "crates.io:collateral-funds", "0.0.1").unwrap();
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    }
+
+    // local stand in for the external yield contract, it holds the deposited principal
+    // and the test accrues yield on top of it
+    struct MockYieldContract {
+        balance: Uint128,
+    }
+
+    impl MockYieldContract {
+        fn execute(&mut self, msg: &CosmosMsg) {
+            if let CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                funds,
+            }) = msg
+            {
+                if contract_addr != "yieldcontract" {
+                    return;
+                }
+                match from_binary(msg).unwrap() {
+                    StrategyExecuteMsg::Deposit {} => self.balance += funds[0].amount,
+                    StrategyExecuteMsg::Withdraw { amount } => {
+                        self.balance = self.balance.checked_sub(amount).unwrap()
+                    }
+                }
+            }
+        }
+    }
+
+    struct YieldQuerier {
+        base: MockQuerier,
+        strategy: RefCell<MockYieldContract>,
+    }
+
+    impl Querier for YieldQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+            match &request {
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                    if contract_addr == "yieldcontract" =>
+                {
+                    let StrategyQueryMsg::Balance { .. } = from_binary(msg).unwrap();
+                    let balance = self.strategy.borrow().balance;
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&StrategyBalanceResponse { balance }).unwrap(),
+                    ))
+                }
+                _ => self.base.handle_query(&request),
+            }
+        }
+    }
+
+    #[test]
+    fn proper_yield_strategy() {
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: YieldQuerier {
+                base: MockQuerier::new(&[]),
+                strategy: RefCell::new(MockYieldContract {
+                    balance: Uint128::zero(),
+                }),
+            },
+        };
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000000, "uusd"))).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SetStrategy {
+                contract: "yieldcontract".to_string(),
+                insurance_fund: "insurancevault".to_string(),
+                target_ratio: Decimal::percent(60),
+            },
+        )
+        .unwrap();
+
+        // 60% of the deposits go into the yield contract, 40% stay liquid
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::Rebalance {},
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+        deps.querier.strategy.borrow_mut().execute(&res.messages[0].msg);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStrategy {}).unwrap();
+        let value: Option<StrategyResponse> = from_binary(&res).unwrap();
+        let value = value.unwrap();
+        assert_eq!(Uint128::from(600000u64), value.deployed);
+        assert_eq!(Uint128::from(600000u64), value.strategy_balance);
+
+        // the yield is pulled out and donated to the insurance fund
+        deps.querier.strategy.borrow_mut().balance += Uint128::from(30000u64);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::HarvestYield {},
+        )
+        .unwrap();
+        assert_eq!(2, res.messages.len());
+        deps.querier.strategy.borrow_mut().execute(&res.messages[0].msg);
+        assert_eq!(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "insurancevault".to_string(),
+                msg: to_binary(&InsuranceInterface::Donate {}).unwrap(),
+                funds: coins(30000, "uusd"),
+            }),
+            res.messages[1].msg
+        );
+        assert_eq!(Uint128::from(600000u64), deps.querier.strategy.borrow().balance);
+
+        // a withdrawal larger than the liquid buffer recalls the difference first
+        let res = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(700000u64),
+        )
+        .unwrap();
+        assert_eq!(2, res.messages.len());
+        deps.querier.strategy.borrow_mut().execute(&res.messages[0].msg);
+        assert_eq!(Uint128::from(300000u64), deps.querier.strategy.borrow().balance);
+
+        // rebalancing the remaining 300000 deposits recalls down to 180000
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::Rebalance {},
+        )
+        .unwrap();
+        deps.querier.strategy.borrow_mut().execute(&res.messages[0].msg);
+        assert_eq!(Uint128::from(180000u64), deps.querier.strategy.borrow().balance);
+
+        // the strategy cannot be dropped while it holds funds
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::RemoveStrategy {})
+            .unwrap_err();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::Recall {
+                amount: Uint128::from(180000u64),
+            },
+        )
+        .unwrap();
+        deps.querier.strategy.borrow_mut().execute(&res.messages[0].msg);
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::RemoveStrategy {})
+            .unwrap();
+    }
 }
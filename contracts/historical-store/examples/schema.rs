@@ -2,7 +2,7 @@ use std::env::current_dir;
 use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
-use historical_store::{msg::{InstantiateMsg, ExecuteMsg, QueryMsg}, state::State};
+use historical_store::{msg::{InstantiateMsg, ExecuteMsg, MigrateMsg, QueryMsg}, state::State};
 
 // use my_first_contract::msg::{CountResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
 // use my_first_contract::state::State;
@@ -15,6 +15,7 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
 
@@ -1,25 +1,50 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response};
-use cw2::set_contract_version;
-use cw_storage_plus::{Bound, PrimaryKey};
+use cosmwasm_std::{
+    coins, to_binary, Addr, BankMsg, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Order,
+    Response, StdError, StdResult, Storage, Uint128,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::{Bound, IndexList, IndexedMap, Map, PrimaryKey, U128Key, U64Key};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::error::ContractError;
 use crate::msg::{
-    ConfigResponse, CurveHistoryResponse, DepositHistoryResponse, ExecuteMsg,
-    FundingPaymentHistoryResponse, FundingRateHistoryResponse, InstantiateMsg, LengthResponse,
-    LiquidationHistoryResponse, QueryMsg, TradeHistoryResponse,
+    AmmSnapshotResponse, CandleResponse, ConfigResponse, CurveHistoryResponse,
+    DepositHistoryResponse, ExecuteMsg, FundingPaymentHistoryResponse, FundingRateHistoryResponse,
+    HistoryRecord, InstantiateMsg, LeaderboardEntry, LeaderboardMetric, LengthResponse,
+    LiquidationHistoryResponse, MigrateMsg, OrderBy, QueryMsg, RetentionResponse,
+    SequencedRecordResponse, TradeHistoryResponse, UserMarketStatsResponse, UserStatsResponse,
+    WriterResponse,
 };
-use crate::package::validate::addr_validate_to_lower;
+use crate::package::number::Number128;
+use crate::package::types::PositionDirection;
+use crate::package::validate::{account_validate_to_lower, addr_validate_to_lower};
 use crate::state::{
-    CurveRecord, DepositRecord, FundingPaymentRecord, FundingRateRecord, Length, LiquidationRecord,
-    State, TradeRecord, CURVEHISTORY, DEPOSIT_HISTORY, FUNDING_PAYMENT_HISTORY,
-    FUNDING_RATE_HISTORY, LENGTH, LIQUIDATION_HISTORY, STATE, TRADE_HISTORY,
+    amm_snapshot_history, curve_history, curve_history_v020, deposit_history, deposit_history_v020,
+    funding_payment_history, funding_payment_history_v020, funding_rate_history,
+    funding_rate_history_v020, liquidation_history, liquidation_history_v020, trade_history,
+    trade_history_v020, AmmSnapshotRecord, Candle, CandleResolution, CurveRecord, DepositRecord,
+    FundingPaymentRecord, FundingRateRecord, Length, LiquidationRecord, LogEntry, PruneReward,
+    RecordType, RetentionPolicy, State, TradeRecord, UserMarketStats, UserStats, ALL_TIME, CANDLES,
+    DEPOSIT_HISTORY_V010, FUNDING_PAYMENT_HISTORY_V010, GLOBAL_SEQ, LAST_TS, LENGTH,
+    LIQUIDATION_HISTORY_V010, PNL_RANKING, PRUNED, PRUNE_REWARD, RECORD_LOG, RECORD_MIGRATION,
+    RECORD_SEQ, RETENTION, STATE, TRADE_HISTORY_V010, USER_DAILY_STATS, USER_MARKET_STATS,
+    USER_STATS, VOLUME_RANKING, WRITERS,
 };
+use std::collections::BTreeMap;
+use std::convert::TryInto;
 
 // iterator limits
 pub const MAX_LIMIT: u32 = 20;
 pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_PRUNE_LIMIT: u32 = 100;
+pub const DEFAULT_PRUNE_LIMIT: u32 = 30;
+pub const MAX_MIGRATE_LIMIT: u32 = 100;
+pub const DEFAULT_MIGRATE_LIMIT: u32 = 30;
+// days a leaderboard window may sum up
+pub const MAX_LEADERBOARD_DAYS: u64 = 31;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:my-first-contract";
@@ -40,6 +65,7 @@ pub fn instantiate(
     STATE.save(deps.storage, &state)?;
 
     let length = Length {
+        amm_snapshot_history_length: 0,
         curve_history_length: 0,
         deposit_history_length: 0,
         funding_payment_history_length: 0,
@@ -56,7 +82,7 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -65,12 +91,27 @@ pub fn execute(
         ExecuteMsg::UpdateClearingHouse { new_house } => {
             try_new_clearing_house(deps, info, new_house)
         }
+        ExecuteMsg::RecordAmmSnapshot { s } => try_record_amm_snapshot(deps, info, s),
         ExecuteMsg::RecordCurve { c } => try_record_curve(deps, info, c),
         ExecuteMsg::RecordFundingPayment { f } => try_record_funding_payment(deps, info, f),
         ExecuteMsg::RecordFundingRate { f } => try_record_funding_rate(deps, info, f),
         ExecuteMsg::RecordLiquidation { l } => try_record_liquidation(deps, info, l),
         ExecuteMsg::RecordTrade { t } => try_record_trade(deps, info, t),
         ExecuteMsg::RecordDeposit { d } => try_record_deposit(deps, info, d),
+        ExecuteMsg::AddWriter {
+            writer,
+            record_types,
+        } => try_add_writer(deps, info, writer, record_types),
+        ExecuteMsg::RemoveWriter { writer } => try_remove_writer(deps, info, writer),
+        ExecuteMsg::UpdateRetention {
+            record_type,
+            max_count,
+            max_age,
+        } => try_update_retention(deps, info, record_type, max_count, max_age),
+        ExecuteMsg::UpdatePruneReward { reward } => try_update_prune_reward(deps, info, reward),
+        ExecuteMsg::Prune { record_type, limit } => try_prune(deps, env, info, record_type, limit),
+        ExecuteMsg::MigrateRecords { limit } => try_migrate_records(deps, limit),
+        ExecuteMsg::RecordBatch { records } => try_record_batch(deps, info, records),
         ExecuteMsg::RecordFundingPaymentsMultiple { vecf } => {
             try_record_funding_payment_multiple(deps, info, vecf)
         } // ExecuteMsg::RecordOrder { o } => try_record_order(deps, info, o),
@@ -82,31 +123,39 @@ fn try_record_funding_payment_multiple(
     info: MessageInfo,
     vecf: Vec<FundingPaymentRecord>,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
-    };
-
-    for f in vecf.iter() {
-        let mut len = LENGTH.load(deps.storage)?;
-        let funding_payment_history_info_length = len
-            .funding_payment_history_length
-            .checked_add(1)
-            .ok_or_else(|| (ContractError::MathError))?;
-        len.funding_payment_history_length = funding_payment_history_info_length;
-        LENGTH.update(deps.storage, |_l| -> Result<Length, ContractError> {
-            Ok(len)
-        })?;
-        FUNDING_PAYMENT_HISTORY.save(
-            deps.storage,
-            (&f.user, funding_payment_history_info_length.to_string()),
-            f,
-        )?;
+    for f in vecf {
+        record_funding_payment(deps.storage, &info.sender, f)?;
     }
 
     Ok(Response::new().add_attribute("method", "record_funding_payment_records_multple"))
 }
 
+// every record is checked against the sender's permitted record types
+fn try_record_batch(
+    deps: DepsMut,
+    info: MessageInfo,
+    records: Vec<HistoryRecord>,
+) -> Result<Response, ContractError> {
+    let count = records.len();
+    for record in records {
+        match record {
+            HistoryRecord::AmmSnapshot(s) => record_amm_snapshot(deps.storage, &info.sender, s)?,
+            HistoryRecord::Curve(c) => record_curve(deps.storage, &info.sender, c)?,
+            HistoryRecord::Deposit(d) => record_deposit(deps.storage, &info.sender, d)?,
+            HistoryRecord::FundingPayment(f) => {
+                record_funding_payment(deps.storage, &info.sender, f)?
+            }
+            HistoryRecord::FundingRate(f) => record_funding_rate(deps.storage, &info.sender, f)?,
+            HistoryRecord::Liquidation(l) => record_liquidation(deps.storage, &info.sender, l)?,
+            HistoryRecord::Trade(t) => record_trade(deps.storage, &info.sender, t)?,
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "record_batch")
+        .add_attribute("records", count.to_string()))
+}
+
 // fn try_record_order(deps: DepsMut, info: MessageInfo, o: OrderRecord) -> Result<Response, ContractError>  {
 //     todo!()
 // }
@@ -116,27 +165,31 @@ fn try_record_deposit(
     info: MessageInfo,
     d: DepositRecord,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
-    };
+    record_deposit(deps.storage, &info.sender, d)?;
 
-    let mut len = LENGTH.load(deps.storage)?;
+    Ok(Response::new().add_attribute("method", "record_deposit"))
+}
+
+fn record_deposit(
+    storage: &mut dyn Storage,
+    writer: &Addr,
+    mut d: DepositRecord,
+) -> Result<(), ContractError> {
+    assert_writer(storage, writer, RecordType::Deposit)?;
+    advance_ts(storage, RecordType::Deposit, d.ts)?;
+    d.writer = Some(writer.clone());
+
+    let mut len = LENGTH.load(storage)?;
     let deposit_history_info_length = len
         .deposit_history_length
         .checked_add(1)
         .ok_or_else(|| (ContractError::MathError))?;
     len.deposit_history_length = deposit_history_info_length;
-    LENGTH.update(deps.storage, |_l| -> Result<Length, ContractError> {
-        Ok(len)
-    })?;
-    DEPOSIT_HISTORY.save(
-        deps.storage,
-        (&d.user, deposit_history_info_length.to_string()),
-        &d,
-    )?;
+    LENGTH.update(storage, |_l| -> Result<Length, ContractError> { Ok(len) })?;
+    deposit_history().save(storage, U64Key::new(deposit_history_info_length), &d)?;
+    log_record(storage, RecordType::Deposit, deposit_history_info_length)?;
 
-    Ok(Response::new().add_attribute("method", "record_deposit"))
+    Ok(())
 }
 
 fn try_record_trade(
@@ -144,27 +197,198 @@ fn try_record_trade(
     info: MessageInfo,
     t: TradeRecord,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
-    };
+    record_trade(deps.storage, &info.sender, t)?;
+
+    Ok(Response::new().add_attribute("method", "record_trade"))
+}
+
+fn record_trade(
+    storage: &mut dyn Storage,
+    writer: &Addr,
+    mut t: TradeRecord,
+) -> Result<(), ContractError> {
+    assert_writer(storage, writer, RecordType::Trade)?;
+    advance_ts(storage, RecordType::Trade, t.ts)?;
+    t.writer = Some(writer.clone());
 
-    let mut len = LENGTH.load(deps.storage)?;
+    let mut len = LENGTH.load(storage)?;
     let trade_history_info_length = len
         .trade_history_length
         .checked_add(1)
         .ok_or_else(|| (ContractError::MathError))?;
     len.trade_history_length = trade_history_info_length;
-    LENGTH.update(deps.storage, |_l| -> Result<Length, ContractError> {
-        Ok(len)
-    })?;
-    TRADE_HISTORY.save(
-        deps.storage,
-        (&t.user, trade_history_info_length.to_string()),
-        &t,
-    )?;
+    LENGTH.update(storage, |_l| -> Result<Length, ContractError> { Ok(len) })?;
+    trade_history().save(storage, U64Key::new(trade_history_info_length), &t)?;
+    log_record(storage, RecordType::Trade, trade_history_info_length)?;
+    update_candles(storage, &t)?;
+    update_trade_stats(storage, &t)?;
 
-    Ok(Response::new().add_attribute("method", "record_trade"))
+    Ok(())
+}
+
+fn update_candles(storage: &mut dyn Storage, t: &TradeRecord) -> Result<(), ContractError> {
+    let price = t.mark_price_after;
+    for resolution in CandleResolution::ALL.iter() {
+        let start_ts = resolution.bucket_start(t.ts);
+        let key = (
+            U64Key::new(t.market_index),
+            U64Key::new(resolution.seconds()),
+            U64Key::new(start_ts),
+        );
+        CANDLES.update(storage, key, |candle| -> Result<Candle, ContractError> {
+            Ok(match candle {
+                Some(mut candle) => {
+                    candle.high = candle.high.max(price);
+                    candle.low = candle.low.min(price);
+                    candle.close = price;
+                    candle.volume = candle.volume.checked_add(t.quote_asset_amount)?;
+                    candle.trade_count += 1;
+                    candle
+                }
+                None => Candle {
+                    start_ts,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: t.quote_asset_amount,
+                    trade_count: 1,
+                },
+            })
+        })?;
+    }
+    Ok(())
+}
+
+fn add_signed(a: Number128, b: i128) -> Result<Number128, ContractError> {
+    Ok(Number128::new(
+        a.i128().checked_add(b).ok_or(ContractError::MathError)?,
+    ))
+}
+
+// realized pnl of the trade against the replayed position, before fees
+fn apply_trade_to_position(
+    stats: &mut UserMarketStats,
+    t: &TradeRecord,
+) -> Result<i128, ContractError> {
+    let base = stats.base_asset_amount.i128();
+    let delta = match t.direction {
+        PositionDirection::Long => t.base_asset_amount.u128() as i128,
+        PositionDirection::Short => -(t.base_asset_amount.u128() as i128),
+    };
+    let new_base = base.checked_add(delta).ok_or(ContractError::MathError)?;
+    stats.base_asset_amount = Number128::new(new_base);
+
+    if base == 0 || base.signum() == delta.signum() {
+        stats.quote_entry_amount = stats.quote_entry_amount.checked_add(t.quote_asset_amount)?;
+        return Ok(0);
+    }
+
+    let (entry_closed, quote_closed) = if delta.unsigned_abs() <= base.unsigned_abs() {
+        let entry_closed = stats
+            .quote_entry_amount
+            .multiply_ratio(delta.unsigned_abs(), base.unsigned_abs());
+        (entry_closed, t.quote_asset_amount)
+    } else {
+        // position flips, the rest of the trade opens the new side
+        let quote_closed = t
+            .quote_asset_amount
+            .multiply_ratio(base.unsigned_abs(), delta.unsigned_abs());
+        (stats.quote_entry_amount, quote_closed)
+    };
+    stats.quote_entry_amount = if new_base.signum() == base.signum() {
+        stats.quote_entry_amount.checked_sub(entry_closed)?
+    } else {
+        t.quote_asset_amount.checked_sub(quote_closed)?
+    };
+
+    let pnl = quote_closed.u128() as i128 - entry_closed.u128() as i128;
+    Ok(if base > 0 { pnl } else { -pnl })
+}
+
+fn update_trade_stats(storage: &mut dyn Storage, t: &TradeRecord) -> Result<(), ContractError> {
+    let key = (&t.user, U64Key::new(t.market_index));
+    let mut market_stats = USER_MARKET_STATS
+        .may_load(storage, key.clone())?
+        .unwrap_or_default();
+    let pnl = apply_trade_to_position(&mut market_stats, t)?;
+    market_stats.volume = market_stats.volume.checked_add(t.quote_asset_amount)?;
+    market_stats.fees_paid = market_stats.fees_paid.checked_add(t.fee)?;
+    market_stats.realized_pnl = add_signed(market_stats.realized_pnl, pnl)?;
+    market_stats.trade_count += 1;
+    USER_MARKET_STATS.save(storage, key, &market_stats)?;
+
+    let mut stats = USER_STATS.may_load(storage, &t.user)?.unwrap_or_default();
+    unrank(storage, ALL_TIME, &t.user, stats.volume, stats.realized_pnl);
+    stats.volume = stats.volume.checked_add(t.quote_asset_amount)?;
+    stats.fees_paid = stats.fees_paid.checked_add(t.fee)?;
+    stats.realized_pnl = add_signed(stats.realized_pnl, pnl)?;
+    stats.trade_count += 1;
+    USER_STATS.save(storage, &t.user, &stats)?;
+    rank(storage, ALL_TIME, &t.user, stats.volume, stats.realized_pnl)?;
+
+    let day = CandleResolution::OneDay.bucket_start(t.ts);
+    let key = (U64Key::new(day), &t.user);
+    let mut daily = USER_DAILY_STATS
+        .may_load(storage, key.clone())?
+        .unwrap_or_default();
+    unrank(storage, day, &t.user, daily.volume, daily.realized_pnl);
+    daily.volume = daily.volume.checked_add(t.quote_asset_amount)?;
+    daily.realized_pnl = add_signed(daily.realized_pnl, pnl)?;
+    USER_DAILY_STATS.save(storage, key, &daily)?;
+    rank(storage, day, &t.user, daily.volume, daily.realized_pnl)?;
+    Ok(())
+}
+
+// flipping the sign bit orders every i128 as its u128
+fn pnl_rank_key(pnl: Number128) -> U128Key {
+    U128Key::new((pnl.i128() as u128) ^ (1 << 127))
+}
+
+fn rank(
+    storage: &mut dyn Storage,
+    period: u64,
+    user: &Addr,
+    volume: Uint128,
+    pnl: Number128,
+) -> StdResult<()> {
+    let period = U64Key::new(period);
+    let volume = U128Key::new(volume.u128());
+    VOLUME_RANKING.save(storage, (period.clone(), volume, user), &Empty {})?;
+    PNL_RANKING.save(storage, (period, pnl_rank_key(pnl), user), &Empty {})
+}
+
+fn unrank(storage: &mut dyn Storage, period: u64, user: &Addr, volume: Uint128, pnl: Number128) {
+    let period = U64Key::new(period);
+    VOLUME_RANKING.remove(storage, (period.clone(), U128Key::new(volume.u128()), user));
+    PNL_RANKING.remove(storage, (period, pnl_rank_key(pnl), user));
+}
+
+fn update_funding_stats(
+    storage: &mut dyn Storage,
+    f: &FundingPaymentRecord,
+) -> Result<(), ContractError> {
+    // a positive funding_payment is received by the user
+    let paid = -f.funding_payment.i128();
+    USER_MARKET_STATS.update(
+        storage,
+        (&f.user, U64Key::new(f.market_index)),
+        |stats| -> Result<UserMarketStats, ContractError> {
+            let mut stats = stats.unwrap_or_default();
+            stats.funding_paid = add_signed(stats.funding_paid, paid)?;
+            Ok(stats)
+        },
+    )?;
+    USER_STATS.update(
+        storage,
+        &f.user,
+        |stats| -> Result<UserStats, ContractError> {
+            let mut stats = stats.unwrap_or_default();
+            stats.funding_paid = add_signed(stats.funding_paid, paid)?;
+            Ok(stats)
+        },
+    )?;
+    Ok(())
 }
 
 fn try_record_liquidation(
@@ -172,27 +396,44 @@ fn try_record_liquidation(
     info: MessageInfo,
     l: LiquidationRecord,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
-    };
+    record_liquidation(deps.storage, &info.sender, l)?;
+
+    Ok(Response::new().add_attribute("method", "record_liquidation"))
+}
 
-    let mut len = LENGTH.load(deps.storage)?;
+fn record_liquidation(
+    storage: &mut dyn Storage,
+    writer: &Addr,
+    mut l: LiquidationRecord,
+) -> Result<(), ContractError> {
+    assert_writer(storage, writer, RecordType::Liquidation)?;
+    advance_ts(storage, RecordType::Liquidation, l.ts)?;
+    l.writer = Some(writer.clone());
+
+    let mut len = LENGTH.load(storage)?;
     let liquidation_history_info_length = len
         .liquidation_history_length
         .checked_add(1)
         .ok_or_else(|| (ContractError::MathError))?;
     len.liquidation_history_length = liquidation_history_info_length;
-    LENGTH.update(deps.storage, |_l| -> Result<Length, ContractError> {
-        Ok(len)
-    })?;
-    LIQUIDATION_HISTORY.save(
-        deps.storage,
-        (&l.user, liquidation_history_info_length.to_string()),
-        &l,
+    LENGTH.update(storage, |_l| -> Result<Length, ContractError> { Ok(len) })?;
+    liquidation_history().save(storage, U64Key::new(liquidation_history_info_length), &l)?;
+    log_record(
+        storage,
+        RecordType::Liquidation,
+        liquidation_history_info_length,
+    )?;
+    USER_STATS.update(
+        storage,
+        &l.user,
+        |stats| -> Result<UserStats, ContractError> {
+            let mut stats = stats.unwrap_or_default();
+            stats.liquidation_count += 1;
+            Ok(stats)
+        },
     )?;
 
-    Ok(Response::new().add_attribute("method", "record_liquidation"))
+    Ok(())
 }
 
 fn try_record_funding_rate(
@@ -200,27 +441,35 @@ fn try_record_funding_rate(
     info: MessageInfo,
     f: FundingRateRecord,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
-    };
+    record_funding_rate(deps.storage, &info.sender, f)?;
+
+    Ok(Response::new().add_attribute("method", "record_funding_rate"))
+}
 
-    let mut len = LENGTH.load(deps.storage)?;
+fn record_funding_rate(
+    storage: &mut dyn Storage,
+    writer: &Addr,
+    mut f: FundingRateRecord,
+) -> Result<(), ContractError> {
+    assert_writer(storage, writer, RecordType::FundingRate)?;
+    advance_ts(storage, RecordType::FundingRate, f.ts)?;
+    f.writer = Some(writer.clone());
+
+    let mut len = LENGTH.load(storage)?;
     let funding_rate_history_info_length = len
         .funding_rate_history_length
         .checked_add(1)
         .ok_or_else(|| (ContractError::MathError))?;
     len.funding_rate_history_length = funding_rate_history_info_length;
-    LENGTH.update(deps.storage, |_l| -> Result<Length, ContractError> {
-        Ok(len)
-    })?;
-    FUNDING_RATE_HISTORY.save(
-        deps.storage,
-        funding_rate_history_info_length.to_string(),
-        &f,
+    LENGTH.update(storage, |_l| -> Result<Length, ContractError> { Ok(len) })?;
+    funding_rate_history().save(storage, U64Key::new(funding_rate_history_info_length), &f)?;
+    log_record(
+        storage,
+        RecordType::FundingRate,
+        funding_rate_history_info_length,
     )?;
 
-    Ok(Response::new().add_attribute("method", "record_funding_rate"))
+    Ok(())
 }
 
 fn try_record_funding_payment(
@@ -228,27 +477,76 @@ fn try_record_funding_payment(
     info: MessageInfo,
     f: FundingPaymentRecord,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
-    };
+    record_funding_payment(deps.storage, &info.sender, f)?;
+
+    Ok(Response::new().add_attribute("method", "record_funding_payment"))
+}
+
+fn record_funding_payment(
+    storage: &mut dyn Storage,
+    writer: &Addr,
+    mut f: FundingPaymentRecord,
+) -> Result<(), ContractError> {
+    assert_writer(storage, writer, RecordType::FundingPayment)?;
+    advance_ts(storage, RecordType::FundingPayment, f.ts)?;
+    f.writer = Some(writer.clone());
 
-    let mut len = LENGTH.load(deps.storage)?;
+    let mut len = LENGTH.load(storage)?;
     let funding_payment_history_info_length = len
         .funding_payment_history_length
         .checked_add(1)
         .ok_or_else(|| (ContractError::MathError))?;
     len.funding_payment_history_length = funding_payment_history_info_length;
-    LENGTH.update(deps.storage, |_l| -> Result<Length, ContractError> {
-        Ok(len)
-    })?;
-    FUNDING_PAYMENT_HISTORY.save(
-        deps.storage,
-        (&f.user, funding_payment_history_info_length.to_string()),
+    LENGTH.update(storage, |_l| -> Result<Length, ContractError> { Ok(len) })?;
+    funding_payment_history().save(
+        storage,
+        U64Key::new(funding_payment_history_info_length),
         &f,
     )?;
+    log_record(
+        storage,
+        RecordType::FundingPayment,
+        funding_payment_history_info_length,
+    )?;
+    update_funding_stats(storage, &f)?;
 
-    Ok(Response::new().add_attribute("method", "record_funding_payment"))
+    Ok(())
+}
+
+fn try_record_amm_snapshot(
+    deps: DepsMut,
+    info: MessageInfo,
+    s: AmmSnapshotRecord,
+) -> Result<Response, ContractError> {
+    record_amm_snapshot(deps.storage, &info.sender, s)?;
+
+    Ok(Response::new().add_attribute("method", "record_amm_snapshot"))
+}
+
+fn record_amm_snapshot(
+    storage: &mut dyn Storage,
+    writer: &Addr,
+    mut s: AmmSnapshotRecord,
+) -> Result<(), ContractError> {
+    assert_writer(storage, writer, RecordType::AmmSnapshot)?;
+    advance_ts(storage, RecordType::AmmSnapshot, s.ts)?;
+    s.writer = Some(writer.clone());
+
+    let mut len = LENGTH.load(storage)?;
+    let amm_snapshot_history_length = len
+        .amm_snapshot_history_length
+        .checked_add(1)
+        .ok_or(ContractError::MathError)?;
+    len.amm_snapshot_history_length = amm_snapshot_history_length;
+    LENGTH.update(storage, |_l| -> Result<Length, ContractError> { Ok(len) })?;
+    amm_snapshot_history().save(storage, U64Key::new(amm_snapshot_history_length), &s)?;
+    log_record(
+        storage,
+        RecordType::AmmSnapshot,
+        amm_snapshot_history_length,
+    )?;
+
+    Ok(())
 }
 
 fn try_record_curve(
@@ -256,23 +554,31 @@ fn try_record_curve(
     info: MessageInfo,
     c: CurveRecord,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
-    };
+    record_curve(deps.storage, &info.sender, c)?;
+
+    Ok(Response::new().add_attribute("method", "record_curve"))
+}
+
+fn record_curve(
+    storage: &mut dyn Storage,
+    writer: &Addr,
+    mut c: CurveRecord,
+) -> Result<(), ContractError> {
+    assert_writer(storage, writer, RecordType::Curve)?;
+    advance_ts(storage, RecordType::Curve, c.ts)?;
+    c.writer = Some(writer.clone());
 
-    let mut len = LENGTH.load(deps.storage)?;
+    let mut len = LENGTH.load(storage)?;
     let curve_history_info_length = len
         .curve_history_length
         .checked_add(1)
         .ok_or_else(|| (ContractError::MathError))?;
     len.curve_history_length = curve_history_info_length;
-    LENGTH.update(deps.storage, |_l| -> Result<Length, ContractError> {
-        Ok(len)
-    })?;
-    CURVEHISTORY.save(deps.storage, curve_history_info_length.to_string(), &c)?;
+    LENGTH.update(storage, |_l| -> Result<Length, ContractError> { Ok(len) })?;
+    curve_history().save(storage, U64Key::new(curve_history_info_length), &c)?;
+    log_record(storage, RecordType::Curve, curve_history_info_length)?;
 
-    Ok(Response::new().add_attribute("method", "record_curve"))
+    Ok(())
 }
 
 fn try_new_clearing_house(
@@ -313,75 +619,956 @@ fn try_new_admin(
     Ok(Response::new().add_attribute("method", "new_admin"))
 }
 
+// the clearing house may write every record type, other writers only what they were granted
+fn assert_writer(
+    storage: &dyn Storage,
+    sender: &Addr,
+    record_type: RecordType,
+) -> Result<(), ContractError> {
+    let state = STATE.load(storage)?;
+    if *sender == state.clearing_house {
+        return Ok(());
+    }
+    let record_types = WRITERS.may_load(storage, sender)?.unwrap_or_default();
+    if !record_types.contains(&record_type) {
+        return Err(ContractError::UnauthorizedWriter {
+            record_type: record_type.key().to_string(),
+        });
+    }
+    Ok(())
+}
+
+// the ts range queries bisect the ids, so a record may not be older than the last of its type.
+// records migrated from before 0.3.0 are not checked
+fn advance_ts(
+    storage: &mut dyn Storage,
+    record_type: RecordType,
+    ts: u64,
+) -> Result<(), ContractError> {
+    let last_ts = LAST_TS.may_load(storage, record_type.key())?.unwrap_or_default();
+    if ts < last_ts {
+        return Err(ContractError::RecordOutOfOrder {
+            record_type: record_type.key().to_string(),
+            ts,
+            last_ts,
+        });
+    }
+    LAST_TS.save(storage, record_type.key(), &ts)?;
+    Ok(())
+}
+
+// numbers records across every type in the order they were written, for GetRecordsSince
+fn log_record(
+    storage: &mut dyn Storage,
+    record_type: RecordType,
+    id: u64,
+) -> Result<(), ContractError> {
+    let global_seq = GLOBAL_SEQ
+        .may_load(storage)?
+        .unwrap_or_default()
+        .checked_add(1)
+        .ok_or(ContractError::MathError)?;
+    GLOBAL_SEQ.save(storage, &global_seq)?;
+    RECORD_LOG.save(
+        storage,
+        U64Key::new(global_seq),
+        &LogEntry { record_type, id },
+    )?;
+    RECORD_SEQ.save(storage, (record_type.key(), U64Key::new(id)), &global_seq)?;
+    Ok(())
+}
+
+fn try_add_writer(
+    deps: DepsMut,
+    info: MessageInfo,
+    writer: String,
+    record_types: Vec<RecordType>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    };
+
+    let writer_addr = addr_validate_to_lower(deps.api, &writer)?;
+    WRITERS.save(deps.storage, &writer_addr, &record_types)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_writer")
+        .add_attribute("writer", writer_addr))
+}
+
+fn try_remove_writer(
+    deps: DepsMut,
+    info: MessageInfo,
+    writer: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    };
+
+    let writer_addr = addr_validate_to_lower(deps.api, &writer)?;
+    if !WRITERS.has(deps.storage, &writer_addr) {
+        return Err(ContractError::WriterNotFound {});
+    }
+    WRITERS.remove(deps.storage, &writer_addr);
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_writer")
+        .add_attribute("writer", writer_addr))
+}
+
+fn try_update_retention(
+    deps: DepsMut,
+    info: MessageInfo,
+    record_type: RecordType,
+    max_count: Option<u64>,
+    max_age: Option<u64>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    };
+
+    RETENTION.save(
+        deps.storage,
+        record_type.key(),
+        &RetentionPolicy { max_count, max_age },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_retention")
+        .add_attribute("record_type", record_type.key()))
+}
+
+fn try_update_prune_reward(
+    deps: DepsMut,
+    info: MessageInfo,
+    reward: Option<PruneReward>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    };
+
+    match reward {
+        Some(reward) => PRUNE_REWARD.save(deps.storage, &reward)?,
+        None => PRUNE_REWARD.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attribute("method", "update_prune_reward"))
+}
+
+// removes records and their log entries from the oldest id up while the retention policy is
+// exceeded, returns how many ids the cursor moved past and how many of them still held a record
+#[allow(clippy::too_many_arguments)]
+fn prune_oldest<'a, T, I>(
+    storage: &mut dyn Storage,
+    record_type: RecordType,
+    records: IndexedMap<'a, U64Key, T, I>,
+    ts: fn(&T) -> u64,
+    policy: &RetentionPolicy,
+    first_id: u64,
+    last_id: u64,
+    now: u64,
+    limit: u64,
+) -> Result<(u64, u64), ContractError>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    let mut removed = 0;
+    let mut id = first_id;
+    while id <= last_id && id - first_id < limit {
+        let live = last_id - id + 1;
+        let over_count = matches!(policy.max_count, Some(max) if live > max);
+        if let Some(record) = records.may_load(storage, U64Key::new(id))? {
+            let too_old =
+                matches!(policy.max_age, Some(max) if ts(&record).saturating_add(max) < now);
+            if !over_count && !too_old {
+                break;
+            }
+            records.remove(storage, U64Key::new(id))?;
+            let seq_key = (record_type.key(), U64Key::new(id));
+            if let Some(global_seq) = RECORD_SEQ.may_load(storage, seq_key.clone())? {
+                RECORD_LOG.remove(storage, U64Key::new(global_seq));
+                RECORD_SEQ.remove(storage, seq_key);
+            }
+            removed += 1;
+        }
+        id += 1;
+    }
+    Ok((id - first_id, removed))
+}
+
+fn try_prune(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    record_type: RecordType,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    // the oldest records may not be under their 0.3.0 key yet
+    if RECORD_MIGRATION.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::MigrationPending {});
+    }
+    let policy = RETENTION
+        .may_load(deps.storage, record_type.key())?
+        .unwrap_or_default();
+    let already_pruned = PRUNED
+        .may_load(deps.storage, record_type.key())?
+        .unwrap_or_default();
+    let len = LENGTH.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_PRUNE_LIMIT).min(MAX_PRUNE_LIMIT) as u64;
+    let now = env.block.time.seconds();
+    let first_id = already_pruned + 1;
+
+    let (passed, pruned) = match record_type {
+        RecordType::AmmSnapshot => prune_oldest(
+            deps.storage,
+            RecordType::AmmSnapshot,
+            amm_snapshot_history(),
+            |r| r.ts,
+            &policy,
+            first_id,
+            len.amm_snapshot_history_length,
+            now,
+            limit,
+        )?,
+        RecordType::Curve => prune_oldest(
+            deps.storage,
+            RecordType::Curve,
+            curve_history(),
+            |r| r.ts,
+            &policy,
+            first_id,
+            len.curve_history_length,
+            now,
+            limit,
+        )?,
+        RecordType::Deposit => prune_oldest(
+            deps.storage,
+            RecordType::Deposit,
+            deposit_history(),
+            |r| r.ts,
+            &policy,
+            first_id,
+            len.deposit_history_length,
+            now,
+            limit,
+        )?,
+        RecordType::FundingPayment => prune_oldest(
+            deps.storage,
+            RecordType::FundingPayment,
+            funding_payment_history(),
+            |r| r.ts,
+            &policy,
+            first_id,
+            len.funding_payment_history_length,
+            now,
+            limit,
+        )?,
+        RecordType::FundingRate => prune_oldest(
+            deps.storage,
+            RecordType::FundingRate,
+            funding_rate_history(),
+            |r| r.ts,
+            &policy,
+            first_id,
+            len.funding_rate_history_length,
+            now,
+            limit,
+        )?,
+        RecordType::Liquidation => prune_oldest(
+            deps.storage,
+            RecordType::Liquidation,
+            liquidation_history(),
+            |r| r.ts,
+            &policy,
+            first_id,
+            len.liquidation_history_length,
+            now,
+            limit,
+        )?,
+        RecordType::Trade => prune_oldest(
+            deps.storage,
+            RecordType::Trade,
+            trade_history(),
+            |r| r.ts,
+            &policy,
+            first_id,
+            len.trade_history_length,
+            now,
+            limit,
+        )?,
+    };
+    if passed == 0 {
+        return Err(ContractError::NothingToPrune {});
+    }
+    PRUNED.save(deps.storage, record_type.key(), &(already_pruned + passed))?;
+
+    let mut res = Response::new()
+        .add_attribute("method", "prune")
+        .add_attribute("record_type", record_type.key())
+        .add_attribute("pruned", pruned.to_string());
+
+    // pays what the contract holds if it cannot cover the full reward
+    if let Some(reward) = PRUNE_REWARD.may_load(deps.storage)? {
+        let balance = deps
+            .querier
+            .query_balance(&env.contract.address, &reward.denom)?;
+        let amount = reward
+            .per_record
+            .checked_mul(Uint128::from(pruned))?
+            .min(balance.amount);
+        if !amount.is_zero() {
+            res = res.add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: coins(amount.u128(), reward.denom),
+            });
+        }
+        res = res.add_attribute("reward", amount);
+    }
+    Ok(res)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    // every step moves the stored structs one version forward, 0.1.0 records are re-keyed
+    // straight to the 0.3.0 keys together with the 0.2.0 ones
+    let mut version = stored.version.clone();
+    if version == "0.1.0" {
+        version = "0.2.0".to_string();
+    }
+    if version == "0.2.0" {
+        migrate_from_0_2_0(deps.storage)?;
+        version = "0.3.0".to_string();
+    }
+    if version != CONTRACT_VERSION {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+// 0.3.0 keys every history map on the big-endian id so records sort numerically. the first
+// batch moves here, the rest through MigrateRecords
+fn migrate_from_0_2_0(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    RECORD_MIGRATION.save(storage, &true)?;
+    migrate_records(storage, DEFAULT_MIGRATE_LIMIT)?;
+    Ok(())
+}
+
+fn try_migrate_records(deps: DepsMut, limit: Option<u32>) -> Result<Response, ContractError> {
+    if RECORD_MIGRATION.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NothingToMigrate {});
+    }
+    let limit = limit.unwrap_or(DEFAULT_MIGRATE_LIMIT).min(MAX_MIGRATE_LIMIT);
+    let migrated = migrate_records(deps.storage, limit)?;
+    let done = RECORD_MIGRATION.may_load(deps.storage)?.is_none();
+    Ok(Response::new()
+        .add_attribute("method", "try_migrate_records")
+        .add_attribute("migrated", migrated.to_string())
+        .add_attribute("done", done.to_string()))
+}
+
+// moves up to `limit` legacy records to the 0.3.0 keys and logs them for GetRecordsSince as
+// they move, the migration is done once a call finds fewer than `limit` left
+fn migrate_records(storage: &mut dyn Storage, limit: u32) -> Result<u32, ContractError> {
+    let limit = limit as usize;
+    let mut moved = 0;
+    moved += move_v010_records(
+        storage,
+        RecordType::Deposit,
+        DEPOSIT_HISTORY_V010,
+        deposit_history(),
+        limit - moved,
+    )?;
+    moved += move_v010_records(
+        storage,
+        RecordType::FundingPayment,
+        FUNDING_PAYMENT_HISTORY_V010,
+        funding_payment_history(),
+        limit - moved,
+    )?;
+    moved += move_v010_records(
+        storage,
+        RecordType::Liquidation,
+        LIQUIDATION_HISTORY_V010,
+        liquidation_history(),
+        limit - moved,
+    )?;
+    moved += move_v010_records(
+        storage,
+        RecordType::Trade,
+        TRADE_HISTORY_V010,
+        trade_history(),
+        limit - moved,
+    )?;
+    moved += rekey_records(
+        storage,
+        RecordType::Curve,
+        curve_history_v020(),
+        curve_history(),
+        limit - moved,
+    )?;
+    moved += rekey_records(
+        storage,
+        RecordType::Deposit,
+        deposit_history_v020(),
+        deposit_history(),
+        limit - moved,
+    )?;
+    moved += rekey_records(
+        storage,
+        RecordType::FundingPayment,
+        funding_payment_history_v020(),
+        funding_payment_history(),
+        limit - moved,
+    )?;
+    moved += rekey_records(
+        storage,
+        RecordType::FundingRate,
+        funding_rate_history_v020(),
+        funding_rate_history(),
+        limit - moved,
+    )?;
+    moved += rekey_records(
+        storage,
+        RecordType::Liquidation,
+        liquidation_history_v020(),
+        liquidation_history(),
+        limit - moved,
+    )?;
+    moved += rekey_records(
+        storage,
+        RecordType::Trade,
+        trade_history_v020(),
+        trade_history(),
+        limit - moved,
+    )?;
+    if moved < limit {
+        RECORD_MIGRATION.remove(storage);
+    }
+    Ok(moved as u32)
+}
+
+// 0.1.0 kept trader records in their own (user, id) maps
+fn move_v010_records<'a, T, I>(
+    storage: &mut dyn Storage,
+    record_type: RecordType,
+    old: Map<'a, (&'a Addr, String), T>,
+    new: IndexedMap<'a, U64Key, T, I>,
+    limit: usize,
+) -> Result<usize, ContractError>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    let keys = old
+        .keys(storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<Vec<_>>();
+    for key in keys.iter() {
+        let (user, id) = v010_record_key(key)?;
+        let numeric_id = parse_record_id(&id)?;
+        let record = old.load(storage, (&user, id.clone()))?;
+        old.remove(storage, (&user, id));
+        new.save(storage, U64Key::new(numeric_id), &record)?;
+        log_record(storage, record_type, numeric_id)?;
+    }
+    Ok(keys.len())
+}
+
+// the string ids are ascii digits, so they sort after every big-endian id below 2^56 that
+// already shares the namespace
+fn rekey_records<'a, T, I>(
+    storage: &mut dyn Storage,
+    record_type: RecordType,
+    old: IndexedMap<'a, String, T, I>,
+    new: IndexedMap<'a, U64Key, T, I>,
+    limit: usize,
+) -> Result<usize, ContractError>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    let start = Some(Bound::inclusive(b"0".to_vec()));
+    let records = old
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (id, record) in records.iter() {
+        let id = String::from_utf8(id.clone()).map_err(StdError::from)?;
+        let numeric_id = parse_record_id(&id)?;
+        old.remove(storage, id)?;
+        new.save(storage, U64Key::new(numeric_id), record)?;
+        log_record(storage, record_type, numeric_id)?;
+    }
+    Ok(records.len())
+}
+
+fn parse_record_id(id: &str) -> StdResult<u64> {
+    id.parse::<u64>()
+        .map_err(|_| StdError::parse_err("u64", format!("invalid record id {}", id)))
+}
+
+// a 0.1.0 (user, id) key, the user is length prefixed
+fn v010_record_key(key: &[u8]) -> StdResult<(Addr, String)> {
+    let invalid = || StdError::generic_err(format!("invalid 0.1.0 record key {:?}", key));
+    let prefix = key.get(..2).ok_or_else(invalid)?;
+    let user_len = u16::from_be_bytes([prefix[0], prefix[1]]) as usize;
+    let user = key.get(2..2 + user_len).ok_or_else(invalid)?;
+    Ok((
+        Addr::unchecked(String::from_utf8(user.to_vec())?),
+        String::from_utf8(key[2 + user_len..].to_vec())?,
+    ))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::GetConfig {} => Ok(to_binary(&get_config(deps)?)?),
         QueryMsg::GetLength {} => Ok(to_binary(&get_length(deps)?)?),
-        QueryMsg::GetCurveHistory { start_after, limit } => {
-            Ok(to_binary(&get_curve_history(deps, start_after, limit)?)?)
-        }
+        QueryMsg::GetCurveHistory {
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_curve_history(
+            deps,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
         QueryMsg::GetDepositHistory {
             user_address,
             start_after,
             limit,
+            from_ts,
+            to_ts,
+            order,
         } => Ok(to_binary(&get_deposit_history(
             deps,
             user_address,
             start_after,
             limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetGlobalDepositHistory {
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_global_deposit_history(
+            deps,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetFundingPaymentHistory {
+            user_address,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_funding_payment_history(
+            deps,
+            user_address,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetGlobalFundingPaymentHistory {
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_global_funding_payment_history(
+            deps,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetFundingRateHistory {
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_funding_rate_history(
+            deps,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetLiquidationHistory {
+            user_address,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_liquidation_history(
+            deps,
+            user_address,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetGlobalLiquidationHistory {
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_global_liquidation_history(
+            deps,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetTradeHistory {
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_trade_history(
+            deps,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        )?)?),
+        QueryMsg::GetTradeHistoryByAddress {
+            user_address,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_trade_history_by_user(
+            deps,
+            user_address,
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order,
         )?)?),
-        QueryMsg::GetFundingPaymentHistory {
-            user_address,
+        QueryMsg::GetTradeHistoryByMarket {
+            market_index,
             start_after,
             limit,
-        } => Ok(to_binary(&get_funding_payment_history(
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_trade_history_by_market(
             deps,
-            user_address,
+            market_index,
             start_after,
             limit,
+            from_ts,
+            to_ts,
+            order,
         )?)?),
-        QueryMsg::GetFundingRateHistory { start_after, limit } => Ok(to_binary(
-            &get_funding_rate_history(deps, start_after, limit)?,
-        )?),
-        QueryMsg::GetLiquidationHistory {
-            user_address,
+        QueryMsg::GetCurveHistoryByMarket {
+            market_index,
             start_after,
             limit,
-        } => Ok(to_binary(&get_liquidation_history(
+            from_ts,
+            to_ts,
+            order,
+        } => Ok(to_binary(&get_curve_history_by_market(
             deps,
-            user_address,
+            market_index,
             start_after,
             limit,
+            from_ts,
+            to_ts,
+            order,
         )?)?),
-        QueryMsg::GetTradeHistory { start_after, limit } => {
-            Ok(to_binary(&get_trade_history(deps, start_after, limit)?)?)
-        }
-        QueryMsg::GetTradeHistoryByAddress {
-            user_address,
+        QueryMsg::GetCandles {
+            market_index,
+            resolution,
+            from_ts,
+            to_ts,
+            limit,
+        } => Ok(to_binary(&get_candles(
+            deps,
+            market_index,
+            resolution,
+            from_ts,
+            to_ts,
+            limit,
+        )?)?),
+        QueryMsg::GetAmmSnapshots {
+            market_index,
+            from_ts,
+            to_ts,
             start_after,
             limit,
-        } => Ok(to_binary(&get_trade_history_by_user(
+            order,
+        } => Ok(to_binary(&get_amm_snapshots(
             deps,
-            user_address,
+            market_index,
+            from_ts,
+            to_ts,
             start_after,
             limit,
+            order,
+        )?)?),
+        QueryMsg::GetWriters { start_after, limit } => {
+            Ok(to_binary(&get_writers(deps, start_after, limit)?)?)
+        }
+        QueryMsg::GetRecordsSince { global_seq, limit } => {
+            Ok(to_binary(&get_records_since(deps, global_seq, limit)?)?)
+        }
+        QueryMsg::GetRetention {} => Ok(to_binary(&get_retention(deps)?)?),
+        QueryMsg::GetUserStats { user_address } => {
+            Ok(to_binary(&get_user_stats(deps, user_address)?)?)
+        }
+        QueryMsg::GetLeaderboard {
+            metric,
+            start_day,
+            end_day,
+            limit,
+        } => Ok(to_binary(&get_leaderboard(
+            deps, metric, start_day, end_day, limit,
         )?)?),
     }
 }
 
+// ids are handed out in ts order, see advance_ts, so a ts range is a run of consecutive ids:
+// the walk starts at the near end of the range and the caller stops at the far end with take_while.
+// the cursor goes on the upper bound when walking the keys backwards
+#[allow(clippy::too_many_arguments)]
+fn ts_range_bounds<'a, T, I>(
+    storage: &dyn Storage,
+    records: &IndexedMap<'a, U64Key, T, I>,
+    ts: fn(&T) -> u64,
+    start_after: Option<u64>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
+) -> StdResult<(Option<Bound>, Option<Bound>, Order)>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    match order.unwrap_or(OrderBy::Descending) {
+        OrderBy::Ascending => {
+            let from_id = from_ts
+                .map(|from| first_id_from(storage, records, ts, from))
+                .transpose()?;
+            let min = start_after.map(|start| start.saturating_add(1)).max(from_id);
+            let min = min.map(|id| Bound::Inclusive(U64Key::new(id).joined_key()));
+            Ok((min, None, Order::Ascending))
+        }
+        OrderBy::Descending => {
+            let to_id = to_ts
+                .and_then(|to| to.checked_add(1))
+                .map(|after| first_id_from(storage, records, ts, after))
+                .transpose()?;
+            let max = match (start_after, to_id) {
+                (Some(start), Some(to)) => Some(start.min(to)),
+                (start, to) => start.or(to),
+            };
+            let max = max.map(|id| Bound::Exclusive(U64Key::new(id).joined_key()));
+            Ok((None, max, Order::Descending))
+        }
+    }
+}
+
+// binary search for the first id recorded at or after `from`, one past the last id if none is.
+// pruning leaves gaps, so every probe reads the first record left at or above the midpoint
+fn first_id_from<'a, T, I>(
+    storage: &dyn Storage,
+    records: &IndexedMap<'a, U64Key, T, I>,
+    ts: fn(&T) -> u64,
+    from: u64,
+) -> StdResult<u64>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    let mut hi = match records
+        .range(storage, None, None, Order::Descending)
+        .next()
+    {
+        Some(item) => record_id(&item?.0) + 1,
+        None => return Ok(0),
+    };
+    let mut lo = 0;
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let min = Some(Bound::Inclusive(U64Key::new(mid).joined_key()));
+        let max = Some(Bound::Exclusive(U64Key::new(hi).joined_key()));
+        match records.range(storage, min, max, Order::Ascending).next() {
+            Some(item) => {
+                let (k, record) = item?;
+                if ts(&record) >= from {
+                    hi = mid;
+                } else {
+                    lo = record_id(&k) + 1;
+                }
+            }
+            None => hi = mid,
+        }
+    }
+    Ok(lo)
+}
+
+// history primary keys are the big-endian record id
+fn record_id(pk: &[u8]) -> u64 {
+    pk.try_into().map(u64::from_be_bytes).unwrap_or_default()
+}
+
+fn in_ts_range(ts: u64, from_ts: Option<u64>, to_ts: Option<u64>) -> bool {
+    ts >= from_ts.unwrap_or(0) && ts <= to_ts.unwrap_or(u64::MAX)
+}
+
 fn get_config(deps: Deps) -> Result<ConfigResponse, ContractError> {
     let state = STATE.load(deps.storage)?;
     let config = ConfigResponse {
         clearing_house: state.clearing_house,
         owner: state.owner,
+        prune_reward: PRUNE_REWARD.may_load(deps.storage)?,
     };
     Ok(config)
 }
 
+pub fn get_writers(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<WriterResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|start| Bound::exclusive(start.as_bytes()));
+    let writers = WRITERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (writer, record_types) = item?;
+            Ok(WriterResponse {
+                writer: String::from_utf8(writer).map_err(StdError::from)?,
+                record_types,
+            })
+        })
+        .collect::<StdResult<Vec<WriterResponse>>>()?;
+    Ok(writers)
+}
+
+pub fn get_records_since(
+    deps: Deps,
+    global_seq: u64,
+    limit: Option<u32>,
+) -> Result<Vec<SequencedRecordResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = Some(Bound::Exclusive(U64Key::new(global_seq).joined_key()));
+    RECORD_LOG
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|entry| {
+            let (pk, entry) = entry?;
+            Ok(SequencedRecordResponse {
+                global_seq: record_id(&pk),
+                record_type: entry.record_type,
+                id: entry.id,
+                record: load_record(deps.storage, &entry)?,
+            })
+        })
+        .collect()
+}
+
+fn load_record(storage: &dyn Storage, entry: &LogEntry) -> StdResult<Option<HistoryRecord>> {
+    let id = U64Key::new(entry.id);
+    let record = match entry.record_type {
+        RecordType::AmmSnapshot => amm_snapshot_history()
+            .may_load(storage, id)?
+            .map(HistoryRecord::AmmSnapshot),
+        RecordType::Curve => curve_history()
+            .may_load(storage, id)?
+            .map(HistoryRecord::Curve),
+        RecordType::Deposit => deposit_history()
+            .may_load(storage, id)?
+            .map(HistoryRecord::Deposit),
+        RecordType::FundingPayment => funding_payment_history()
+            .may_load(storage, id)?
+            .map(HistoryRecord::FundingPayment),
+        RecordType::FundingRate => funding_rate_history()
+            .may_load(storage, id)?
+            .map(HistoryRecord::FundingRate),
+        RecordType::Liquidation => liquidation_history()
+            .may_load(storage, id)?
+            .map(HistoryRecord::Liquidation),
+        RecordType::Trade => trade_history()
+            .may_load(storage, id)?
+            .map(HistoryRecord::Trade),
+    };
+    Ok(record)
+}
+
+pub fn get_retention(deps: Deps) -> Result<Vec<RetentionResponse>, ContractError> {
+    let mut retention = vec![];
+    for record_type in RecordType::ALL.iter() {
+        let policy = RETENTION
+            .may_load(deps.storage, record_type.key())?
+            .unwrap_or_default();
+        retention.push(RetentionResponse {
+            record_type: *record_type,
+            max_count: policy.max_count,
+            max_age: policy.max_age,
+            pruned: PRUNED
+                .may_load(deps.storage, record_type.key())?
+                .unwrap_or_default(),
+        });
+    }
+    Ok(retention)
+}
+
 pub fn get_length(deps: Deps) -> Result<LengthResponse, ContractError> {
     let len = LENGTH.load(deps.storage)?;
     let length = LengthResponse {
+        global_seq: GLOBAL_SEQ.may_load(deps.storage)?.unwrap_or_default(),
+        amm_snapshot_history_length: len.amm_snapshot_history_length,
         curve_history_length: len.curve_history_length,
         deposit_history_length: len.deposit_history_length,
         funding_payment_history_length: len.funding_payment_history_length,
@@ -394,21 +1581,32 @@ pub fn get_length(deps: Deps) -> Result<LengthResponse, ContractError> {
 
 pub fn get_curve_history(
     deps: Deps,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
 ) -> Result<Vec<CurveHistoryResponse>, ContractError> {
     let chl = LENGTH.load(deps.storage)?.curve_history_length;
     let mut curves: Vec<CurveHistoryResponse> = vec![];
     if chl > 0 {
         let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-        let start = start_after
-            .map(|start| start.joined_key())
-            .map(Bound::Exclusive);
+        let (min, max, order) = ts_range_bounds(
+            deps.storage,
+            &curve_history(),
+            |r| r.ts,
+            start_after,
+            from_ts,
+            to_ts,
+            order,
+        )?;
 
-        curves = CURVEHISTORY
-            .range(deps.storage, start, None, Order::Descending)
+        curves = curve_history()
+            .range(deps.storage, min, max, order)
             .filter_map(|curve_record| {
                 curve_record.ok().map(|curve| CurveHistoryResponse {
+                    id: record_id(&curve.0),
+                    writer: curve.1.writer.as_ref().map(Addr::to_string),
                     ts: curve.1.ts,
                     market_index: curve.1.market_index,
                     peg_multiplier_before: curve.1.peg_multiplier_before,
@@ -429,6 +1627,7 @@ pub fn get_curve_history(
                     oracle_price: curve.1.oracle_price,
                 })
             })
+            .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
             .take(limit)
             .collect();
     }
@@ -438,19 +1637,70 @@ pub fn get_curve_history(
 pub fn get_deposit_history(
     deps: Deps,
     user_address: String,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
 ) -> Result<Vec<DepositHistoryResponse>, ContractError> {
-    let user_addr = addr_validate_to_lower(deps.api, &user_address.to_string())?;
+    let user_addr = account_validate_to_lower(deps.api, &user_address)?;
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after
-        .map(|start| start.joined_key())
-        .map(Bound::Exclusive);
-    let deposit_history = DEPOSIT_HISTORY
-        .prefix(&user_addr)
-        .range(deps.storage, start, None, Order::Descending)
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &deposit_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let deposit_history = deposit_history()
+        .idx
+        .user
+        .prefix(user_addr.as_bytes().to_vec())
+        .range(deps.storage, min, max, order)
+        .filter_map(|records| {
+            records.ok().map(|record| DepositHistoryResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
+                ts: record.1.ts,
+                user: record.1.user.to_string(),
+                direction: record.1.direction,
+                collateral_before: record.1.collateral_before,
+                cumulative_deposits_before: record.1.cumulative_deposits_before,
+                amount: record.1.amount,
+            })
+        })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
+        .take(limit)
+        .collect();
+    Ok(deposit_history)
+}
+
+pub fn get_global_deposit_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
+) -> Result<Vec<DepositHistoryResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &deposit_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let deposit_history = deposit_history()
+        .range(deps.storage, min, max, order)
         .filter_map(|records| {
             records.ok().map(|record| DepositHistoryResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
                 ts: record.1.ts,
                 user: record.1.user.to_string(),
                 direction: record.1.direction,
@@ -459,6 +1709,7 @@ pub fn get_deposit_history(
                 amount: record.1.amount,
             })
         })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
         .take(limit)
         .collect();
     Ok(deposit_history)
@@ -467,21 +1718,77 @@ pub fn get_deposit_history(
 pub fn get_funding_payment_history(
     deps: Deps,
     user_address: String,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
 ) -> Result<Vec<FundingPaymentHistoryResponse>, ContractError> {
-    let user_addr = addr_validate_to_lower(deps.api, user_address.as_str())?;
+    let user_addr = account_validate_to_lower(deps.api, &user_address)?;
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after
-        .map(|start| start.joined_key())
-        .map(Bound::Exclusive);
-    let funding_payment_history = FUNDING_PAYMENT_HISTORY
-        .prefix(&user_addr)
-        .range(deps.storage, start, None, Order::Descending)
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &funding_payment_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let funding_payment_history = funding_payment_history()
+        .idx
+        .user
+        .prefix(user_addr.as_bytes().to_vec())
+        .range(deps.storage, min, max, order)
+        .filter_map(|funding_payments| {
+            funding_payments
+                .ok()
+                .map(|fp| FundingPaymentHistoryResponse {
+                    id: record_id(&fp.0),
+                    writer: fp.1.writer.as_ref().map(Addr::to_string),
+                    ts: fp.1.ts,
+                    user: fp.1.user.to_string(),
+                    market_index: fp.1.market_index,
+                    funding_payment: fp.1.funding_payment,
+                    base_asset_amount: fp.1.base_asset_amount,
+                    user_last_cumulative_funding: fp.1.user_last_cumulative_funding,
+                    user_last_funding_rate_ts: fp.1.user_last_funding_rate_ts,
+                    amm_cumulative_funding_long: fp.1.amm_cumulative_funding_long,
+                    amm_cumulative_funding_short: fp.1.amm_cumulative_funding_short,
+                })
+        })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
+        .take(limit)
+        .collect();
+    Ok(funding_payment_history)
+}
+
+pub fn get_global_funding_payment_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
+) -> Result<Vec<FundingPaymentHistoryResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &funding_payment_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let funding_payment_history = funding_payment_history()
+        .range(deps.storage, min, max, order)
         .filter_map(|funding_payments| {
             funding_payments
                 .ok()
                 .map(|fp| FundingPaymentHistoryResponse {
+                    id: record_id(&fp.0),
+                    writer: fp.1.writer.as_ref().map(Addr::to_string),
                     ts: fp.1.ts,
                     user: fp.1.user.to_string(),
                     market_index: fp.1.market_index,
@@ -493,6 +1800,7 @@ pub fn get_funding_payment_history(
                     amm_cumulative_funding_short: fp.1.amm_cumulative_funding_short,
                 })
         })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
         .take(limit)
         .collect();
     Ok(funding_payment_history)
@@ -500,22 +1808,33 @@ pub fn get_funding_payment_history(
 
 pub fn get_funding_rate_history(
     deps: Deps,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
 ) -> Result<Vec<FundingRateHistoryResponse>, ContractError> {
     let mut fr_history: Vec<FundingRateHistoryResponse> = vec![];
     let length = LENGTH.load(deps.storage)?;
     if length.funding_rate_history_length > 0 {
         let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-        let start = start_after
-            .map(|start| start.joined_key())
-            .map(Bound::Exclusive);
-        fr_history = FUNDING_RATE_HISTORY
-            .range(deps.storage, start, None, Order::Descending)
+        let (min, max, order) = ts_range_bounds(
+            deps.storage,
+            &funding_rate_history(),
+            |r| r.ts,
+            start_after,
+            from_ts,
+            to_ts,
+            order,
+        )?;
+        fr_history = funding_rate_history()
+            .range(deps.storage, min, max, order)
             .filter_map(|fr_records| {
                 fr_records
                     .ok()
                     .map(|funding_record| FundingRateHistoryResponse {
+                        id: record_id(&funding_record.0),
+                        writer: funding_record.1.writer.as_ref().map(Addr::to_string),
                         ts: funding_record.1.ts,
                         market_index: funding_record.1.market_index,
                         funding_rate: funding_record.1.funding_rate,
@@ -527,6 +1846,7 @@ pub fn get_funding_rate_history(
                         mark_price_twap: funding_record.1.mark_price_twap,
                     })
             })
+            .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
             .take(limit)
             .collect();
     }
@@ -536,20 +1856,78 @@ pub fn get_funding_rate_history(
 pub fn get_liquidation_history(
     deps: Deps,
     user_address: String,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
 ) -> Result<Vec<LiquidationHistoryResponse>, ContractError> {
-    let user_addr = addr_validate_to_lower(deps.api, &user_address)?;
+    let user_addr = account_validate_to_lower(deps.api, &user_address)?;
 
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after
-        .map(|start| start.joined_key())
-        .map(Bound::Exclusive);
-    let liq_history = LIQUIDATION_HISTORY
-        .prefix(&user_addr)
-        .range(deps.storage, start, None, Order::Descending)
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &liquidation_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let liq_history = liquidation_history()
+        .idx
+        .user
+        .prefix(user_addr.as_bytes().to_vec())
+        .range(deps.storage, min, max, order)
+        .filter_map(|records| {
+            records.ok().map(|record| LiquidationHistoryResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
+                ts: record.1.ts,
+                user: record.1.user.to_string(),
+                partial: record.1.partial,
+                base_asset_value: record.1.base_asset_value,
+                base_asset_value_closed: record.1.base_asset_value_closed,
+                liquidation_fee: record.1.liquidation_fee,
+                fee_to_liquidator: record.1.fee_to_liquidator,
+                fee_to_insurance_fund: record.1.fee_to_insurance_fund,
+                liquidator: record.1.liquidator.to_string(),
+                total_collateral: record.1.total_collateral,
+                collateral: record.1.collateral,
+                unrealized_pnl: record.1.unrealized_pnl,
+                margin_ratio: record.1.margin_ratio,
+            })
+        })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
+        .take(limit)
+        .collect();
+    Ok(liq_history)
+}
+
+pub fn get_global_liquidation_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
+) -> Result<Vec<LiquidationHistoryResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &liquidation_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let liq_history = liquidation_history()
+        .range(deps.storage, min, max, order)
         .filter_map(|records| {
             records.ok().map(|record| LiquidationHistoryResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
                 ts: record.1.ts,
                 user: record.1.user.to_string(),
                 partial: record.1.partial,
@@ -565,6 +1943,7 @@ pub fn get_liquidation_history(
                 margin_ratio: record.1.margin_ratio,
             })
         })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
         .take(limit)
         .collect();
     Ok(liq_history)
@@ -572,17 +1951,28 @@ pub fn get_liquidation_history(
 
 pub fn get_trade_history(
     deps: Deps,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
 ) -> Result<Vec<TradeHistoryResponse>, ContractError> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after
-        .map(|start| start.joined_key())
-        .map(Bound::Exclusive);
-    let trade_history = TRADE_HISTORY
-        .range(deps.storage, start, None, Order::Descending)
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &trade_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let trade_history = trade_history()
+        .range(deps.storage, min, max, order)
         .filter_map(|records| {
             records.ok().map(|record| TradeHistoryResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
                 ts: record.1.ts,
                 user: record.1.user.to_string(),
                 direction: record.1.direction,
@@ -599,6 +1989,7 @@ pub fn get_trade_history(
                 oracle_price: record.1.oracle_price,
             })
         })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
         .take(limit)
         .collect();
     Ok(trade_history)
@@ -607,19 +1998,82 @@ pub fn get_trade_history(
 pub fn get_trade_history_by_user(
     deps: Deps,
     user_address: String,
-    start_after: Option<String>,
+    start_after: Option<u64>,
     limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
 ) -> Result<Vec<TradeHistoryResponse>, ContractError> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after
-        .map(|start| start.joined_key())
-        .map(Bound::Exclusive);
-    let user_addr = addr_validate_to_lower(deps.api, &user_address)?;
-    let trade_history = TRADE_HISTORY
-        .prefix(&user_addr)
-        .range(deps.storage, start, None, Order::Descending)
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &trade_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let user_addr = account_validate_to_lower(deps.api, &user_address)?;
+    let trade_history = trade_history()
+        .idx
+        .user
+        .prefix(user_addr.as_bytes().to_vec())
+        .range(deps.storage, min, max, order)
+        .filter_map(|records| {
+            records.ok().map(|record| TradeHistoryResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
+                ts: record.1.ts,
+                user: record.1.user.to_string(),
+                direction: record.1.direction,
+                base_asset_amount: record.1.base_asset_amount,
+                quote_asset_amount: record.1.quote_asset_amount,
+                mark_price_before: record.1.mark_price_before,
+                mark_price_after: record.1.mark_price_after,
+                fee: record.1.fee,
+                referrer_reward: record.1.referrer_reward,
+                referee_discount: record.1.referee_discount,
+                token_discount: record.1.token_discount,
+                liquidation: record.1.liquidation,
+                market_index: record.1.market_index,
+                oracle_price: record.1.oracle_price,
+            })
+        })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
+        .take(limit)
+        .collect();
+    Ok(trade_history)
+}
+
+pub fn get_trade_history_by_market(
+    deps: Deps,
+    market_index: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
+) -> Result<Vec<TradeHistoryResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &trade_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let trade_history = trade_history()
+        .idx
+        .market
+        .prefix(U64Key::new(market_index))
+        .range(deps.storage, min, max, order)
         .filter_map(|records| {
             records.ok().map(|record| TradeHistoryResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
                 ts: record.1.ts,
                 user: record.1.user.to_string(),
                 direction: record.1.direction,
@@ -636,7 +2090,284 @@ pub fn get_trade_history_by_user(
                 oracle_price: record.1.oracle_price,
             })
         })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
         .take(limit)
         .collect();
     Ok(trade_history)
 }
+
+pub fn get_curve_history_by_market(
+    deps: Deps,
+    market_index: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    order: Option<OrderBy>,
+) -> Result<Vec<CurveHistoryResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &curve_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let curves = curve_history()
+        .idx
+        .market
+        .prefix(U64Key::new(market_index))
+        .range(deps.storage, min, max, order)
+        .filter_map(|curve_record| {
+            curve_record.ok().map(|curve| CurveHistoryResponse {
+                id: record_id(&curve.0),
+                writer: curve.1.writer.as_ref().map(Addr::to_string),
+                ts: curve.1.ts,
+                market_index: curve.1.market_index,
+                peg_multiplier_before: curve.1.peg_multiplier_before,
+                base_asset_reserve_before: curve.1.base_asset_reserve_before,
+                quote_asset_reserve_before: curve.1.quote_asset_reserve_before,
+                sqrt_k_before: curve.1.sqrt_k_before,
+                peg_multiplier_after: curve.1.peg_multiplier_after,
+                base_asset_reserve_after: curve.1.base_asset_reserve_after,
+                quote_asset_reserve_after: curve.1.quote_asset_reserve_after,
+                sqrt_k_after: curve.1.sqrt_k_after,
+                base_asset_amount_long: curve.1.base_asset_amount_long,
+                base_asset_amount_short: curve.1.base_asset_amount_short,
+                base_asset_amount: curve.1.base_asset_amount,
+                open_interest: curve.1.open_interest,
+                total_fee: curve.1.total_fee,
+                total_fee_minus_distributions: curve.1.total_fee_minus_distributions,
+                adjustment_cost: curve.1.adjustment_cost,
+                oracle_price: curve.1.oracle_price,
+            })
+        })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
+        .take(limit)
+        .collect();
+    Ok(curves)
+}
+
+pub fn get_candles(
+    deps: Deps,
+    market_index: u64,
+    resolution: CandleResolution,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Vec<CandleResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let min = from_ts
+        .map(|ts| U64Key::new(resolution.bucket_start(ts)).joined_key())
+        .map(Bound::Inclusive);
+    let max = to_ts
+        .map(|ts| U64Key::new(ts).joined_key())
+        .map(Bound::Inclusive);
+    let candles = CANDLES
+        .prefix((U64Key::new(market_index), U64Key::new(resolution.seconds())))
+        .range(deps.storage, min, max, Order::Ascending)
+        .filter_map(|candles| {
+            candles.ok().map(|candle| CandleResponse {
+                start_ts: candle.1.start_ts,
+                open: candle.1.open,
+                high: candle.1.high,
+                low: candle.1.low,
+                close: candle.1.close,
+                volume: candle.1.volume,
+                trade_count: candle.1.trade_count,
+            })
+        })
+        .take(limit)
+        .collect();
+    Ok(candles)
+}
+
+pub fn get_user_stats(
+    deps: Deps,
+    user_address: String,
+) -> Result<UserStatsResponse, ContractError> {
+    let user_addr = account_validate_to_lower(deps.api, &user_address)?;
+    let stats = USER_STATS
+        .may_load(deps.storage, &user_addr)?
+        .unwrap_or_default();
+    let markets = USER_MARKET_STATS
+        .prefix(&user_addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (k, market) = item?;
+            Ok(UserMarketStatsResponse {
+                market_index: u64::from_be_bytes(
+                    k.as_slice()
+                        .try_into()
+                        .map_err(|_| StdError::generic_err("invalid market index key"))?,
+                ),
+                volume: market.volume,
+                fees_paid: market.fees_paid,
+                realized_pnl: market.realized_pnl,
+                funding_paid: market.funding_paid,
+                trade_count: market.trade_count,
+            })
+        })
+        .collect::<StdResult<Vec<UserMarketStatsResponse>>>()?;
+    Ok(UserStatsResponse {
+        user: user_addr.to_string(),
+        volume: stats.volume,
+        fees_paid: stats.fees_paid,
+        realized_pnl: stats.realized_pnl,
+        funding_paid: stats.funding_paid,
+        trade_count: stats.trade_count,
+        liquidation_count: stats.liquidation_count,
+        markets,
+    })
+}
+
+// walks the ranking highest first and only loads the stats of the users it returns
+pub fn get_leaderboard(
+    deps: Deps,
+    metric: LeaderboardMetric,
+    start_day: Option<u64>,
+    end_day: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Vec<LeaderboardEntry>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let day = CandleResolution::OneDay;
+    let period = match start_day {
+        Some(start_day) => {
+            let start = day.bucket_start(start_day);
+            let end = day.bucket_start(end_day.unwrap_or(start_day));
+            if end < start || (end - start) / day.seconds() >= MAX_LEADERBOARD_DAYS {
+                return Err(ContractError::InvalidLeaderboardWindow {
+                    max_days: MAX_LEADERBOARD_DAYS,
+                });
+            }
+            if end > start {
+                return get_window_leaderboard(deps, metric, start, end, limit);
+            }
+            start
+        }
+        None => ALL_TIME,
+    };
+    let ranking = match metric {
+        LeaderboardMetric::Volume => VOLUME_RANKING,
+        LeaderboardMetric::RealizedPnl => PNL_RANKING,
+    };
+    ranking
+        .sub_prefix(U64Key::new(period))
+        .keys(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|key| {
+            // key is the length prefixed metric followed by the user
+            let user = String::from_utf8(key[18..].to_vec()).map_err(StdError::from)?;
+            let user = Addr::unchecked(user);
+            let (volume, realized_pnl) = if period == ALL_TIME {
+                let stats = USER_STATS.load(deps.storage, &user)?;
+                (stats.volume, stats.realized_pnl)
+            } else {
+                let daily = USER_DAILY_STATS.load(deps.storage, (U64Key::new(period), &user))?;
+                (daily.volume, daily.realized_pnl)
+            };
+            Ok(LeaderboardEntry {
+                user: user.to_string(),
+                volume,
+                realized_pnl,
+            })
+        })
+        .collect()
+}
+
+// the rankings are kept per day, a window of several days sums the daily stats of every user
+fn get_window_leaderboard(
+    deps: Deps,
+    metric: LeaderboardMetric,
+    start: u64,
+    end: u64,
+    limit: usize,
+) -> Result<Vec<LeaderboardEntry>, ContractError> {
+    let mut totals: BTreeMap<String, (Uint128, Number128)> = BTreeMap::new();
+    let days = (start..=end).step_by(CandleResolution::OneDay.seconds() as usize);
+    for day in days {
+        for item in USER_DAILY_STATS
+            .prefix(U64Key::new(day))
+            .range(deps.storage, None, None, Order::Ascending)
+        {
+            let (user, daily) = item?;
+            let user = String::from_utf8(user).map_err(StdError::from)?;
+            let total = totals
+                .entry(user)
+                .or_insert((Uint128::zero(), Number128::zero()));
+            total.0 = total.0.checked_add(daily.volume)?;
+            total.1 = add_signed(total.1, daily.realized_pnl.i128())?;
+        }
+    }
+
+    // ties are broken on the user, highest first, the same as the rankings
+    let mut entries = totals
+        .into_iter()
+        .map(|(user, (volume, realized_pnl))| LeaderboardEntry {
+            user,
+            volume,
+            realized_pnl,
+        })
+        .collect::<Vec<_>>();
+    match metric {
+        LeaderboardMetric::Volume => {
+            entries.sort_by(|a, b| (b.volume, &b.user).cmp(&(a.volume, &a.user)))
+        }
+        LeaderboardMetric::RealizedPnl => entries.sort_by(|a, b| {
+            (b.realized_pnl.i128(), &b.user).cmp(&(a.realized_pnl.i128(), &a.user))
+        }),
+    }
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+pub fn get_amm_snapshots(
+    deps: Deps,
+    market_index: u64,
+    from_ts: Option<u64>,
+    to_ts: Option<u64>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> Result<Vec<AmmSnapshotResponse>, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (min, max, order) = ts_range_bounds(
+        deps.storage,
+        &amm_snapshot_history(),
+        |r| r.ts,
+        start_after,
+        from_ts,
+        to_ts,
+        order,
+    )?;
+    let snapshots = amm_snapshot_history()
+        .idx
+        .market
+        .prefix(U64Key::new(market_index))
+        .range(deps.storage, min, max, order)
+        .filter_map(|records| {
+            records.ok().map(|record| AmmSnapshotResponse {
+                id: record_id(&record.0),
+                writer: record.1.writer.as_ref().map(Addr::to_string),
+                ts: record.1.ts,
+                market_index: record.1.market_index,
+                mark_price: record.1.mark_price,
+                mark_price_twap: record.1.mark_price_twap,
+                oracle_price: record.1.oracle_price,
+                base_asset_reserve: record.1.base_asset_reserve,
+                quote_asset_reserve: record.1.quote_asset_reserve,
+                sqrt_k: record.1.sqrt_k,
+                peg_multiplier: record.1.peg_multiplier,
+                base_asset_amount_long: record.1.base_asset_amount_long,
+                base_asset_amount_short: record.1.base_asset_amount_short,
+                base_asset_amount: record.1.base_asset_amount,
+                open_interest: record.1.open_interest,
+            })
+        })
+        .take_while(|record| in_ts_range(record.ts, from_ts, to_ts))
+        .take(limit)
+        .collect();
+    Ok(snapshots)
+}
@@ -9,6 +9,9 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Cannot migrate from {contract} {version}")]
+    InvalidMigration { contract: String, version: String },
+
     #[error("Clearing House history already initialized")]
     HistoryAlreadyInitialized,
 
@@ -20,6 +23,31 @@ pub enum ContractError {
 
     #[error("Math Error")]
     MathError,
+
+    #[error("Writer may not record {record_type} records")]
+    UnauthorizedWriter { record_type: String },
+
+    #[error("Writer not found")]
+    WriterNotFound {},
+
+    #[error("Nothing to prune")]
+    NothingToPrune {},
+
+    #[error("Nothing to migrate")]
+    NothingToMigrate {},
+
+    #[error("Records are still being migrated, try again once MigrateRecords is done")]
+    MigrationPending {},
+
+    #[error("A {record_type} record at {ts} is older than the last one at {last_ts}")]
+    RecordOutOfOrder {
+        record_type: String,
+        ts: u64,
+        last_ts: u64,
+    },
+
+    #[error("Leaderboard window must not end before it starts or span more than {max_days} days")]
+    InvalidLeaderboardWindow { max_days: u64 },
 }
 
 impl From<OverflowError> for ContractError {
@@ -3,6 +3,6 @@ mod error;
 pub mod msg;
 pub mod state;
 pub mod package;
-// pub mod test;
+pub mod test;
 
 pub use crate::error::ContractError;
@@ -4,12 +4,32 @@ use serde::{Deserialize, Serialize};
 
 use crate::package::types::{PositionDirection, DepositDirection};
 use crate::package::number::Number128;
-use crate::state::{CurveRecord, FundingPaymentRecord, FundingRateRecord, LiquidationRecord, TradeRecord, DepositRecord};
+use crate::state::{
+    AmmSnapshotRecord, CandleResolution, CurveRecord, FundingPaymentRecord, FundingRateRecord,
+    LiquidationRecord, PruneReward, RecordType, TradeRecord, DepositRecord,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
+// one entry of a RecordBatch, recorded in order. a record older than the last of its type fails
+// the batch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryRecord {
+    AmmSnapshot(AmmSnapshotRecord),
+    Curve(CurveRecord),
+    Deposit(DepositRecord),
+    FundingPayment(FundingPaymentRecord),
+    FundingRate(FundingRateRecord),
+    Liquidation(LiquidationRecord),
+    Trade(TradeRecord),
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -19,6 +39,9 @@ pub enum ExecuteMsg {
     UpdateClearingHouse {
         new_house: String,
     },
+    RecordAmmSnapshot {
+        s: AmmSnapshotRecord
+    },
     RecordCurve {
         c: CurveRecord
     },
@@ -40,52 +63,214 @@ pub enum ExecuteMsg {
     RecordTrade {
         t: TradeRecord
     },
+    RecordBatch {
+        records: Vec<HistoryRecord>
+    },
+    // replaces the record types the writer may record
+    AddWriter {
+        writer: String,
+        record_types: Vec<RecordType>,
+    },
+    RemoveWriter {
+        writer: String,
+    },
+    UpdateRetention {
+        record_type: RecordType,
+        max_count: Option<u64>,
+        max_age: Option<u64>,
+    },
+    // none turns the reward off
+    UpdatePruneReward {
+        reward: Option<PruneReward>,
+    },
+    Prune {
+        record_type: RecordType,
+        limit: Option<u32>,
+    },
+    // anyone can call it, re-keys the next records left over from 0.1.0 or 0.2.0
+    MigrateRecords {
+        limit: Option<u32>,
+    },
     // RecordOrder {
     //     o: OrderRecord
     // },
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    Volume,
+    RealizedPnl,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderBy {
+    Ascending,
+    Descending,
+}
+
+// history queries default to newest first; from_ts and to_ts are inclusive,
+// start_after is the id of the last record of the previous page
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     GetConfig {},
     GetLength {},
     GetCurveHistory {
-        start_after: Option<String>,
+        start_after: Option<u64>,
         limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
     },
     GetDepositHistory {
         user_address: String,
-        start_after: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
+    },
+    // the global feeds list every user's records, like GetTradeHistory does for trades
+    GetGlobalDepositHistory {
+        start_after: Option<u64>,
         limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
     },
     GetFundingPaymentHistory {
         user_address: String,
-        start_after: Option<String>,
+        start_after: Option<u64>,
         limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
+    },
+    GetGlobalFundingPaymentHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
     },
     GetFundingRateHistory {
-        start_after: Option<String>,
+        start_after: Option<u64>,
         limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
     },
     GetLiquidationHistory {
         user_address: String,
-        start_after: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
+    },
+    GetGlobalLiquidationHistory {
+        start_after: Option<u64>,
         limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
     },
     GetTradeHistory {
-        start_after: Option<String>,
+        start_after: Option<u64>,
         limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
     },
     GetTradeHistoryByAddress {
         user_address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
+    },
+    GetTradeHistoryByMarket {
+        market_index: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
+    },
+    GetCurveHistoryByMarket {
+        market_index: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: Option<OrderBy>,
+    },
+    // oldest first, from_ts is rounded down to the start of its candle
+    GetCandles {
+        market_index: u64,
+        resolution: CandleResolution,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetAmmSnapshots {
+        market_index: u64,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    GetWriters {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    // every record type in write order, starting after the given global sequence number;
+    // records from before 0.3.0 are listed in the order MigrateRecords moved them
+    GetRecordsSince {
+        global_seq: u64,
+        limit: Option<u32>,
+    },
+    GetRetention {},
+    GetUserStats {
+        user_address: String,
+    },
+    // all time without a start day, otherwise summed over the days start_day to end_day fall
+    // into, both included. end_day defaults to start_day
+    GetLeaderboard {
+        metric: LeaderboardMetric,
+        start_day: Option<u64>,
+        end_day: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AmmSnapshotResponse {
+    pub id: u64,
+    pub writer: Option<String>,
+    pub ts: u64,
+    pub market_index: u64,
+    pub mark_price: Uint128,
+    pub mark_price_twap: Uint128,
+    pub oracle_price: Number128,
+    pub base_asset_reserve: Uint128,
+    pub quote_asset_reserve: Uint128,
+    pub sqrt_k: Uint128,
+    pub peg_multiplier: Uint128,
+    pub base_asset_amount_long: Uint128,
+    pub base_asset_amount_short: Uint128,
+    pub base_asset_amount: Number128,
+    pub open_interest: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CurveHistoryResponse {
+    pub id: u64,
+    pub writer: Option<String>,
     pub ts: u64,
     pub market_index: u64,
     pub peg_multiplier_before: Uint128,
@@ -108,6 +293,8 @@ pub struct CurveHistoryResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct DepositHistoryResponse {
+    pub id: u64,
+    pub writer: Option<String>,
     pub ts: u64,
     pub user: String,
     pub direction: DepositDirection,
@@ -118,6 +305,8 @@ pub struct DepositHistoryResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct FundingPaymentHistoryResponse {
+    pub id: u64,
+    pub writer: Option<String>,
     pub ts: u64,
     pub user: String,
     pub market_index: u64,
@@ -131,6 +320,8 @@ pub struct FundingPaymentHistoryResponse {
     
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct FundingRateHistoryResponse {
+    pub id: u64,
+    pub writer: Option<String>,
     pub ts: u64,
     pub market_index: u64,
     pub funding_rate: Number128,
@@ -142,6 +333,8 @@ pub struct FundingRateHistoryResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct LiquidationHistoryResponse {
+    pub id: u64,
+    pub writer: Option<String>,
     pub ts: u64,
     pub user: String,
     pub partial: bool,
@@ -159,6 +352,8 @@ pub struct LiquidationHistoryResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TradeHistoryResponse {
+    pub id: u64,
+    pub writer: Option<String>,
     pub ts: u64,
     pub user: String,
     pub direction: PositionDirection,
@@ -175,8 +370,74 @@ pub struct TradeHistoryResponse {
     pub oracle_price: Number128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CandleResponse {
+    pub start_ts: u64,
+    pub open: Uint128,
+    pub high: Uint128,
+    pub low: Uint128,
+    pub close: Uint128,
+    pub volume: Uint128,
+    pub trade_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserMarketStatsResponse {
+    pub market_index: u64,
+    pub volume: Uint128,
+    pub fees_paid: Uint128,
+    pub realized_pnl: Number128,
+    pub funding_paid: Number128,
+    pub trade_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStatsResponse {
+    pub user: String,
+    pub volume: Uint128,
+    pub fees_paid: Uint128,
+    pub realized_pnl: Number128,
+    pub funding_paid: Number128,
+    pub trade_count: u64,
+    pub liquidation_count: u64,
+    pub markets: Vec<UserMarketStatsResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardEntry {
+    pub user: String,
+    pub volume: Uint128,
+    pub realized_pnl: Number128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WriterResponse {
+    pub writer: String,
+    pub record_types: Vec<RecordType>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RetentionResponse {
+    pub record_type: RecordType,
+    pub max_count: Option<u64>,
+    pub max_age: Option<u64>,
+    pub pruned: u64,
+}
+
+// pruning drops the entry together with its record, record is none only if the record is gone
+// without its entry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SequencedRecordResponse {
+    pub global_seq: u64,
+    pub record_type: RecordType,
+    pub id: u64,
+    pub record: Option<HistoryRecord>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct LengthResponse {
+    pub global_seq: u64,
+    pub amm_snapshot_history_length: u64,
     pub curve_history_length: u64,
     pub deposit_history_length: u64,
     pub funding_payment_history_length: u64,
@@ -190,4 +451,5 @@ pub struct LengthResponse {
 pub struct ConfigResponse {
     pub clearing_house: Addr,
     pub owner: Addr,
+    pub prune_reward: Option<PruneReward>,
 }
\ No newline at end of file
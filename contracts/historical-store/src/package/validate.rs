@@ -8,4 +8,24 @@ pub fn addr_validate_to_lower(api: &dyn Api, addr: &str) -> StdResult<Addr> {
         )));
     }
     api.addr_validate(addr)
-}
\ No newline at end of file
+}
+
+// the clearing house keys subaccount n of a wallet as `<wallet>/<n>`, only the wallet is an
+// address so the suffix is checked to be the subaccount number and kept as is
+pub fn account_validate_to_lower(api: &dyn Api, account: &str) -> StdResult<Addr> {
+    match account.split_once('/') {
+        Some((wallet, subaccount)) => {
+            let wallet = addr_validate_to_lower(api, wallet)?;
+            match subaccount.parse::<u64>() {
+                Ok(n) if n != 0 && n.to_string() == subaccount => {
+                    Ok(Addr::unchecked(format!("{}/{}", wallet, n)))
+                }
+                _ => Err(StdError::generic_err(format!(
+                    "Account {} should be a wallet or <wallet>/<subaccount>",
+                    account
+                ))),
+            }
+        }
+        None => addr_validate_to_lower(api, account),
+    }
+}
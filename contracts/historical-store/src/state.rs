@@ -1,8 +1,8 @@
 use crate::package::number::Number128;
-use cosmwasm_std::{Uint128, Addr};
+use cosmwasm_std::{Uint128, Addr, Empty};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cw_storage_plus::{Map, Item};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, U128Key, U64Key};
 use crate::package::types::{PositionDirection, DepositDirection};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -18,6 +18,52 @@ impl Default for Type {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AmmSnapshotRecord {
+    pub ts: u64,
+    pub market_index: u64,
+    pub mark_price: Uint128,
+    pub mark_price_twap: Uint128,
+    pub oracle_price: Number128,
+    pub base_asset_reserve: Uint128,
+    pub quote_asset_reserve: Uint128,
+    pub sqrt_k: Uint128,
+    pub peg_multiplier: Uint128,
+    pub base_asset_amount_long: Uint128,
+    pub base_asset_amount_short: Uint128,
+    pub base_asset_amount: Number128,
+    pub open_interest: Uint128,
+    // set by the contract to the sender that recorded it
+    #[serde(default)]
+    pub writer: Option<Addr>,
+}
+
+pub struct AmmSnapshotIndexes<'a> {
+    pub market: MultiIndex<'a, (U64Key, Vec<u8>), AmmSnapshotRecord>,
+}
+
+impl<'a> IndexList<AmmSnapshotRecord> for AmmSnapshotIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<AmmSnapshotRecord>> + '_> {
+        let v: Vec<&dyn Index<AmmSnapshotRecord>> = vec![&self.market];
+        Box::new(v.into_iter())
+    }
+}
+
+fn amm_snapshot_history_indexes<'a>() -> AmmSnapshotIndexes<'a> {
+    AmmSnapshotIndexes {
+        market: MultiIndex::new(
+            |s, pk| (U64Key::new(s.market_index), pk),
+            "amm_snapshot_history",
+            "amm_snapshot_history__market",
+        ),
+    }
+}
+
+pub fn amm_snapshot_history<'a>(
+) -> IndexedMap<'a, U64Key, AmmSnapshotRecord, AmmSnapshotIndexes<'a>> {
+    IndexedMap::new("amm_snapshot_history", amm_snapshot_history_indexes())
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CurveRecord {
     pub ts: u64,
@@ -37,10 +83,41 @@ pub struct CurveRecord {
     pub total_fee: Uint128,
     pub total_fee_minus_distributions: Uint128,
     pub adjustment_cost: Number128,
-    pub oracle_price: Number128
+    pub oracle_price: Number128,
+    // set by the contract to the sender that recorded it
+    #[serde(default)]
+    pub writer: Option<Addr>,
+}
+
+pub struct CurveIndexes<'a> {
+    pub market: MultiIndex<'a, (U64Key, Vec<u8>), CurveRecord>,
+}
+
+impl<'a> IndexList<CurveRecord> for CurveIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CurveRecord>> + '_> {
+        let v: Vec<&dyn Index<CurveRecord>> = vec![&self.market];
+        Box::new(v.into_iter())
+    }
 }
 
-pub const CURVEHISTORY: Map<String,  CurveRecord> = Map::new("curve_history");
+fn curve_history_indexes<'a>() -> CurveIndexes<'a> {
+    CurveIndexes {
+        market: MultiIndex::new(
+            |c, pk| (U64Key::new(c.market_index), pk),
+            "curve_history",
+            "curve_history__market",
+        ),
+    }
+}
+
+pub fn curve_history<'a>() -> IndexedMap<'a, U64Key, CurveRecord, CurveIndexes<'a>> {
+    IndexedMap::new("curve_history", curve_history_indexes())
+}
+
+// keyed by id.to_string() up to 0.2.0
+pub fn curve_history_v020<'a>() -> IndexedMap<'a, String, CurveRecord, CurveIndexes<'a>> {
+    IndexedMap::new("curve_history", curve_history_indexes())
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct DepositRecord {
@@ -50,9 +127,43 @@ pub struct DepositRecord {
     pub collateral_before: Uint128,
     pub cumulative_deposits_before: Uint128,
     pub amount: u64,
+    // set by the contract to the sender that recorded it
+    #[serde(default)]
+    pub writer: Option<Addr>,
+}
+
+pub struct DepositIndexes<'a> {
+    pub user: MultiIndex<'a, (Vec<u8>, Vec<u8>), DepositRecord>,
+}
+
+impl<'a> IndexList<DepositRecord> for DepositIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<DepositRecord>> + '_> {
+        let v: Vec<&dyn Index<DepositRecord>> = vec![&self.user];
+        Box::new(v.into_iter())
+    }
+}
+
+fn deposit_history_indexes<'a>() -> DepositIndexes<'a> {
+    DepositIndexes {
+        user: MultiIndex::new(
+            |d, pk| (d.user.as_bytes().to_vec(), pk),
+            "deposit_records",
+            "deposit_records__user",
+        ),
+    }
+}
+
+pub fn deposit_history<'a>() -> IndexedMap<'a, U64Key, DepositRecord, DepositIndexes<'a>> {
+    IndexedMap::new("deposit_records", deposit_history_indexes())
+}
+
+// keyed by id.to_string() up to 0.2.0
+pub fn deposit_history_v020<'a>() -> IndexedMap<'a, String, DepositRecord, DepositIndexes<'a>> {
+    IndexedMap::new("deposit_records", deposit_history_indexes())
 }
 
-pub const DEPOSIT_HISTORY: Map<(&Addr, String),  DepositRecord> = Map::new("deposit_history");
+// keyed by (user, id) up to 0.1.0
+pub const DEPOSIT_HISTORY_V010: Map<(&Addr, String),  DepositRecord> = Map::new("deposit_history");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct FundingPaymentRecord {
@@ -65,9 +176,53 @@ pub struct FundingPaymentRecord {
     pub user_last_funding_rate_ts: u64,
     pub amm_cumulative_funding_long: Number128,
     pub amm_cumulative_funding_short: Number128,
+    // set by the contract to the sender that recorded it
+    #[serde(default)]
+    pub writer: Option<Addr>,
+}
+
+pub struct FundingPaymentIndexes<'a> {
+    pub market: MultiIndex<'a, (U64Key, Vec<u8>), FundingPaymentRecord>,
+    pub user: MultiIndex<'a, (Vec<u8>, Vec<u8>), FundingPaymentRecord>,
+}
+
+impl<'a> IndexList<FundingPaymentRecord> for FundingPaymentIndexes<'a> {
+    fn get_indexes(
+        &'_ self,
+    ) -> Box<dyn Iterator<Item = &'_ dyn Index<FundingPaymentRecord>> + '_> {
+        let v: Vec<&dyn Index<FundingPaymentRecord>> = vec![&self.market, &self.user];
+        Box::new(v.into_iter())
+    }
+}
+
+fn funding_payment_history_indexes<'a>() -> FundingPaymentIndexes<'a> {
+    FundingPaymentIndexes {
+        market: MultiIndex::new(
+            |f, pk| (U64Key::new(f.market_index), pk),
+            "funding_payment_records",
+            "funding_payment_records__market",
+        ),
+        user: MultiIndex::new(
+            |f, pk| (f.user.as_bytes().to_vec(), pk),
+            "funding_payment_records",
+            "funding_payment_records__user",
+        ),
+    }
+}
+
+pub fn funding_payment_history<'a>(
+) -> IndexedMap<'a, U64Key, FundingPaymentRecord, FundingPaymentIndexes<'a>> {
+    IndexedMap::new("funding_payment_records", funding_payment_history_indexes())
+}
+
+// keyed by id.to_string() up to 0.2.0
+pub fn funding_payment_history_v020<'a>(
+) -> IndexedMap<'a, String, FundingPaymentRecord, FundingPaymentIndexes<'a>> {
+    IndexedMap::new("funding_payment_records", funding_payment_history_indexes())
 }
 
-pub const FUNDING_PAYMENT_HISTORY: Map<(&Addr, String),  FundingPaymentRecord> = Map::new("funding_history");
+// keyed by (user, id) up to 0.1.0
+pub const FUNDING_PAYMENT_HISTORY_V010: Map<(&Addr, String),  FundingPaymentRecord> = Map::new("funding_history");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct FundingRateRecord {
@@ -78,9 +233,43 @@ pub struct FundingRateRecord {
     pub cumulative_funding_rate_short: Number128,
     pub oracle_price_twap: Number128,
     pub mark_price_twap: Uint128,
+    // set by the contract to the sender that recorded it
+    #[serde(default)]
+    pub writer: Option<Addr>,
+}
+
+pub struct FundingRateIndexes<'a> {
+    pub market: MultiIndex<'a, (U64Key, Vec<u8>), FundingRateRecord>,
+}
+
+impl<'a> IndexList<FundingRateRecord> for FundingRateIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<FundingRateRecord>> + '_> {
+        let v: Vec<&dyn Index<FundingRateRecord>> = vec![&self.market];
+        Box::new(v.into_iter())
+    }
+}
+
+fn funding_rate_history_indexes<'a>() -> FundingRateIndexes<'a> {
+    FundingRateIndexes {
+        market: MultiIndex::new(
+            |f, pk| (U64Key::new(f.market_index), pk),
+            "funding_payment_history",
+            "funding_rate_history__market",
+        ),
+    }
 }
 
-pub const FUNDING_RATE_HISTORY: Map<String,  FundingRateRecord> = Map::new("funding_payment_history");
+// keeps the namespace the funding rates were stored under up to 0.1.0
+pub fn funding_rate_history<'a>(
+) -> IndexedMap<'a, U64Key, FundingRateRecord, FundingRateIndexes<'a>> {
+    IndexedMap::new("funding_payment_history", funding_rate_history_indexes())
+}
+
+// keyed by id.to_string() up to 0.2.0
+pub fn funding_rate_history_v020<'a>(
+) -> IndexedMap<'a, String, FundingRateRecord, FundingRateIndexes<'a>> {
+    IndexedMap::new("funding_payment_history", funding_rate_history_indexes())
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct LiquidationRecord {
@@ -97,9 +286,45 @@ pub struct LiquidationRecord {
     pub collateral: Uint128,
     pub unrealized_pnl: Number128,
     pub margin_ratio: Uint128,
+    // set by the contract to the sender that recorded it
+    #[serde(default)]
+    pub writer: Option<Addr>,
 }
 
-pub const LIQUIDATION_HISTORY: Map<(&Addr, String),  LiquidationRecord> = Map::new("liquidation_history");
+pub struct LiquidationIndexes<'a> {
+    pub user: MultiIndex<'a, (Vec<u8>, Vec<u8>), LiquidationRecord>,
+}
+
+impl<'a> IndexList<LiquidationRecord> for LiquidationIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<LiquidationRecord>> + '_> {
+        let v: Vec<&dyn Index<LiquidationRecord>> = vec![&self.user];
+        Box::new(v.into_iter())
+    }
+}
+
+fn liquidation_history_indexes<'a>() -> LiquidationIndexes<'a> {
+    LiquidationIndexes {
+        user: MultiIndex::new(
+            |l, pk| (l.user.as_bytes().to_vec(), pk),
+            "liquidation_records",
+            "liquidation_records__user",
+        ),
+    }
+}
+
+pub fn liquidation_history<'a>(
+) -> IndexedMap<'a, U64Key, LiquidationRecord, LiquidationIndexes<'a>> {
+    IndexedMap::new("liquidation_records", liquidation_history_indexes())
+}
+
+// keyed by id.to_string() up to 0.2.0
+pub fn liquidation_history_v020<'a>(
+) -> IndexedMap<'a, String, LiquidationRecord, LiquidationIndexes<'a>> {
+    IndexedMap::new("liquidation_records", liquidation_history_indexes())
+}
+
+// keyed by (user, id) up to 0.1.0
+pub const LIQUIDATION_HISTORY_V010: Map<(&Addr, String),  LiquidationRecord> = Map::new("liquidation_history");
 
 // #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 // pub enum OrderAction {
@@ -150,12 +375,179 @@ pub struct TradeRecord {
     pub liquidation: bool,
     pub market_index: u64,
     pub oracle_price: Number128,
+    // set by the contract to the sender that recorded it
+    #[serde(default)]
+    pub writer: Option<Addr>,
+}
+
+pub struct TradeIndexes<'a> {
+    pub market: MultiIndex<'a, (U64Key, Vec<u8>), TradeRecord>,
+    pub user: MultiIndex<'a, (Vec<u8>, Vec<u8>), TradeRecord>,
+}
+
+impl<'a> IndexList<TradeRecord> for TradeIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TradeRecord>> + '_> {
+        let v: Vec<&dyn Index<TradeRecord>> = vec![&self.market, &self.user];
+        Box::new(v.into_iter())
+    }
+}
+
+fn trade_history_indexes<'a>() -> TradeIndexes<'a> {
+    TradeIndexes {
+        market: MultiIndex::new(
+            |t, pk| (U64Key::new(t.market_index), pk),
+            "trade_records",
+            "trade_records__market",
+        ),
+        user: MultiIndex::new(
+            |t, pk| (t.user.as_bytes().to_vec(), pk),
+            "trade_records",
+            "trade_records__user",
+        ),
+    }
+}
+
+pub fn trade_history<'a>() -> IndexedMap<'a, U64Key, TradeRecord, TradeIndexes<'a>> {
+    IndexedMap::new("trade_records", trade_history_indexes())
 }
 
-pub const TRADE_HISTORY: Map<(&Addr, String),  TradeRecord> = Map::new("trade_history");
+// keyed by id.to_string() up to 0.2.0
+pub fn trade_history_v020<'a>() -> IndexedMap<'a, String, TradeRecord, TradeIndexes<'a>> {
+    IndexedMap::new("trade_records", trade_history_indexes())
+}
+
+// keyed by (user, id) up to 0.1.0
+pub const TRADE_HISTORY_V010: Map<(&Addr, String),  TradeRecord> = Map::new("trade_history");
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CandleResolution {
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+impl CandleResolution {
+    pub const ALL: [CandleResolution; 4] = [
+        CandleResolution::OneMinute,
+        CandleResolution::FiveMinutes,
+        CandleResolution::OneHour,
+        CandleResolution::OneDay,
+    ];
+
+    pub fn seconds(&self) -> u64 {
+        match self {
+            CandleResolution::OneMinute => 60,
+            CandleResolution::FiveMinutes => 300,
+            CandleResolution::OneHour => 3_600,
+            CandleResolution::OneDay => 86_400,
+        }
+    }
+
+    // start of the candle the timestamp falls into
+    pub fn bucket_start(&self, ts: u64) -> u64 {
+        ts - ts % self.seconds()
+    }
+}
+
+// prices are the trade's mark_price_after, volume is in quote
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Candle {
+    pub start_ts: u64,
+    pub open: Uint128,
+    pub high: Uint128,
+    pub low: Uint128,
+    pub close: Uint128,
+    pub volume: Uint128,
+    pub trade_count: u64,
+}
+
+// keyed by (market_index, resolution seconds, start_ts)
+pub const CANDLES: Map<(U64Key, U64Key, U64Key), Candle> = Map::new("candles");
+
+// running totals, funding_paid is positive when the user paid funding
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStats {
+    pub volume: Uint128,
+    pub fees_paid: Uint128,
+    pub realized_pnl: Number128,
+    pub funding_paid: Number128,
+    pub trade_count: u64,
+    pub liquidation_count: u64,
+}
+
+impl Default for UserStats {
+    fn default() -> Self {
+        UserStats {
+            volume: Uint128::zero(),
+            fees_paid: Uint128::zero(),
+            realized_pnl: Number128::zero(),
+            funding_paid: Number128::zero(),
+            trade_count: 0,
+            liquidation_count: 0,
+        }
+    }
+}
+
+// base_asset_amount and quote_entry_amount replay the position to realize pnl on reduces
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserMarketStats {
+    pub volume: Uint128,
+    pub fees_paid: Uint128,
+    pub realized_pnl: Number128,
+    pub funding_paid: Number128,
+    pub trade_count: u64,
+    pub base_asset_amount: Number128,
+    pub quote_entry_amount: Uint128,
+}
+
+impl Default for UserMarketStats {
+    fn default() -> Self {
+        UserMarketStats {
+            volume: Uint128::zero(),
+            fees_paid: Uint128::zero(),
+            realized_pnl: Number128::zero(),
+            funding_paid: Number128::zero(),
+            trade_count: 0,
+            base_asset_amount: Number128::zero(),
+            quote_entry_amount: Uint128::zero(),
+        }
+    }
+}
+
+// per day buckets the leaderboard sums over
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserDailyStats {
+    pub volume: Uint128,
+    pub realized_pnl: Number128,
+}
+
+impl Default for UserDailyStats {
+    fn default() -> Self {
+        UserDailyStats {
+            volume: Uint128::zero(),
+            realized_pnl: Number128::zero(),
+        }
+    }
+}
+
+pub const USER_STATS: Map<&Addr, UserStats> = Map::new("user_stats");
+pub const USER_MARKET_STATS: Map<(&Addr, U64Key), UserMarketStats> = Map::new("user_market_stats");
+// keyed by (day start ts, user)
+pub const USER_DAILY_STATS: Map<(U64Key, &Addr), UserDailyStats> = Map::new("user_daily_stats");
+
+// leaderboards keyed by (period, metric, user) so a ranking is read highest first, the period
+// is a day start or ALL_TIME. pnl is stored as the signed value with its sign bit flipped
+pub const ALL_TIME: u64 = u64::MAX;
+pub const VOLUME_RANKING: Map<(U64Key, U128Key, &Addr), Empty> = Map::new("volume_ranking");
+pub const PNL_RANKING: Map<(U64Key, U128Key, &Addr), Empty> = Map::new("pnl_ranking");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Length {
+    // absent from lengths saved before amm snapshots were recorded
+    #[serde(default)]
+    pub amm_snapshot_history_length: u64,
     pub curve_history_length: u64,
     pub deposit_history_length: u64,
     pub funding_payment_history_length: u64,
@@ -171,5 +563,78 @@ pub struct State {
     pub owner: Addr,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordType {
+    AmmSnapshot,
+    Curve,
+    Deposit,
+    FundingPayment,
+    FundingRate,
+    Liquidation,
+    Trade,
+}
+
+impl RecordType {
+    pub const ALL: [RecordType; 7] = [
+        RecordType::AmmSnapshot,
+        RecordType::Curve,
+        RecordType::Deposit,
+        RecordType::FundingPayment,
+        RecordType::FundingRate,
+        RecordType::Liquidation,
+        RecordType::Trade,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            RecordType::AmmSnapshot => "amm_snapshot",
+            RecordType::Curve => "curve",
+            RecordType::Deposit => "deposit",
+            RecordType::FundingPayment => "funding_payment",
+            RecordType::FundingRate => "funding_rate",
+            RecordType::Liquidation => "liquidation",
+            RecordType::Trade => "trade",
+        }
+    }
+}
+
+// a record is prunable once either limit is exceeded, none keeps everything
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct RetentionPolicy {
+    pub max_count: Option<u64>,
+    pub max_age: Option<u64>,
+}
+
+// paid out of the contract balance to whoever calls prune
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PruneReward {
+    pub denom: String,
+    pub per_record: Uint128,
+}
+
 pub const STATE: Item<State> = Item::new("state");
-pub const LENGTH : Item<Length> = Item::new("length");
\ No newline at end of file
+pub const LENGTH : Item<Length> = Item::new("length");
+// record types each writer besides the clearing house may record
+pub const WRITERS: Map<&Addr, Vec<RecordType>> = Map::new("writers");
+pub const RETENTION: Map<&str, RetentionPolicy> = Map::new("retention");
+// ids are sequential, so the oldest live record of a type is pruned + 1
+pub const PRUNED: Map<&str, u64> = Map::new("pruned");
+// ts of the newest record of each type, an older record is rejected so ids stay in ts order
+pub const LAST_TS: Map<&str, u64> = Map::new("last_ts");
+pub const PRUNE_REWARD: Item<PruneReward> = Item::new("prune_reward");
+// present while records left over from 0.1.0 or 0.2.0 are still being re-keyed
+pub const RECORD_MIGRATION: Item<bool> = Item::new("record_migration");
+
+// where the record written at a given global sequence number is stored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LogEntry {
+    pub record_type: RecordType,
+    pub id: u64,
+}
+
+// last global sequence number handed out, shared by every record type
+pub const GLOBAL_SEQ: Item<u64> = Item::new("global_seq");
+pub const RECORD_LOG: Map<U64Key, LogEntry> = Map::new("record_log");
+// (record type, id) -> the global sequence number of the record, so pruning can drop its entry
+pub const RECORD_SEQ: Map<(&str, U64Key), u64> = Map::new("record_seq");
@@ -1,72 +1,1057 @@
-
-
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary};
+    use crate::contract::{execute, instantiate, migrate, query};
+    use crate::msg::{
+        CandleResponse, CurveHistoryResponse, DepositHistoryResponse, ExecuteMsg, HistoryRecord,
+        InstantiateMsg, LeaderboardEntry, LeaderboardMetric, MigrateMsg, OrderBy, QueryMsg,
+        SequencedRecordResponse, TradeHistoryResponse, UserStatsResponse, WriterResponse,
+    };
+    use crate::package::number::Number128;
+    use crate::package::types::{DepositDirection, PositionDirection};
+    use crate::state::{
+        curve_history, curve_history_v020, trade_history, CandleResolution, CurveRecord,
+        DepositRecord, FundingPaymentRecord, LiquidationRecord, PruneReward, RecordType,
+        TradeRecord, RECORD_MIGRATION, TRADE_HISTORY_V010,
+    };
+    use crate::ContractError;
 
-    #[test]
-    fn proper_initialization() {
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        coins, from_binary, to_vec, Addr, BankMsg, CosmosMsg, Order, OwnedDeps, Response, Storage,
+        Uint128,
+    };
+    use cw_storage_plus::U64Key;
+
+    type TestDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+    const OWNER: &str = "owner";
+    const CLEARING_HOUSE: &str = "clearinghouse";
+
+    fn setup() -> TestDeps {
         let mut deps = mock_dependencies(&[]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            InstantiateMsg {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::UpdateClearingHouse {
+                new_house: CLEARING_HOUSE.to_string(),
+            },
+        )
+        .unwrap();
+        deps
+    }
+
+    fn trade_record(ts: u64, user: &str, market_index: u64) -> TradeRecord {
+        TradeRecord {
+            ts,
+            user: Addr::unchecked(user),
+            direction: PositionDirection::Long,
+            base_asset_amount: Uint128::from(10u64),
+            quote_asset_amount: Uint128::from(100u64),
+            mark_price_before: Uint128::from(10u64),
+            mark_price_after: Uint128::from(10u64),
+            fee: Uint128::from(1u64),
+            referrer_reward: Uint128::zero(),
+            referee_discount: Uint128::zero(),
+            token_discount: Uint128::zero(),
+            liquidation: false,
+            market_index,
+            oracle_price: Number128::zero(),
+            writer: None,
+        }
+    }
+
+    fn curve_record(ts: u64, market_index: u64) -> CurveRecord {
+        CurveRecord {
+            ts,
+            market_index,
+            peg_multiplier_before: Uint128::from(1u64),
+            peg_multiplier_after: Uint128::from(2u64),
+            base_asset_reserve_before: Uint128::from(1_000u64),
+            base_asset_reserve_after: Uint128::from(1_000u64),
+            quote_asset_reserve_before: Uint128::from(1_000u64),
+            quote_asset_reserve_after: Uint128::from(1_000u64),
+            sqrt_k_before: Uint128::from(1_000u64),
+            sqrt_k_after: Uint128::from(1_000u64),
+            base_asset_amount_long: Uint128::zero(),
+            base_asset_amount_short: Uint128::zero(),
+            base_asset_amount: Number128::zero(),
+            open_interest: Uint128::zero(),
+            total_fee: Uint128::zero(),
+            total_fee_minus_distributions: Uint128::zero(),
+            adjustment_cost: Number128::zero(),
+            oracle_price: Number128::zero(),
+            writer: None,
+        }
+    }
+
+    fn deposit_record(ts: u64, user: &str, amount: u64) -> DepositRecord {
+        DepositRecord {
+            ts,
+            user: Addr::unchecked(user),
+            direction: DepositDirection::DEPOSIT,
+            collateral_before: Uint128::zero(),
+            cumulative_deposits_before: Uint128::zero(),
+            amount,
+            writer: None,
+        }
+    }
+
+    fn record(deps: &mut TestDeps, sender: &str, msg: ExecuteMsg) {
+        execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg).unwrap();
+    }
+
+    fn record_trade(deps: &mut TestDeps, t: TradeRecord) {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(CLEARING_HOUSE, &[]),
+            ExecuteMsg::RecordTrade { t },
+        )
+        .unwrap();
+    }
+
+    fn trade_ids(deps: &TestDeps, msg: QueryMsg) -> Vec<u64> {
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let trades: Vec<TradeHistoryResponse> = from_binary(&res).unwrap();
+        trades.iter().map(|t| t.id).collect()
+    }
+
+    fn trade_history_msg(
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+        order: OrderBy,
+    ) -> QueryMsg {
+        QueryMsg::GetTradeHistory {
+            start_after,
+            limit,
+            from_ts,
+            to_ts,
+            order: Some(order),
+        }
+    }
+
+    #[test]
+    fn ts_range_starts_at_the_near_bound_and_stops_at_the_far_bound() {
+        let mut deps = setup();
+        // ids 1..=10 at ts 100..=1000, two trades share ts 500
+        for (i, ts) in [100, 200, 300, 400, 500, 500, 700, 800, 900, 1000]
+            .iter()
+            .enumerate()
+        {
+            let user = if i % 2 == 0 { "alice" } else { "bob" };
+            record_trade(&mut deps, trade_record(*ts, user, 0));
+        }
+
+        let asc = trade_history_msg(None, None, Some(300), Some(700), OrderBy::Ascending);
+        assert_eq!(trade_ids(&deps, asc), vec![3, 4, 5, 6, 7]);
+        let desc = trade_history_msg(None, None, Some(300), Some(700), OrderBy::Descending);
+        assert_eq!(trade_ids(&deps, desc), vec![7, 6, 5, 4, 3]);
+
+        // bounds that fall between records
+        let asc = trade_history_msg(None, None, Some(250), Some(650), OrderBy::Ascending);
+        assert_eq!(trade_ids(&deps, asc), vec![3, 4, 5, 6]);
+        let desc = trade_history_msg(None, None, Some(250), Some(650), OrderBy::Descending);
+        assert_eq!(trade_ids(&deps, desc), vec![6, 5, 4, 3]);
+
+        // paging inside the window
+        let page = trade_history_msg(None, Some(2), Some(300), Some(700), OrderBy::Ascending);
+        assert_eq!(trade_ids(&deps, page), vec![3, 4]);
+        let page = trade_history_msg(Some(4), Some(2), Some(300), Some(700), OrderBy::Ascending);
+        assert_eq!(trade_ids(&deps, page), vec![5, 6]);
+        let page = trade_history_msg(Some(6), Some(2), Some(300), Some(700), OrderBy::Descending);
+        assert_eq!(trade_ids(&deps, page), vec![5, 4]);
+        let page = trade_history_msg(Some(1), Some(2), Some(300), None, OrderBy::Ascending);
+        assert_eq!(trade_ids(&deps, page), vec![3, 4]);
+        let page = trade_history_msg(Some(9), Some(2), None, Some(700), OrderBy::Descending);
+        assert_eq!(trade_ids(&deps, page), vec![7, 6]);
+
+        // open ended and empty windows
+        let asc = trade_history_msg(None, None, Some(900), None, OrderBy::Ascending);
+        assert_eq!(trade_ids(&deps, asc), vec![9, 10]);
+        let desc = trade_history_msg(None, None, None, Some(200), OrderBy::Descending);
+        assert_eq!(trade_ids(&deps, desc), vec![2, 1]);
+        let after = trade_history_msg(None, None, Some(1001), None, OrderBy::Ascending);
+        assert!(trade_ids(&deps, after).is_empty());
+        let before = trade_history_msg(None, None, None, Some(99), OrderBy::Descending);
+        assert!(trade_ids(&deps, before).is_empty());
+        let between = trade_history_msg(None, None, Some(550), Some(650), OrderBy::Descending);
+        assert!(trade_ids(&deps, between).is_empty());
+
+        // the same window on the user index
+        let by_user = QueryMsg::GetTradeHistoryByAddress {
+            user_address: "alice".to_string(),
+            start_after: None,
+            limit: None,
+            from_ts: Some(300),
+            to_ts: Some(900),
+            order: Some(OrderBy::Ascending),
+        };
+        assert_eq!(trade_ids(&deps, by_user), vec![3, 5, 7, 9]);
+        let by_user = QueryMsg::GetTradeHistoryByAddress {
+            user_address: "bob".to_string(),
+            start_after: None,
+            limit: None,
+            from_ts: Some(300),
+            to_ts: Some(900),
+            order: Some(OrderBy::Descending),
+        };
+        assert_eq!(trade_ids(&deps, by_user), vec![8, 6, 4]);
+    }
+
+    #[test]
+    fn market_and_user_indexes_only_list_their_own_records() {
+        let mut deps = setup();
+        for i in 0..12u64 {
+            let user = if i % 3 == 0 { "alice" } else { "bob" };
+            record_trade(&mut deps, trade_record(100 + i, user, i % 2));
+            record(
+                &mut deps,
+                CLEARING_HOUSE,
+                ExecuteMsg::RecordCurve {
+                    c: curve_record(100 + i, i % 3),
+                },
+            );
+            record(
+                &mut deps,
+                CLEARING_HOUSE,
+                ExecuteMsg::RecordDeposit {
+                    d: deposit_record(100 + i, user, i),
+                },
+            );
+        }
+
+        // newest first by default, ids past nine sort numerically
+        let by_market = QueryMsg::GetTradeHistoryByMarket {
+            market_index: 1,
+            start_after: None,
+            limit: None,
+            from_ts: None,
+            to_ts: None,
+            order: None,
+        };
+        assert_eq!(trade_ids(&deps, by_market), vec![12, 10, 8, 6, 4, 2]);
+        let by_market = QueryMsg::GetTradeHistoryByMarket {
+            market_index: 0,
+            start_after: Some(7),
+            limit: Some(2),
+            from_ts: None,
+            to_ts: None,
+            order: Some(OrderBy::Ascending),
+        };
+        assert_eq!(trade_ids(&deps, by_market), vec![9, 11]);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetCurveHistoryByMarket {
+                market_index: 2,
+                start_after: None,
+                limit: None,
+                from_ts: None,
+                to_ts: None,
+                order: Some(OrderBy::Ascending),
+            },
+        )
+        .unwrap();
+        let curves: Vec<CurveHistoryResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            curves.iter().map(|c| c.id).collect::<Vec<u64>>(),
+            vec![3, 6, 9, 12]
+        );
+        assert!(curves.iter().all(|c| c.market_index == 2));
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetDepositHistory {
+                user_address: "alice".to_string(),
+                start_after: None,
+                limit: None,
+                from_ts: None,
+                to_ts: None,
+                order: None,
+            },
+        )
+        .unwrap();
+        let deposits: Vec<DepositHistoryResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            deposits.iter().map(|d| d.amount).collect::<Vec<u64>>(),
+            vec![9, 6, 3, 0]
+        );
+        assert!(deposits.iter().all(|d| d.user == "alice"));
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetGlobalDepositHistory {
+                start_after: None,
+                limit: Some(3),
+                from_ts: None,
+                to_ts: None,
+                order: None,
+            },
+        )
+        .unwrap();
+        let deposits: Vec<DepositHistoryResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            deposits.iter().map(|d| d.id).collect::<Vec<u64>>(),
+            vec![12, 11, 10]
+        );
+    }
+
+    fn candles(
+        deps: &TestDeps,
+        market_index: u64,
+        resolution: CandleResolution,
+        from_ts: Option<u64>,
+        to_ts: Option<u64>,
+    ) -> Vec<CandleResponse> {
+        let msg = QueryMsg::GetCandles {
+            market_index,
+            resolution,
+            from_ts,
+            to_ts,
+            limit: None,
+        };
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn trades_roll_up_into_candles_per_market_and_resolution() {
+        let mut deps = setup();
+        for (ts, price, market_index) in [
+            (60, 10u64, 0),
+            (90, 15, 0),
+            (100, 8, 0),
+            (110, 50, 1),
+            (130, 12, 0),
+            (400, 20, 0),
+        ] {
+            let mut t = trade_record(ts, "alice", market_index);
+            t.mark_price_after = Uint128::from(price);
+            record_trade(&mut deps, t);
+        }
+
+        let one_minute = candles(&deps, 0, CandleResolution::OneMinute, None, None);
+        assert_eq!(
+            one_minute.iter().map(|c| c.start_ts).collect::<Vec<u64>>(),
+            vec![60, 120, 360]
+        );
+        let first = &one_minute[0];
+        assert_eq!(first.open, Uint128::from(10u64));
+        assert_eq!(first.high, Uint128::from(15u64));
+        assert_eq!(first.low, Uint128::from(8u64));
+        assert_eq!(first.close, Uint128::from(8u64));
+        assert_eq!(first.volume, Uint128::from(300u64));
+        assert_eq!(first.trade_count, 3);
+
+        let five_minutes = candles(&deps, 0, CandleResolution::FiveMinutes, None, None);
+        assert_eq!(five_minutes.len(), 2);
+        assert_eq!(five_minutes[0].start_ts, 0);
+        assert_eq!(five_minutes[0].open, Uint128::from(10u64));
+        assert_eq!(five_minutes[0].close, Uint128::from(12u64));
+        assert_eq!(five_minutes[0].trade_count, 4);
+        assert_eq!(five_minutes[1].start_ts, 300);
+
+        let day = candles(&deps, 1, CandleResolution::OneDay, None, None);
+        assert_eq!(day.len(), 1);
+        assert_eq!(day[0].high, Uint128::from(50u64));
+        assert_eq!(day[0].trade_count, 1);
+
+        // from_ts rounds down to the candle it falls into, to_ts is inclusive
+        let window = candles(&deps, 0, CandleResolution::OneMinute, Some(70), Some(120));
+        assert_eq!(
+            window.iter().map(|c| c.start_ts).collect::<Vec<u64>>(),
+            vec![60, 120]
+        );
+        assert!(candles(&deps, 2, CandleResolution::OneHour, None, None).is_empty());
+    }
+
+    fn prune(deps: &mut TestDeps, limit: Option<u32>) -> Result<Response, ContractError> {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("pruner", &[]),
+            ExecuteMsg::Prune {
+                record_type: RecordType::Trade,
+                limit,
+            },
+        )
+    }
+
+    fn attr(res: &Response, key: &str) -> String {
+        res.attributes
+            .iter()
+            .find(|a| a.key == key)
+            .map(|a| a.value.clone())
+            .unwrap_or_default()
+    }
+
+    fn all_trade_ids(deps: &TestDeps) -> Vec<u64> {
+        trade_ids(
+            deps,
+            trade_history_msg(None, None, None, None, OrderBy::Ascending),
+        )
+    }
 
-        let msg = InstantiateMsg { count: 17 };
-        let info = mock_info("creator", &coins(1000, "earth"));
+    #[test]
+    fn prune_removes_the_oldest_records_and_pays_per_record_removed() {
+        let mut deps = setup();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(1_000, "uusd"));
+        let now = mock_env().block.time.seconds();
+        for i in 0..8 {
+            record_trade(&mut deps, trade_record(now - 800 + i * 100, "alice", 0));
+        }
+        record(
+            &mut deps,
+            OWNER,
+            ExecuteMsg::UpdatePruneReward {
+                reward: Some(PruneReward {
+                    denom: "uusd".to_string(),
+                    per_record: Uint128::from(10u64),
+                }),
+            },
+        );
+        // retention not exceeded yet
+        assert!(matches!(
+            prune(&mut deps, None),
+            Err(ContractError::NothingToPrune {})
+        ));
+
+        record(
+            &mut deps,
+            OWNER,
+            ExecuteMsg::UpdateRetention {
+                record_type: RecordType::Trade,
+                max_count: Some(6),
+                max_age: None,
+            },
+        );
+        let res = prune(&mut deps, Some(1)).unwrap();
+        assert_eq!(attr(&res, "pruned"), "1");
+        let res = prune(&mut deps, None).unwrap();
+        assert_eq!(attr(&res, "pruned"), "1");
+        assert_eq!(attr(&res, "reward"), "10");
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "pruner".to_string(),
+                amount: coins(10, "uusd"),
+            })
+        );
+        assert_eq!(all_trade_ids(&deps), vec![3, 4, 5, 6, 7, 8]);
+
+        // ids that no longer hold a record move the cursor but are not paid for
+        trade_history()
+            .remove(&mut deps.storage, U64Key::new(3))
+            .unwrap();
+        trade_history()
+            .remove(&mut deps.storage, U64Key::new(4))
+            .unwrap();
+        record(
+            &mut deps,
+            OWNER,
+            ExecuteMsg::UpdateRetention {
+                record_type: RecordType::Trade,
+                max_count: None,
+                max_age: Some(250),
+            },
+        );
+        let res = prune(&mut deps, None).unwrap();
+        assert_eq!(attr(&res, "pruned"), "2");
+        assert_eq!(attr(&res, "reward"), "20");
+        assert_eq!(all_trade_ids(&deps), vec![7, 8]);
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        // the ts seek skips the pruned ids
+        let window = trade_history_msg(None, None, Some(now - 500), None, OrderBy::Ascending);
+        assert_eq!(trade_ids(&deps, window), vec![7, 8]);
+        let window = trade_history_msg(None, None, None, Some(now - 150), OrderBy::Descending);
+        assert_eq!(trade_ids(&deps, window), vec![7]);
+        assert!(matches!(
+            prune(&mut deps, None),
+            Err(ContractError::NothingToPrune {})
+        ));
+    }
 
-        // it worked, let's query the state
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(17, value.count);
+    fn migrate_records(deps: &mut TestDeps, limit: Option<u32>) -> Result<Response, ContractError> {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::MigrateRecords { limit },
+        )
     }
 
     #[test]
-    fn increment() {
-        let mut deps = mock_dependencies(&coins(2, "token"));
+    fn legacy_records_are_rekeyed_in_batches() {
+        let mut deps = setup();
+        let alice = Addr::unchecked("alice");
+        // 0.1.0 trades under (user, id) and 0.2.0 curves under the string id
+        for id in 1..=25u64 {
+            TRADE_HISTORY_V010
+                .save(
+                    deps.as_mut().storage,
+                    (&alice, id.to_string()),
+                    &trade_record(id, "alice", 1),
+                )
+                .unwrap();
+        }
+        for id in 1..=10u64 {
+            curve_history_v020()
+                .save(deps.as_mut().storage, id.to_string(), &curve_record(id, 1))
+                .unwrap();
+        }
+        cw2::set_contract_version(
+            deps.as_mut().storage,
+            "crates.io:my-first-contract",
+            "0.1.0",
+        )
+        .unwrap();
+
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(attr(&res, "to_version"), "0.3.0");
+        // the first 30 records move with the migration, every trade and 5 curves
+        assert_eq!(
+            RECORD_MIGRATION.may_load(&deps.storage).unwrap(),
+            Some(true)
+        );
+        let curves = |deps: &TestDeps| {
+            (1..=10u64)
+                .filter(|id| {
+                    curve_history()
+                        .may_load(&deps.storage, U64Key::new(*id))
+                        .unwrap()
+                        .is_some()
+                })
+                .count()
+        };
+        assert_eq!(curves(&deps), 5);
+        assert!(matches!(
+            prune(&mut deps, None),
+            Err(ContractError::MigrationPending {})
+        ));
 
-        let msg = InstantiateMsg { count: 17 };
-        let info = mock_info("creator", &coins(2, "token"));
-        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = migrate_records(&mut deps, Some(3)).unwrap();
+        assert_eq!(attr(&res, "migrated"), "3");
+        assert_eq!(attr(&res, "done"), "false");
+        let res = migrate_records(&mut deps, None).unwrap();
+        assert_eq!(attr(&res, "migrated"), "2");
+        assert_eq!(attr(&res, "done"), "true");
+        assert!(matches!(
+            migrate_records(&mut deps, None),
+            Err(ContractError::NothingToMigrate {})
+        ));
 
-        // beneficiary can release it
-        let info = mock_info("anyone", &coins(2, "token"));
-        let msg = ExecuteMsg::Increment {};
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(curves(&deps), 10);
+        let ids = trade_ids(
+            &deps,
+            trade_history_msg(None, Some(20), None, None, OrderBy::Ascending),
+        );
+        assert_eq!(ids, (1..=20).collect::<Vec<u64>>());
+        let legacy = TRADE_HISTORY_V010
+            .keys(&deps.storage, None, None, Order::Ascending)
+            .count();
+        assert_eq!(legacy, 0);
+        let t = trade_history()
+            .load(&deps.storage, U64Key::new(25))
+            .unwrap();
+        assert_eq!(t.ts, 25);
 
-        // should increase counter by 1
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(18, value.count);
+        // every moved record is logged in the order it was migrated
+        let mut logged = records_since(&deps, 0, Some(20));
+        logged.extend(records_since(&deps, 20, Some(20)));
+        let seqs = logged.iter().map(|r| r.global_seq).collect::<Vec<_>>();
+        assert_eq!(seqs, (1..=35).collect::<Vec<u64>>());
+        assert!(logged[..25]
+            .iter()
+            .all(|r| r.record_type == RecordType::Trade));
+        // the legacy string keys sort "10" before "2"
+        let mut curve_ids = logged[25..]
+            .iter()
+            .filter(|r| r.record_type == RecordType::Curve && r.record.is_some())
+            .map(|r| r.id)
+            .collect::<Vec<_>>();
+        curve_ids.sort_unstable();
+        assert_eq!(curve_ids, (1..=10).collect::<Vec<u64>>());
     }
 
     #[test]
-    fn reset() {
-        let mut deps = mock_dependencies(&coins(2, "token"));
+    fn truncated_legacy_key_fails_the_migration() {
+        let mut deps = setup();
+        // a (user, id) key in the 0.1.0 trade map that is too short to hold the user length
+        let mut key = vec![0u8, 13];
+        key.extend_from_slice(b"trade_history");
+        key.push(1);
+        let value = to_vec(&trade_record(1, "alice", 1)).unwrap();
+        deps.storage.set(&key, &value);
+        cw2::set_contract_version(
+            deps.as_mut().storage,
+            "crates.io:my-first-contract",
+            "0.2.0",
+        )
+        .unwrap();
 
-        let msg = InstantiateMsg { count: 17 };
-        let info = mock_info("creator", &coins(2, "token"));
-        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+    }
+
+    fn trade(
+        deps: &mut TestDeps,
+        ts: u64,
+        user: &str,
+        direction: PositionDirection,
+        quote_asset_amount: u64,
+    ) {
+        let mut t = trade_record(ts, user, 1);
+        t.direction = direction;
+        t.quote_asset_amount = Uint128::from(quote_asset_amount);
+        record_trade(deps, t);
+    }
+
+    fn try_leaderboard(
+        deps: &TestDeps,
+        metric: LeaderboardMetric,
+        window: Option<(u64, u64)>,
+        limit: Option<u32>,
+    ) -> Result<Vec<(String, u64, i128)>, ContractError> {
+        let msg = QueryMsg::GetLeaderboard {
+            metric,
+            start_day: window.map(|w| w.0),
+            end_day: window.map(|w| w.1),
+            limit,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg)?;
+        let entries: Vec<LeaderboardEntry> = from_binary(&res).unwrap();
+        Ok(entries
+            .into_iter()
+            .map(|e| (e.user, e.volume.u128() as u64, e.realized_pnl.i128()))
+            .collect())
+    }
+
+    fn leaderboard(
+        deps: &TestDeps,
+        metric: LeaderboardMetric,
+        window: Option<(u64, u64)>,
+        limit: Option<u32>,
+    ) -> Vec<(String, u64, i128)> {
+        try_leaderboard(deps, metric, window, limit).unwrap()
+    }
 
-        // beneficiary can release it
-        let unauth_info = mock_info("anyone", &coins(2, "token"));
-        let msg = ExecuteMsg::Reset { count: 5 };
-        let res = execute(deps.as_mut(), mock_env(), unauth_info, msg);
+    #[test]
+    fn stats_and_leaderboards_follow_every_trade() {
+        let mut deps = setup();
+        let day = 86_400;
+        // day one everyone opens 10 base, day two alice and bob close it
+        trade(&mut deps, 100, "alice", PositionDirection::Long, 100);
+        trade(&mut deps, 200, "bob", PositionDirection::Long, 300);
+        trade(&mut deps, 300, "carol", PositionDirection::Long, 200);
+        trade(&mut deps, day + 100, "alice", PositionDirection::Short, 150);
+        trade(&mut deps, day + 200, "bob", PositionDirection::Short, 200);
+        record(
+            &mut deps,
+            CLEARING_HOUSE,
+            ExecuteMsg::RecordFundingPayment {
+                f: FundingPaymentRecord {
+                    ts: day + 300,
+                    user: Addr::unchecked("alice"),
+                    market_index: 1,
+                    funding_payment: Number128::new(-7),
+                    base_asset_amount: Number128::zero(),
+                    user_last_cumulative_funding: Number128::zero(),
+                    user_last_funding_rate_ts: 0,
+                    amm_cumulative_funding_long: Number128::zero(),
+                    amm_cumulative_funding_short: Number128::zero(),
+                    writer: None,
+                },
+            },
+        );
+        record(
+            &mut deps,
+            CLEARING_HOUSE,
+            ExecuteMsg::RecordLiquidation {
+                l: LiquidationRecord {
+                    ts: day + 400,
+                    user: Addr::unchecked("alice"),
+                    partial: false,
+                    base_asset_value: Uint128::zero(),
+                    base_asset_value_closed: Uint128::zero(),
+                    liquidation_fee: Uint128::zero(),
+                    fee_to_liquidator: 0,
+                    fee_to_insurance_fund: 0,
+                    liquidator: Addr::unchecked("keeper"),
+                    total_collateral: Uint128::zero(),
+                    collateral: Uint128::zero(),
+                    unrealized_pnl: Number128::zero(),
+                    margin_ratio: Uint128::zero(),
+                    writer: None,
+                },
+            },
+        );
+
+        let msg = QueryMsg::GetUserStats {
+            user_address: "alice".to_string(),
+        };
+        let stats: UserStatsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(stats.volume, Uint128::from(250u64));
+        assert_eq!(stats.fees_paid, Uint128::from(2u64));
+        assert_eq!(stats.realized_pnl.i128(), 50);
+        assert_eq!(stats.funding_paid.i128(), 7);
+        assert_eq!(stats.trade_count, 2);
+        assert_eq!(stats.liquidation_count, 1);
+        assert_eq!(stats.markets.len(), 1);
+        assert_eq!(stats.markets[0].market_index, 1);
+        assert_eq!(stats.markets[0].realized_pnl.i128(), 50);
+
+        // every user is ranked once, on their latest totals
+        let all_time = leaderboard(&deps, LeaderboardMetric::Volume, None, None);
+        assert_eq!(
+            all_time,
+            vec![
+                ("bob".to_string(), 500, -100),
+                ("alice".to_string(), 250, 50),
+                ("carol".to_string(), 200, 0),
+            ]
+        );
+        let pnl = leaderboard(&deps, LeaderboardMetric::RealizedPnl, None, Some(2));
+        let users = pnl.iter().map(|e| e.0.as_str()).collect::<Vec<&str>>();
+        assert_eq!(users, vec!["alice", "carol"]);
+
+        let day_one = leaderboard(&deps, LeaderboardMetric::Volume, Some((500, 500)), None);
+        let users = day_one.iter().map(|e| e.0.as_str()).collect::<Vec<&str>>();
+        assert_eq!(users, vec!["bob", "carol", "alice"]);
+        let day_two = leaderboard(
+            &deps,
+            LeaderboardMetric::RealizedPnl,
+            Some((day + 1, day + 1)),
+            None,
+        );
+        assert_eq!(
+            day_two,
+            vec![
+                ("alice".to_string(), 150, 50),
+                ("bob".to_string(), 200, -100)
+            ]
+        );
+        let day_three = Some((2 * day, 2 * day));
+        assert!(leaderboard(&deps, LeaderboardMetric::Volume, day_three, None).is_empty());
+
+        // a window of several days sums the daily stats of each user
+        let both_days = leaderboard(&deps, LeaderboardMetric::Volume, Some((0, day)), None);
+        assert_eq!(both_days, all_time);
+        let pnl = leaderboard(
+            &deps,
+            LeaderboardMetric::RealizedPnl,
+            Some((500, 3 * day)),
+            Some(2),
+        );
+        assert_eq!(
+            pnl,
+            vec![
+                ("alice".to_string(), 250, 50),
+                ("carol".to_string(), 200, 0)
+            ]
+        );
+        let backwards = try_leaderboard(&deps, LeaderboardMetric::Volume, Some((day, 0)), None);
+        assert!(matches!(
+            backwards,
+            Err(ContractError::InvalidLeaderboardWindow { max_days: 31 })
+        ));
+        let too_long = Some((0, 31 * day));
+        let res = try_leaderboard(&deps, LeaderboardMetric::Volume, too_long, None);
+        assert!(matches!(
+            res,
+            Err(ContractError::InvalidLeaderboardWindow { .. })
+        ));
+        assert_eq!(
+            leaderboard(&deps, LeaderboardMetric::Volume, Some((0, 30 * day)), None),
+            all_time
+        );
+    }
+
+    fn try_record(
+        deps: &mut TestDeps,
+        sender: &str,
+        msg: ExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+    }
+
+    fn update_writer(deps: &mut TestDeps, sender: &str, record_types: Vec<RecordType>) {
+        let msg = ExecuteMsg::AddWriter {
+            writer: "indexer".to_string(),
+            record_types,
+        };
+        try_record(deps, sender, msg).unwrap();
+    }
+
+    fn rejected_type(res: Result<Response, ContractError>) -> String {
         match res {
-            Err(ContractError::Unauthorized {}) => {}
-            _ => panic!("Must return unauthorized error"),
+            Err(ContractError::UnauthorizedWriter { record_type }) => record_type,
+            other => panic!("expected UnauthorizedWriter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn writers_may_only_record_their_allowed_types() {
+        let mut deps = setup();
+        let trade = || ExecuteMsg::RecordTrade {
+            t: trade_record(1, "alice", 1),
+        };
+        let deposit = || ExecuteMsg::RecordDeposit {
+            d: deposit_record(1, "alice", 10),
+        };
+
+        let res = try_record(&mut deps, "indexer", trade());
+        assert_eq!(rejected_type(res), "trade");
+        let msg = ExecuteMsg::AddWriter {
+            writer: "indexer".to_string(),
+            record_types: vec![RecordType::Deposit],
+        };
+        let res = try_record(&mut deps, "indexer", msg);
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+
+        update_writer(&mut deps, OWNER, vec![RecordType::Deposit]);
+        try_record(&mut deps, "indexer", deposit()).unwrap();
+        let res = try_record(&mut deps, "indexer", trade());
+        assert_eq!(rejected_type(res), "trade");
+
+        // adding the writer again replaces its record types
+        update_writer(&mut deps, OWNER, vec![RecordType::Trade]);
+        let res = try_record(&mut deps, "indexer", deposit());
+        assert_eq!(rejected_type(res), "deposit");
+        try_record(&mut deps, "indexer", trade()).unwrap();
+        // the clearing house needs no entry
+        try_record(&mut deps, CLEARING_HOUSE, trade()).unwrap();
+
+        let msg = trade_history_msg(None, None, None, None, OrderBy::Ascending);
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let trades: Vec<TradeHistoryResponse> = from_binary(&res).unwrap();
+        let writers = trades.into_iter().map(|t| t.writer).collect::<Vec<_>>();
+        assert_eq!(
+            writers,
+            vec![
+                Some("indexer".to_string()),
+                Some(CLEARING_HOUSE.to_string())
+            ]
+        );
+        let msg = QueryMsg::GetWriters {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let listed: Vec<WriterResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            listed,
+            vec![WriterResponse {
+                writer: "indexer".to_string(),
+                record_types: vec![RecordType::Trade],
+            }]
+        );
+
+        let remove = || ExecuteMsg::RemoveWriter {
+            writer: "indexer".to_string(),
+        };
+        try_record(&mut deps, OWNER, remove()).unwrap();
+        let res = try_record(&mut deps, "indexer", trade());
+        assert_eq!(rejected_type(res), "trade");
+        let res = try_record(&mut deps, OWNER, remove());
+        assert!(matches!(res, Err(ContractError::WriterNotFound {})));
+    }
+
+    #[test]
+    fn record_batch_records_every_entry_for_its_writer() {
+        let mut deps = setup();
+        let records = vec![
+            HistoryRecord::Trade(trade_record(10, "alice", 1)),
+            HistoryRecord::Deposit(deposit_record(10, "alice", 50)),
+            HistoryRecord::Trade(trade_record(11, "bob", 2)),
+            HistoryRecord::Curve(curve_record(11, 2)),
+        ];
+        let res = try_record(
+            &mut deps,
+            CLEARING_HOUSE,
+            ExecuteMsg::RecordBatch { records },
+        )
+        .unwrap();
+        assert_eq!(attr(&res, "records"), "4");
+
+        // each entry goes through the same path as its single record message
+        assert_eq!(all_trade_ids(&deps), vec![1, 2]);
+        let msg = QueryMsg::GetDepositHistory {
+            user_address: "alice".to_string(),
+            start_after: None,
+            limit: None,
+            from_ts: None,
+            to_ts: None,
+            order: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let deposits: Vec<DepositHistoryResponse> = from_binary(&res).unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].writer, Some(CLEARING_HOUSE.to_string()));
+        let msg = QueryMsg::GetUserStats {
+            user_address: "bob".to_string(),
+        };
+        let stats: UserStatsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(stats.trade_count, 1);
+        assert_eq!(stats.markets[0].market_index, 2);
+
+        // one entry the writer may not record fails the whole batch
+        update_writer(&mut deps, OWNER, vec![RecordType::Trade]);
+        let records = vec![
+            HistoryRecord::Trade(trade_record(12, "alice", 1)),
+            HistoryRecord::Deposit(deposit_record(12, "alice", 50)),
+        ];
+        let res = try_record(&mut deps, "indexer", ExecuteMsg::RecordBatch { records });
+        assert_eq!(rejected_type(res), "deposit");
+    }
+
+    #[test]
+    fn records_older_than_the_last_of_their_type_are_rejected() {
+        let mut deps = setup();
+        update_writer(
+            &mut deps,
+            OWNER,
+            vec![RecordType::Trade, RecordType::Deposit],
+        );
+        // the ts order is kept per type, records at the same ts are fine
+        let records = vec![
+            HistoryRecord::Trade(trade_record(10, "alice", 1)),
+            HistoryRecord::Trade(trade_record(10, "bob", 1)),
+            HistoryRecord::Deposit(deposit_record(5, "alice", 50)),
+        ];
+        try_record(&mut deps, "indexer", ExecuteMsg::RecordBatch { records }).unwrap();
+
+        let records = vec![
+            HistoryRecord::Trade(trade_record(11, "alice", 1)),
+            HistoryRecord::Trade(trade_record(9, "bob", 1)),
+        ];
+        let res = try_record(&mut deps, "indexer", ExecuteMsg::RecordBatch { records });
+        assert!(matches!(
+            res,
+            Err(ContractError::RecordOutOfOrder { record_type, ts: 9, last_ts: 11 })
+                if record_type == "trade"
+        ));
+        let res = try_record(
+            &mut deps,
+            CLEARING_HOUSE,
+            ExecuteMsg::RecordTrade {
+                t: trade_record(8, "carol", 1),
+            },
+        );
+        assert!(matches!(res, Err(ContractError::RecordOutOfOrder { .. })));
+    }
+
+    fn records_since(
+        deps: &TestDeps,
+        global_seq: u64,
+        limit: Option<u32>,
+    ) -> Vec<SequencedRecordResponse> {
+        let msg = QueryMsg::GetRecordsSince { global_seq, limit };
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn records_since_lists_every_type_in_write_order() {
+        let mut deps = setup();
+        record_trade(&mut deps, trade_record(1, "alice", 1));
+        let d = deposit_record(2, "alice", 50);
+        record(&mut deps, CLEARING_HOUSE, ExecuteMsg::RecordDeposit { d });
+        let c = curve_record(3, 1);
+        record(&mut deps, CLEARING_HOUSE, ExecuteMsg::RecordCurve { c });
+        record_trade(&mut deps, trade_record(4, "bob", 1));
+
+        let all = records_since(&deps, 0, None);
+        let entries = all
+            .iter()
+            .map(|r| (r.global_seq, r.record_type, r.id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                (1, RecordType::Trade, 1),
+                (2, RecordType::Deposit, 1),
+                (3, RecordType::Curve, 1),
+                (4, RecordType::Trade, 2),
+            ]
+        );
+        assert!(matches!(&all[1].record, Some(HistoryRecord::Deposit(d)) if d.amount == 50));
+
+        let page = records_since(&deps, 2, Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(
+            (page[0].global_seq, page[0].record_type),
+            (3, RecordType::Curve)
+        );
+        assert!(records_since(&deps, 4, None).is_empty());
+
+        // pruning drops the log entry together with the record
+        let msg = ExecuteMsg::UpdateRetention {
+            record_type: RecordType::Trade,
+            max_count: Some(1),
+            max_age: None,
+        };
+        record(&mut deps, OWNER, msg);
+        prune(&mut deps, None).unwrap();
+        let all = records_since(&deps, 0, None);
+        let seqs = all.iter().map(|r| r.global_seq).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![2, 3, 4]);
+        assert!(all.iter().all(|r| r.record.is_some()));
+        assert!(matches!(&all[2].record, Some(HistoryRecord::Trade(t)) if t.ts == 4));
+    }
+
+    fn deposit_users(deps: &TestDeps, user: &str) -> Result<Vec<String>, ContractError> {
+        let msg = QueryMsg::GetDepositHistory {
+            user_address: user.to_string(),
+            start_after: None,
+            limit: None,
+            from_ts: None,
+            to_ts: None,
+            order: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg)?;
+        let deposits: Vec<DepositHistoryResponse> = from_binary(&res).unwrap();
+        Ok(deposits.into_iter().map(|d| d.user.to_string()).collect())
+    }
+
+    #[test]
+    fn subaccounts_query_their_own_history() {
+        let mut deps = setup();
+        for user in ["alice", "alice/2"] {
+            let d = deposit_record(1, user, 50);
+            record(&mut deps, CLEARING_HOUSE, ExecuteMsg::RecordDeposit { d });
         }
+        trade(&mut deps, 2, "alice/2", PositionDirection::Long, 100);
 
-        // only the original creator can reset the counter
-        let auth_info = mock_info("creator", &coins(2, "token"));
-        let msg = ExecuteMsg::Reset { count: 5 };
-        let _res = execute(deps.as_mut(), mock_env(), auth_info, msg).unwrap();
+        // the subaccount is keyed `<wallet>/<n>` and lists only its own records
+        assert_eq!(deposit_users(&deps, "alice/2").unwrap(), vec!["alice/2"]);
+        assert_eq!(deposit_users(&deps, "alice").unwrap(), vec!["alice"]);
+        let msg = QueryMsg::GetUserStats {
+            user_address: "alice/2".to_string(),
+        };
+        let stats: UserStatsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(stats.trade_count, 1);
 
-        // should now be 5
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(5, value.count);
+        // only the canonical form of a non-zero subaccount of a lowercase wallet is accepted
+        for account in ["alice/0", "alice/02", "alice/x", "alice/", "Alice/2"] {
+            assert!(deposit_users(&deps, account).is_err());
+        }
     }
 }
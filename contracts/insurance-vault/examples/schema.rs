@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 use cosmwasm_std::BalanceResponse;
-use insurance_vault::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, ConfigResponse};
+use insurance_vault::msg::{InstantiateMsg, ExecuteMsg, MigrateMsg, QueryMsg, ConfigResponse, ClearingHouseResponse, ReconciliationResponse, WindowUsageResponse, HeldWithdrawalResponse, PayoutResponse, PayoutTotalResponse};
 use insurance_vault::state::State;
 
 fn main() {
@@ -14,8 +14,15 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(BalanceResponse), &out_dir);
+    export_schema(&schema_for!(ClearingHouseResponse), &out_dir);
+    export_schema(&schema_for!(ReconciliationResponse), &out_dir);
+    export_schema(&schema_for!(WindowUsageResponse), &out_dir);
+    export_schema(&schema_for!(HeldWithdrawalResponse), &out_dir);
+    export_schema(&schema_for!(PayoutResponse), &out_dir);
+    export_schema(&schema_for!(PayoutTotalResponse), &out_dir);
 }
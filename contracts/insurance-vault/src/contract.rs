@@ -3,22 +3,36 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, to_binary, Addr, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
-    Uint128,
+    coins, to_binary, Addr, BankMsg, Binary, Deps, DepsMut, Env, Event, MessageInfo, Order,
+    Response, StdResult, Storage, Uint128,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::{Bound, PrimaryKey, U64Key};
 
 use crate::error::ContractError;
-use crate::msg::{BalanceResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{State, STATE};
+use crate::msg::{
+    BalanceResponse, ClearingHouseResponse, ConfigResponse, ExecuteMsg, HeldWithdrawalResponse,
+    InstantiateMsg, MigrateMsg, PayoutResponse, PayoutTotalResponse, QueryMsg,
+    ReconciliationResponse, WindowUsageResponse,
+};
+use crate::state::{
+    ClearingHouseLedger, FundTarget, HeldWithdrawal, OutflowWindow, Pause, PayoutReason,
+    PayoutRecord, RateLimit, State, ADMIN, CLEARING_HOUSES, FUND_TARGET, HELD_WITHDRAWALS,
+    HELD_WITHDRAWALS_V020, OUTFLOW_WINDOW, PAUSE, PAYOUTS, PAYOUT_COUNT, PAYOUT_TOTALS,
+    PENDING_ADMIN, PENDING_CLEARING_HOUSE, RATE_LIMIT, STATE, STATE_V010, WINDOW_SECONDS,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:insurance-funds";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// iterator limits
+const MAX_LIMIT: u32 = 20;
+const DEFAULT_LIMIT: u32 = 10;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
@@ -26,14 +40,13 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let state = State {
-        admin: info.sender.clone(),
         total_deposit: Uint128::zero(),
         clearing_house: Addr::unchecked(""),
         denom_stable: msg.denom_stable,
     };
 
     STATE.save(deps.storage, &state)?;
-    // ADMIN.set(deps.branch(), Some(info.sender.clone()))?;
+    ADMIN.set(deps.branch(), Some(info.sender.clone()))?;
     Ok(Response::new()
         .add_attribute("method", "instantiate")
         .add_attribute("clearing_house", info.sender.clone())
@@ -43,66 +56,293 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateAdmin { new_admin } => change_admin(deps, info, new_admin),
-        ExecuteMsg::UpdateClearingHouse { new_clearing_house } => {
-            change_clearing_house(deps, info, new_clearing_house)
+        ExecuteMsg::ProposeAdmin { new_admin } => propose_admin(deps, info, new_admin),
+        ExecuteMsg::AcceptAdmin {} => accept_admin(deps, info),
+        ExecuteMsg::ProposeClearingHouse { new_clearing_house } => {
+            propose_clearing_house(deps, info, new_clearing_house)
+        }
+        ExecuteMsg::AcceptClearingHouse {} => accept_clearing_house(deps, info),
+        ExecuteMsg::AuthorizeClearingHouse {
+            clearing_house,
+            deposit_cap,
+        } => authorize_clearing_house(deps, info, clearing_house, deposit_cap),
+        ExecuteMsg::RevokeClearingHouse { clearing_house } => {
+            revoke_clearing_house(deps, info, clearing_house)
         }
         ExecuteMsg::Deposit {} => deposit(deps, info),
-        ExecuteMsg::Withdraw { to_address, amount } => withdraw(deps, info, to_address, amount),
+        ExecuteMsg::Donate {} => donate(deps, info),
+        ExecuteMsg::Withdraw {
+            to_address,
+            amount,
+            reason,
+            market_index,
+        } => withdraw(
+            deps,
+            env,
+            info,
+            to_address,
+            amount,
+            reason.unwrap_or(PayoutReason::Unspecified),
+            market_index,
+        ),
+        ExecuteMsg::UpdateRateLimit {
+            max_per_window,
+            max_per_withdrawal,
+        } => update_rate_limit(deps, info, max_per_window, max_per_withdrawal),
+        ExecuteMsg::ReleaseHeldWithdrawal { id } => release_held_withdrawal(deps, env, info, id),
+        ExecuteMsg::UpdateGuardian { guardian } => update_guardian(deps, info, guardian),
+        ExecuteMsg::Pause { deposit, withdraw } => pause(deps, info, deposit, withdraw),
+        ExecuteMsg::Unpause { deposit, withdraw } => unpause(deps, info, deposit, withdraw),
+        ExecuteMsg::UpdateFundTarget { target, treasury } => {
+            update_fund_target(deps, info, target, treasury)
+        }
+        ExecuteMsg::Sweep {} => sweep(deps, env),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    // every step moves the stored structs one version forward
+    let mut version = stored.version.clone();
+    if version == "0.1.0" {
+        migrate_from_0_1_0(deps.branch())?;
+        version = "0.2.0".to_string();
+    }
+    if version == "0.2.0" {
+        migrate_from_0_2_0(deps.storage)?;
+        version = "0.3.0".to_string();
+    }
+    if version != CONTRACT_VERSION {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+// 0.2.0 moves the admin out of State into ADMIN and adds the rate limit, outflow window and pause items
+fn migrate_from_0_1_0(mut deps: DepsMut) -> Result<(), ContractError> {
+    let legacy = STATE_V010.load(deps.storage)?;
+    STATE.save(
+        deps.storage,
+        &State {
+            clearing_house: legacy.clearing_house,
+            total_deposit: legacy.total_deposit,
+            denom_stable: legacy.denom_stable,
+        },
+    )?;
+    ADMIN.set(deps.branch(), Some(legacy.admin))?;
+    RATE_LIMIT.save(deps.storage, &RateLimit::default())?;
+    OUTFLOW_WINDOW.save(deps.storage, &OutflowWindow::default())?;
+    PAUSE.save(deps.storage, &Pause::default())?;
+    Ok(())
+}
+
+// 0.3.0 moves the deposits into a sub-ledger of the primary clearing house and records the
+// clearing house and payout reason of every held withdrawal, older ones have no known reason
+fn migrate_from_0_2_0(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let state = STATE.load(storage)?;
+    if !state.clearing_house.as_str().is_empty() {
+        CLEARING_HOUSES.save(
+            storage,
+            &state.clearing_house,
+            &ClearingHouseLedger {
+                balance: state.total_deposit,
+                deposit_cap: Uint128::zero(),
+            },
+        )?;
     }
+    let held_withdrawals = HELD_WITHDRAWALS_V020
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (k, held) in held_withdrawals {
+        HELD_WITHDRAWALS.save(
+            storage,
+            U64Key::from(k),
+            &HeldWithdrawal {
+                clearing_house: state.clearing_house.clone(),
+                to_address: held.to_address,
+                amount: held.amount,
+                ts: held.ts,
+                reason: PayoutReason::Unspecified,
+                market_index: None,
+            },
+        )?;
+    }
+    Ok(())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetConfig {} => to_binary(&query_config(deps)?),
         QueryMsg::GetBalance {} => to_binary(&query_balance(deps)?),
+        QueryMsg::GetClearingHouseBalance { clearing_house } => {
+            to_binary(&query_clearing_house_balance(deps, clearing_house)?)
+        }
+        QueryMsg::GetClearingHouses { start_after, limit } => {
+            to_binary(&query_clearing_houses(deps, start_after, limit)?)
+        }
+        QueryMsg::GetReconciliation {} => to_binary(&query_reconciliation(deps, env)?),
+        QueryMsg::GetWindowUsage {} => to_binary(&query_window_usage(deps, env)?),
+        QueryMsg::GetHeldWithdrawals { start_after, limit } => {
+            to_binary(&query_held_withdrawals(deps, start_after, limit)?)
+        }
+        QueryMsg::GetPayouts { start_after, limit } => {
+            to_binary(&query_payouts(deps, start_after, limit)?)
+        }
+        QueryMsg::GetPayoutTotals {} => to_binary(&query_payout_totals(deps)?),
     }
 }
 
-pub fn change_admin(
+pub fn propose_admin(
     deps: DepsMut,
     info: MessageInfo,
     new_admin: String,
 ) -> Result<Response, ContractError> {
-    let api = deps.api;
-    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
-        if state.admin != info.sender {
-            return Err(ContractError::UnauthorizedAdmin {});
-        }
-        state.admin = api.addr_validate(&new_admin)?;
-        Ok(state)
-    })?;
-    Ok(Response::new().add_attribute("method", "change_clearing_house"))
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let new_admin = deps.api.addr_validate(&new_admin)?;
+    PENDING_ADMIN.save(deps.storage, &new_admin)?;
+    Ok(Response::new()
+        .add_attribute("method", "propose_admin")
+        .add_attribute("pending_admin", new_admin))
+}
+
+pub fn accept_admin(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending_admin = PENDING_ADMIN.may_load(deps.storage)?;
+    if pending_admin.as_ref() != Some(&info.sender) {
+        return Err(ContractError::UnauthorizedPending {});
+    }
+    PENDING_ADMIN.remove(deps.storage);
+    ADMIN.set(deps, pending_admin)?;
+    Ok(Response::new()
+        .add_attribute("method", "accept_admin")
+        .add_attribute("admin", info.sender))
 }
 
-pub fn change_clearing_house(
+pub fn propose_clearing_house(
     deps: DepsMut,
     info: MessageInfo,
-    clearing_house: Addr,
+    new_clearing_house: String,
 ) -> Result<Response, ContractError> {
-    // ADMIN.assert_admin(deps.as_ref(), &info.sender.clone())?;
-    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
-        if state.admin != info.sender {
-            return Err(ContractError::UnauthorizedAdmin {});
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let new_clearing_house = deps.api.addr_validate(&new_clearing_house)?;
+    PENDING_CLEARING_HOUSE.save(deps.storage, &new_clearing_house)?;
+    Ok(Response::new()
+        .add_attribute("method", "propose_clearing_house")
+        .add_attribute("pending_clearing_house", new_clearing_house))
+}
+
+pub fn accept_clearing_house(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending_clearing_house = PENDING_CLEARING_HOUSE.may_load(deps.storage)?;
+    if pending_clearing_house.as_ref() != Some(&info.sender) {
+        return Err(ContractError::UnauthorizedPending {});
+    }
+    PENDING_CLEARING_HOUSE.remove(deps.storage);
+    let mut state = STATE.load(deps.storage)?;
+
+    // the new clearing house takes over the sub-ledger of the one it replaces
+    let mut ledger = CLEARING_HOUSES
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    if state.clearing_house != info.sender {
+        if let Some(previous) = CLEARING_HOUSES.may_load(deps.storage, &state.clearing_house)? {
+            ledger.balance = ledger.balance.checked_add(previous.balance)?;
+            CLEARING_HOUSES.remove(deps.storage, &state.clearing_house);
         }
-        state.clearing_house = clearing_house.clone();
-        Ok(state)
-    })?;
+    }
+    CLEARING_HOUSES.save(deps.storage, &info.sender, &ledger)?;
+    state.clearing_house = info.sender.clone();
+    STATE.save(deps.storage, &state)?;
+    Ok(Response::new()
+        .add_attribute("method", "accept_clearing_house")
+        .add_attribute("clearing_house", info.sender))
+}
+
+// a secondary clearing house can only withdraw what it deposited itself,
+// so unlike the primary one it is authorized without an accept step
+pub fn authorize_clearing_house(
+    deps: DepsMut,
+    info: MessageInfo,
+    clearing_house: String,
+    deposit_cap: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let clearing_house = deps.api.addr_validate(&clearing_house)?;
+    CLEARING_HOUSES.update(
+        deps.storage,
+        &clearing_house,
+        |ledger| -> Result<ClearingHouseLedger, ContractError> {
+            let mut ledger = ledger.unwrap_or_default();
+            ledger.deposit_cap = deposit_cap;
+            Ok(ledger)
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("method", "authorize_clearing_house")
+        .add_attribute("clearing_house", clearing_house)
+        .add_attribute("deposit_cap", deposit_cap))
+}
+
+pub fn revoke_clearing_house(
+    deps: DepsMut,
+    info: MessageInfo,
+    clearing_house: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let clearing_house = deps.api.addr_validate(&clearing_house)?;
+    let state = STATE.load(deps.storage)?;
+    if clearing_house == state.clearing_house {
+        return Err(ContractError::PrimaryClearingHouse {});
+    }
+    let ledger = CLEARING_HOUSES.load(deps.storage, &clearing_house)?;
+    if !ledger.balance.is_zero() {
+        return Err(ContractError::ClearingHouseNotEmpty {});
+    }
+    CLEARING_HOUSES.remove(deps.storage, &clearing_house);
     Ok(Response::new()
-        .add_attribute("method", "change_clearing_house")
-        .add_attribute("new_clearing_house", clearing_house.clone()))
+        .add_attribute("method", "revoke_clearing_house")
+        .add_attribute("clearing_house", clearing_house))
+}
+
+fn load_clearing_house(
+    storage: &dyn Storage,
+    clearing_house: &Addr,
+) -> Result<ClearingHouseLedger, ContractError> {
+    CLEARING_HOUSES
+        .may_load(storage, clearing_house)?
+        .ok_or(ContractError::UnauthorizedClearingHouse {})
 }
 
 pub fn deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
     let mut state: State = STATE.load(deps.storage)?;
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
+    let mut ledger = load_clearing_house(deps.storage, &info.sender)?;
+
+    if PAUSE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .deposit_paused
+    {
+        return Err(ContractError::DepositPaused {});
     }
 
     if info.funds.len() != 1 {
@@ -113,6 +353,12 @@ pub fn deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractErr
         return Err(ContractError::InvalidIncomingAsset {});
     }
 
+    ledger.balance = ledger.balance.checked_add(info.funds[0].amount)?;
+    if !ledger.deposit_cap.is_zero() && ledger.balance > ledger.deposit_cap {
+        return Err(ContractError::DepositCapExceeded {});
+    }
+    CLEARING_HOUSES.save(deps.storage, &info.sender, &ledger)?;
+
     state.total_deposit = state.total_deposit.checked_add(info.funds[0].amount)?;
     STATE.update(deps.storage, |_state| -> Result<_, ContractError> {
         Ok(state)
@@ -122,47 +368,371 @@ pub fn deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractErr
         .add_attribute("amount", info.funds[0].amount))
 }
 
+// anyone can top up the fund of the primary clearing house,
+// the collateral vault sends its strategy yield this way
+pub fn donate(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state: State = STATE.load(deps.storage)?;
+    let mut ledger = load_clearing_house(deps.storage, &state.clearing_house)?;
+
+    if PAUSE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .deposit_paused
+    {
+        return Err(ContractError::DepositPaused {});
+    }
+
+    if info.funds.len() != 1 || info.funds[0].denom != state.denom_stable {
+        return Err(ContractError::InvalidIncomingAsset {});
+    }
+
+    ledger.balance = ledger.balance.checked_add(info.funds[0].amount)?;
+    CLEARING_HOUSES.save(deps.storage, &state.clearing_house, &ledger)?;
+
+    state.total_deposit = state.total_deposit.checked_add(info.funds[0].amount)?;
+    STATE.save(deps.storage, &state)?;
+    Ok(Response::new()
+        .add_attribute("method", "donate_insurance_fund")
+        .add_attribute("donor", info.sender)
+        .add_attribute("amount", info.funds[0].amount))
+}
+
 pub fn withdraw(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     to: String,
     amount: Uint128,
+    reason: PayoutReason,
+    market_index: Option<u64>,
 ) -> Result<Response, ContractError> {
     let mut state: State = STATE.load(deps.storage)?;
     let amount = Uint128::from(amount);
+    let now = env.block.time.seconds();
+    let mut ledger = load_clearing_house(deps.storage, &info.sender)?;
 
-    if info.sender != state.clearing_house {
-        return Err(ContractError::UnauthorizedClearingHouse {});
+    if PAUSE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .withdraw_paused
+    {
+        return Err(ContractError::WithdrawPaused {});
     }
 
-    if amount.gt(&state.total_deposit) {
+    // a clearing house can only withdraw from its own sub-ledger
+    if amount.gt(&ledger.balance) {
         return Err(ContractError::InsufficientFunds {});
     };
+    ledger.balance = ledger.balance.checked_sub(amount)?;
+    CLEARING_HOUSES.save(deps.storage, &info.sender, &ledger)?;
 
     state.total_deposit = state.total_deposit.checked_sub(amount)?;
 
-    let send_tx_msg = BankMsg::Send {
-        to_address: to,
-        amount: coins(amount.u128(), state.denom_stable),
-    };
+    // whatever goes over the per withdrawal or the rolling window cap is held for the admin to release
+    let mut rate_limit = RATE_LIMIT.may_load(deps.storage)?.unwrap_or_default();
+    let mut window = OUTFLOW_WINDOW.may_load(deps.storage)?.unwrap_or_default();
+    window.prune(now);
 
-    STATE.update(deps.storage, |state| -> Result<_, ContractError> {
+    let mut amount_sent = amount;
+    if !rate_limit.max_per_withdrawal.is_zero() {
+        amount_sent = amount_sent.min(rate_limit.max_per_withdrawal);
+    }
+    if !rate_limit.max_per_window.is_zero() {
+        amount_sent = amount_sent.min(rate_limit.max_per_window.saturating_sub(window.used()));
+    }
+    let amount_held = amount.checked_sub(amount_sent)?;
+
+    let mut res = Response::new();
+    if !amount_sent.is_zero() {
+        window.record(now, amount_sent);
+        let payout_id = record_payout(
+            deps.storage,
+            PayoutRecord {
+                ts: now,
+                clearing_house: info.sender.clone(),
+                to_address: to.clone(),
+                amount: amount_sent,
+                reason: reason.clone(),
+                market_index,
+            },
+        )?;
+        res = res
+            .add_message(BankMsg::Send {
+                to_address: to.clone(),
+                amount: coins(amount_sent.u128(), state.denom_stable.clone()),
+            })
+            .add_attribute("payout_id", payout_id.to_string());
+    }
+    if !amount_held.is_zero() {
+        let id = rate_limit.next_held_id;
+        HELD_WITHDRAWALS.save(
+            deps.storage,
+            U64Key::new(id),
+            &HeldWithdrawal {
+                clearing_house: info.sender.clone(),
+                to_address: to,
+                amount: amount_held,
+                ts: now,
+                reason: reason.clone(),
+                market_index,
+            },
+        )?;
+        rate_limit.next_held_id = id + 1;
+        rate_limit.total_held = rate_limit.total_held.checked_add(amount_held)?;
+        res = res
+            .add_attribute("held_id", id.to_string())
+            .add_attribute("held_amount", amount_held);
+    }
+
+    STATE.update(deps.storage, |_s| -> Result<State, ContractError> {
         Ok(state)
     })?;
+    RATE_LIMIT.save(deps.storage, &rate_limit)?;
+    OUTFLOW_WINDOW.save(deps.storage, &window)?;
 
-    Ok(Response::new()
-        .add_message(send_tx_msg)
+    Ok(res
         .add_attribute("method", "withdraw_insurance_fund")
-        .add_attribute("amount", amount))
+        .add_attribute("reason", reason.code())
+        .add_attribute("amount", amount_sent))
+}
+
+pub fn update_rate_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_per_window: Uint128,
+    max_per_withdrawal: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut rate_limit = RATE_LIMIT.may_load(deps.storage)?.unwrap_or_default();
+    rate_limit.max_per_window = max_per_window;
+    rate_limit.max_per_withdrawal = max_per_withdrawal;
+    RATE_LIMIT.save(deps.storage, &rate_limit)?;
+    Ok(Response::new()
+        .add_attribute("method", "update_rate_limit")
+        .add_attribute("max_per_window", max_per_window)
+        .add_attribute("max_per_withdrawal", max_per_withdrawal))
+}
+
+// held withdrawals are released as is and do not count towards the window, the payout keeps
+// the reason of the withdrawal
+pub fn release_held_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let state = STATE.load(deps.storage)?;
+    let held = HELD_WITHDRAWALS.load(deps.storage, U64Key::new(id))?;
+    HELD_WITHDRAWALS.remove(deps.storage, U64Key::new(id));
+    RATE_LIMIT.update(
+        deps.storage,
+        |mut rate_limit| -> Result<RateLimit, ContractError> {
+            rate_limit.total_held = rate_limit.total_held.checked_sub(held.amount)?;
+            Ok(rate_limit)
+        },
+    )?;
+    let payout_id = record_payout(
+        deps.storage,
+        PayoutRecord {
+            ts: env.block.time.seconds(),
+            clearing_house: held.clearing_house,
+            to_address: held.to_address.clone(),
+            amount: held.amount,
+            reason: held.reason,
+            market_index: held.market_index,
+        },
+    )?;
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: held.to_address.clone(),
+            amount: coins(held.amount.u128(), state.denom_stable),
+        })
+        .add_attribute("method", "release_held_withdrawal")
+        .add_attribute("payout_id", payout_id.to_string())
+        .add_attribute("to_address", held.to_address)
+        .add_attribute("amount", held.amount))
+}
+
+pub fn update_guardian(
+    deps: DepsMut,
+    info: MessageInfo,
+    guardian: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let guardian = deps.api.addr_validate(&guardian)?;
+    let mut pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    pause.guardian = Some(guardian.clone());
+    PAUSE.save(deps.storage, &pause)?;
+    Ok(Response::new()
+        .add_attribute("method", "update_guardian")
+        .add_attribute("guardian", guardian))
+}
+
+// the guardian (or the admin) can only freeze, lifting a pause is left to the admin
+pub fn pause(
+    deps: DepsMut,
+    info: MessageInfo,
+    deposit: bool,
+    withdraw: bool,
+) -> Result<Response, ContractError> {
+    let mut pause: Pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    if pause.guardian.as_ref() != Some(&info.sender)
+        && !ADMIN.is_admin(deps.as_ref(), &info.sender)?
+    {
+        return Err(ContractError::UnauthorizedGuardian {});
+    }
+    pause.deposit_paused = pause.deposit_paused || deposit;
+    pause.withdraw_paused = pause.withdraw_paused || withdraw;
+    PAUSE.save(deps.storage, &pause)?;
+    Ok(Response::new()
+        .add_attribute("method", "pause")
+        .add_attribute("deposit_paused", pause.deposit_paused.to_string())
+        .add_attribute("withdraw_paused", pause.withdraw_paused.to_string()))
+}
+
+pub fn unpause(
+    deps: DepsMut,
+    info: MessageInfo,
+    deposit: bool,
+    withdraw: bool,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut pause: Pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    pause.deposit_paused = pause.deposit_paused && !deposit;
+    pause.withdraw_paused = pause.withdraw_paused && !withdraw;
+    PAUSE.save(deps.storage, &pause)?;
+    Ok(Response::new()
+        .add_attribute("method", "unpause")
+        .add_attribute("deposit_paused", pause.deposit_paused.to_string())
+        .add_attribute("withdraw_paused", pause.withdraw_paused.to_string()))
+}
+
+pub fn update_fund_target(
+    deps: DepsMut,
+    info: MessageInfo,
+    target: Uint128,
+    treasury: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let treasury = deps.api.addr_validate(&treasury)?;
+    FUND_TARGET.save(
+        deps.storage,
+        &FundTarget {
+            target,
+            treasury: Some(treasury.clone()),
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("method", "update_fund_target")
+        .add_attribute("target", target)
+        .add_attribute("treasury", treasury))
+}
+
+// what the fund of the primary clearing house holds above the target
+fn fund_excess(
+    storage: &dyn Storage,
+    state: &State,
+    fund_target: &FundTarget,
+) -> StdResult<Uint128> {
+    if fund_target.target.is_zero() {
+        return Ok(Uint128::zero());
+    }
+    let ledger = CLEARING_HOUSES
+        .may_load(storage, &state.clearing_house)?
+        .unwrap_or_default();
+    Ok(ledger.balance.saturating_sub(fund_target.target))
+}
+
+// sends the excess above the target to the treasury, anyone can call it
+pub fn sweep(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let mut state = STATE.load(deps.storage)?;
+    let fund_target = FUND_TARGET.may_load(deps.storage)?.unwrap_or_default();
+    let treasury = fund_target
+        .treasury
+        .clone()
+        .ok_or(ContractError::NoTreasury {})?;
+
+    if PAUSE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .withdraw_paused
+    {
+        return Err(ContractError::WithdrawPaused {});
+    }
+
+    let excess = fund_excess(deps.storage, &state, &fund_target)?;
+    let mut res = Response::new();
+    if !excess.is_zero() {
+        let mut ledger = load_clearing_house(deps.storage, &state.clearing_house)?;
+        ledger.balance = ledger.balance.checked_sub(excess)?;
+        CLEARING_HOUSES.save(deps.storage, &state.clearing_house, &ledger)?;
+        state.total_deposit = state.total_deposit.checked_sub(excess)?;
+        STATE.save(deps.storage, &state)?;
+        let payout_id = record_payout(
+            deps.storage,
+            PayoutRecord {
+                ts: env.block.time.seconds(),
+                clearing_house: state.clearing_house.clone(),
+                to_address: treasury.to_string(),
+                amount: excess,
+                reason: PayoutReason::Sweep,
+                market_index: None,
+            },
+        )?;
+        res = res
+            .add_attribute("payout_id", payout_id.to_string())
+            .add_message(BankMsg::Send {
+                to_address: treasury.to_string(),
+                amount: coins(excess.u128(), state.denom_stable),
+            })
+            .add_event(
+                Event::new("insurance_fund_sweep")
+                    .add_attribute("treasury", treasury)
+                    .add_attribute("target", fund_target.target)
+                    .add_attribute("amount", excess),
+            );
+    }
+    Ok(res
+        .add_attribute("method", "sweep")
+        .add_attribute("amount", excess))
+}
+
+fn record_payout(storage: &mut dyn Storage, record: PayoutRecord) -> Result<u64, ContractError> {
+    let id = PAYOUT_COUNT.may_load(storage)?.unwrap_or_default();
+    PAYOUT_TOTALS.update(
+        storage,
+        record.reason.code(),
+        |total| -> Result<_, ContractError> {
+            let mut total = total.unwrap_or_default();
+            total.amount = total.amount.checked_add(record.amount)?;
+            total.count += 1;
+            Ok(total)
+        },
+    )?;
+    PAYOUTS.save(storage, U64Key::new(id), &record)?;
+    PAYOUT_COUNT.save(storage, &(id + 1))?;
+    Ok(id)
 }
 
 fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let state = STATE.load(deps.storage)?;
-    // let res = ADMIN.query_admin(deps).unwrap();
+    let pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    let fund_target = FUND_TARGET.may_load(deps.storage)?.unwrap_or_default();
+    let excess = fund_excess(deps.storage, &state, &fund_target)?;
+    let res = ADMIN.query_admin(deps)?;
     Ok(ConfigResponse {
         clearing_house: state.clearing_house,
-        admin: state.admin.to_string(),
+        admin: res.admin.unwrap(),
+        pending_admin: PENDING_ADMIN.may_load(deps.storage)?,
+        pending_clearing_house: PENDING_CLEARING_HOUSE.may_load(deps.storage)?,
         denom: state.denom_stable,
+        guardian: pause.guardian,
+        deposit_paused: pause.deposit_paused,
+        withdraw_paused: pause.withdraw_paused,
+        fund_target: fund_target.target,
+        treasury: fund_target.treasury,
+        excess,
     })
 }
 
@@ -172,3 +742,158 @@ fn query_balance(deps: Deps) -> StdResult<BalanceResponse> {
         balance: state.total_deposit,
     })
 }
+
+fn query_clearing_house_balance(deps: Deps, clearing_house: String) -> StdResult<BalanceResponse> {
+    let clearing_house = deps.api.addr_validate(&clearing_house)?;
+    let ledger = CLEARING_HOUSES
+        .may_load(deps.storage, &clearing_house)?
+        .unwrap_or_default();
+    Ok(BalanceResponse {
+        balance: ledger.balance,
+    })
+}
+
+fn query_clearing_houses(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ClearingHouseResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(start) => Some(Bound::exclusive(deps.api.addr_validate(&start)?.as_str())),
+        None => None,
+    };
+
+    CLEARING_HOUSES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, ledger) = item?;
+            Ok(ClearingHouseResponse {
+                clearing_house: Addr::unchecked(String::from_utf8(k)?),
+                balance: ledger.balance,
+                deposit_cap: ledger.deposit_cap,
+            })
+        })
+        .collect()
+}
+
+/// Compares the deposits tracked by the vault with the denom balance actually held by the contract.
+/// Anything sent to the vault outside of `Deposit` shows up as surplus and is never paid out.
+/// Withdrawals held by the rate limit are still in the contract and count as tracked.
+fn query_reconciliation(deps: Deps, env: Env) -> StdResult<ReconciliationResponse> {
+    let state = STATE.load(deps.storage)?;
+    let total_held = RATE_LIMIT
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .total_held;
+    let tracked = state.total_deposit.checked_add(total_held)?;
+    let bank_balance = deps
+        .querier
+        .query_balance(env.contract.address, state.denom_stable)?
+        .amount;
+    let (surplus, deficit) = if bank_balance >= tracked {
+        (bank_balance.checked_sub(tracked)?, Uint128::zero())
+    } else {
+        (Uint128::zero(), tracked.checked_sub(bank_balance)?)
+    };
+    Ok(ReconciliationResponse {
+        total_deposit: state.total_deposit,
+        total_held,
+        bank_balance,
+        surplus,
+        deficit,
+    })
+}
+
+fn query_window_usage(deps: Deps, env: Env) -> StdResult<WindowUsageResponse> {
+    let rate_limit = RATE_LIMIT.may_load(deps.storage)?.unwrap_or_default();
+    let mut window = OUTFLOW_WINDOW.may_load(deps.storage)?.unwrap_or_default();
+    window.prune(env.block.time.seconds());
+    let used = window.used();
+    Ok(WindowUsageResponse {
+        window_seconds: WINDOW_SECONDS,
+        max_per_window: rate_limit.max_per_window,
+        max_per_withdrawal: rate_limit.max_per_withdrawal,
+        used,
+        remaining: if rate_limit.max_per_window.is_zero() {
+            None
+        } else {
+            Some(rate_limit.max_per_window.saturating_sub(used))
+        },
+        total_held: rate_limit.total_held,
+    })
+}
+
+fn query_held_withdrawals(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<HeldWithdrawalResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|start| U64Key::new(start).joined_key())
+        .map(Bound::Exclusive);
+
+    HELD_WITHDRAWALS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, held) = item?;
+            let mut id = [0u8; 8];
+            id.copy_from_slice(&k);
+            Ok(HeldWithdrawalResponse {
+                id: u64::from_be_bytes(id),
+                to_address: held.to_address,
+                amount: held.amount,
+                ts: held.ts,
+                reason: held.reason,
+                market_index: held.market_index,
+            })
+        })
+        .collect()
+}
+
+fn query_payouts(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PayoutResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|start| U64Key::new(start).joined_key())
+        .map(Bound::Exclusive);
+
+    PAYOUTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, payout) = item?;
+            let mut id = [0u8; 8];
+            id.copy_from_slice(&k);
+            Ok(PayoutResponse {
+                id: u64::from_be_bytes(id),
+                ts: payout.ts,
+                clearing_house: payout.clearing_house,
+                to_address: payout.to_address,
+                amount: payout.amount,
+                reason: payout.reason,
+                market_index: payout.market_index,
+            })
+        })
+        .collect()
+}
+
+fn query_payout_totals(deps: Deps) -> StdResult<Vec<PayoutTotalResponse>> {
+    PAYOUT_TOTALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (k, total) = item?;
+            Ok(PayoutTotalResponse {
+                reason: String::from_utf8(k)?,
+                amount: total.amount,
+                count: total.count,
+            })
+        })
+        .collect()
+}
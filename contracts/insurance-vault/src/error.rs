@@ -1,13 +1,13 @@
 use cosmwasm_std::{OverflowError, StdError};
-// use cw_controllers::AdminError;
+use cw_controllers::AdminError;
 use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    // #[error("{0}")]
-    // Admin(#[from] AdminError),
+    #[error("{0}")]
+    Admin(#[from] AdminError),
 
     #[error("Admin only")]
     UnauthorizedAdmin {},
@@ -15,6 +15,33 @@ pub enum ContractError {
     #[error("Clearing house only")]
     UnauthorizedClearingHouse {},
 
+    #[error("Clearing house deposit cap exceeded")]
+    DepositCapExceeded {},
+
+    #[error("Clearing house still holds deposits")]
+    ClearingHouseNotEmpty {},
+
+    #[error("The primary clearing house can only be replaced")]
+    PrimaryClearingHouse {},
+
+    #[error("Proposed address only")]
+    UnauthorizedPending {},
+
+    #[error("Guardian only")]
+    UnauthorizedGuardian {},
+
+    #[error("Deposits are paused")]
+    DepositPaused {},
+
+    #[error("Withdrawals are paused")]
+    WithdrawPaused {},
+
+    #[error("No treasury configured")]
+    NoTreasury {},
+
+    #[error("Cannot migrate from {contract} {version}")]
+    InvalidMigration { contract: String, version: String },
+
     #[error("Math error")]
     MathError {},
 
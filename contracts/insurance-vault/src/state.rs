@@ -2,15 +2,163 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{Uint128, Addr};
-use cw_storage_plus::Item;
-// use cw_controllers::Admin;
+use cw_controllers::Admin;
+use cw_storage_plus::{Item, Map, U64Key};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
+    // primary clearing house, the one handed over through propose/accept
+    pub clearing_house: Addr,
+    // sum of the clearing house sub-ledgers
+    pub total_deposit: Uint128,
+    pub denom_stable: String
+}
+
+// sub-ledger of an authorized clearing house, a zero deposit_cap means no cap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ClearingHouseLedger {
+    pub balance: Uint128,
+    pub deposit_cap: Uint128,
+}
+
+// the fund of the primary clearing house is kept at `target`, anything above it can be
+// swept to the treasury, a zero target disables sweeping
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct FundTarget {
+    pub target: Uint128,
+    pub treasury: Option<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Pause {
+    pub guardian: Option<Addr>,
+    pub deposit_paused: bool,
+    pub withdraw_paused: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutReason {
+    // user withdrawal the collateral vault could not cover
+    UserWithdrawal,
+    // admin transfer into a market's fee pool
+    MarketTopUp,
+    // excess above the fund target sent to the treasury
+    Sweep,
+    Unspecified,
+}
+
+impl PayoutReason {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PayoutReason::UserWithdrawal => "user_withdrawal",
+            PayoutReason::MarketTopUp => "market_top_up",
+            PayoutReason::Sweep => "sweep",
+            PayoutReason::Unspecified => "unspecified",
+        }
+    }
+}
+
+// every amount paid out of the fund, kept for audits
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PayoutRecord {
+    pub ts: u64,
+    pub clearing_house: Addr,
+    pub to_address: String,
+    pub amount: Uint128,
+    pub reason: PayoutReason,
+    pub market_index: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct PayoutTotal {
+    pub amount: Uint128,
+    pub count: u64,
+}
+
+// zero means no cap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct RateLimit {
+    pub max_per_window: Uint128,
+    pub max_per_withdrawal: Uint128,
+    pub total_held: Uint128,
+    pub next_held_id: u64,
+}
+
+// outflow of the rolling window, bucketed per hour
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct OutflowWindow {
+    pub buckets: Vec<(u64, Uint128)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HeldWithdrawal {
+    // the sub-ledger the withdrawal was taken from
+    pub clearing_house: Addr,
+    pub to_address: String,
+    pub amount: Uint128,
+    pub ts: u64,
+    // recorded with the payout once the withdrawal is released
+    pub reason: PayoutReason,
+    pub market_index: Option<u64>,
+}
+
+pub const WINDOW_SECONDS: u64 = 86400;
+pub const BUCKET_SECONDS: u64 = 3600;
+
+// State as stored up to 0.1.0, the admin moved to ADMIN in 0.2.0
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateV010 {
     pub admin: Addr,
     pub clearing_house: Addr,
     pub total_deposit: Uint128,
     pub denom_stable: String
 }
 
+// HeldWithdrawal as stored up to 0.2.0, before the vault kept a sub-ledger per clearing house
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HeldWithdrawalV020 {
+    pub to_address: String,
+    pub amount: Uint128,
+    pub ts: u64,
+}
+
 pub const STATE: Item<State> = Item::new("state");
-// pub const ADMIN: Admin = Admin::new("admin");
\ No newline at end of file
+pub const STATE_V010: Item<StateV010> = Item::new("state");
+pub const ADMIN: Admin = Admin::new("admin");
+// proposed addresses, they take over once they accept
+pub const PENDING_ADMIN: Item<Addr> = Item::new("pending_admin");
+pub const PENDING_CLEARING_HOUSE: Item<Addr> = Item::new("pending_clearing_house");
+pub const CLEARING_HOUSES: Map<&Addr, ClearingHouseLedger> = Map::new("clearing_houses");
+pub const FUND_TARGET: Item<FundTarget> = Item::new("fund_target");
+pub const PAUSE: Item<Pause> = Item::new("pause");
+pub const RATE_LIMIT: Item<RateLimit> = Item::new("rate_limit");
+pub const OUTFLOW_WINDOW: Item<OutflowWindow> = Item::new("outflow_window");
+pub const PAYOUT_COUNT: Item<u64> = Item::new("payout_count");
+pub const PAYOUTS: Map<U64Key, PayoutRecord> = Map::new("payouts");
+// keyed by the reason code
+pub const PAYOUT_TOTALS: Map<&str, PayoutTotal> = Map::new("payout_totals");
+pub const HELD_WITHDRAWALS: Map<U64Key, HeldWithdrawal> = Map::new("held_withdrawals");
+pub const HELD_WITHDRAWALS_V020: Map<U64Key, HeldWithdrawalV020> = Map::new("held_withdrawals");
+
+impl OutflowWindow {
+    // drops the buckets that fell out of the window ending at `now`
+    pub fn prune(&mut self, now: u64) {
+        let window_start = now.saturating_sub(WINDOW_SECONDS);
+        self.buckets.retain(|(bucket_ts, _)| bucket_ts + BUCKET_SECONDS > window_start);
+    }
+
+    pub fn used(&self) -> Uint128 {
+        self.buckets
+            .iter()
+            .fold(Uint128::zero(), |acc, (_, amount)| acc + *amount)
+    }
+
+    pub fn record(&mut self, now: u64, amount: Uint128) {
+        let bucket_ts = now - now % BUCKET_SECONDS;
+        match self.buckets.last_mut() {
+            Some((ts, bucket_amount)) if *ts == bucket_ts => *bucket_amount += amount,
+            _ => self.buckets.push((bucket_ts, amount)),
+        }
+    }
+}
@@ -1,10 +1,19 @@
 #[cfg(test)]
 mod tests {
-    use crate::contract::{change_clearing_house, deposit, execute, instantiate, query, withdraw};
-    use crate::msg::{BalanceResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+    use crate::contract::{
+        accept_clearing_house, deposit, execute, instantiate, migrate, propose_clearing_house,
+        query, withdraw,
+    };
+    use crate::msg::{
+        BalanceResponse, ClearingHouseResponse, ConfigResponse, ExecuteMsg, InstantiateMsg,
+        MigrateMsg, PayoutResponse, PayoutTotalResponse, QueryMsg, ReconciliationResponse,
+        WindowUsageResponse,
+    };
+    use crate::state::{PayoutReason, StateV010, STATE_V010};
 
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary, Addr, Uint128};
+    use cosmwasm_std::{coins, from_binary, Addr, BankMsg, CosmosMsg, Uint128};
+    use cw2::{get_contract_version, set_contract_version};
 
     // initlization and verify data
     // #[test]
@@ -40,12 +49,13 @@ mod tests {
         // we can just call .unwrap() to assert this was a success
         instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        change_clearing_house(
+        propose_clearing_house(
             deps.as_mut(),
             mock_info("creator", &coins(0, "uusd")),
-            Addr::unchecked("newclearing"),
+            "newclearing".to_string(),
         )
         .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
         let value: BalanceResponse = from_binary(&res).unwrap();
         assert_eq!(Uint128::from(0u64), value.balance);
@@ -64,9 +74,12 @@ mod tests {
         let d_info = mock_info("newclearing", &coins(1000000, "uusd"));
         withdraw(
             deps.as_mut(),
+            mock_env(),
             d_info,
             "testaddr".to_string(),
             Uint128::from(1000000u64),
+            PayoutReason::UserWithdrawal,
+            None,
         )
         .unwrap();
 
@@ -92,25 +105,661 @@ mod tests {
         assert_eq!(Addr::unchecked(""), value.clearing_house);
         let dep_info = mock_info("creator", &coins(1000000, "uusd"));
 
-        change_clearing_house(
+        propose_clearing_house(
             deps.as_mut(),
             dep_info.clone(),
-            Addr::unchecked("newclearing"),
+            "newclearing".to_string(),
         )
         .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
 
         execute(
             deps.as_mut(),
             mock_env(),
             dep_info.clone(),
-            ExecuteMsg::UpdateAdmin {
+            ExecuteMsg::ProposeAdmin {
                 new_admin: "newadmin".to_string(),
             },
         )
         .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newadmin", &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap();
         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
         let value: ConfigResponse = from_binary(&res).unwrap();
         assert_eq!(Addr::unchecked("newclearing"), value.clearing_house);
         assert_eq!("newadmin", value.admin);
     }
+
+    #[test]
+    fn proper_reconciliation() {
+        // the vault holds 500000 uusd more than was deposited through the clearing house
+        let mut deps = mock_dependencies(&coins(1500000, "uusd"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &coins(0, "uusd")),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000000, "uusd"))).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReconciliation {}).unwrap();
+        let value: ReconciliationResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000000u64), value.total_deposit);
+        assert_eq!(Uint128::from(1500000u64), value.bank_balance);
+        assert_eq!(Uint128::from(500000u64), value.surplus);
+        assert_eq!(Uint128::zero(), value.deficit);
+
+        // stray funds are not counted as withdrawable balance
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000000u64), value.balance);
+    }
+
+    #[test]
+    fn proper_withdraw_rate_limit() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &coins(0, "uusd")),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000000, "uusd"))).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::UpdateRateLimit {
+                max_per_window: Uint128::from(500000u64),
+                max_per_withdrawal: Uint128::zero(),
+            },
+        )
+        .unwrap();
+
+        // 500000 goes out, the rest is held
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(800000u64),
+            PayoutReason::UserWithdrawal,
+            None,
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetWindowUsage {}).unwrap();
+        let value: WindowUsageResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(500000u64), value.used);
+        assert_eq!(Uint128::from(300000u64), value.total_held);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(200000u64), value.balance);
+
+        // only the admin releases held withdrawals
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            ExecuteMsg::ReleaseHeldWithdrawal { id: 0 },
+        )
+        .unwrap_err();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::ReleaseHeldWithdrawal { id: 0 },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn proper_guardian_pause() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &coins(0, "uusd")),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::UpdateGuardian {
+                guardian: "guardian".to_string(),
+            },
+        )
+        .unwrap();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000000, "uusd"))).unwrap();
+
+        // the guardian freezes withdrawals only
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("guardian", &[]),
+            ExecuteMsg::Pause {
+                deposit: false,
+                withdraw: true,
+            },
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("guardian")), value.guardian);
+        assert!(value.withdraw_paused);
+        assert!(!value.deposit_paused);
+
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(1000u64),
+            PayoutReason::UserWithdrawal,
+            None,
+        )
+        .unwrap_err();
+        deposit(deps.as_mut(), mock_info("newclearing", &coins(1000, "uusd"))).unwrap();
+
+        // only the admin lifts the pause
+        let unpause_msg = ExecuteMsg::Unpause {
+            deposit: false,
+            withdraw: true,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), unpause_msg.clone())
+            .unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), unpause_msg).unwrap();
+
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newclearing", &[]),
+            "testaddr".to_string(),
+            Uint128::from(1000u64),
+            PayoutReason::UserWithdrawal,
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn proper_two_step_transfer() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::ProposeAdmin {
+                new_admin: "newadmin".to_string(),
+            },
+        )
+        .unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "newclearing".to_string(),
+        )
+        .unwrap();
+
+        // nothing changes until the proposed addresses accept
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("creator", value.admin);
+        assert_eq!(Some(Addr::unchecked("newadmin")), value.pending_admin);
+        assert_eq!(Some(Addr::unchecked("newclearing")), value.pending_clearing_house);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap_err();
+        accept_clearing_house(deps.as_mut(), mock_info("creator", &[])).unwrap_err();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("newadmin", &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("newclearing", &[])).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("newadmin", value.admin);
+        assert_eq!(Addr::unchecked("newclearing"), value.clearing_house);
+        assert_eq!(None, value.pending_admin);
+        assert_eq!(None, value.pending_clearing_house);
+    }
+
+    #[test]
+    fn proper_multiple_clearing_houses() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "clearinghouse".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("clearinghouse", &[])).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::AuthorizeClearingHouse {
+                clearing_house: "isolated".to_string(),
+                deposit_cap: Uint128::from(1000u64),
+            },
+        )
+        .unwrap();
+
+        deposit(deps.as_mut(), mock_info("clearinghouse", &coins(500, "uusd"))).unwrap();
+        deposit(deps.as_mut(), mock_info("isolated", &coins(1000, "uusd"))).unwrap();
+        deposit(deps.as_mut(), mock_info("isolated", &coins(1, "uusd"))).unwrap_err();
+
+        // donations go to the fund of the primary clearing house
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("donor", &coins(100, "uusd")),
+            ExecuteMsg::Donate {},
+        )
+        .unwrap();
+
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("clearinghouse", &[]),
+            "user".to_string(),
+            Uint128::from(700u64),
+            PayoutReason::UserWithdrawal,
+            None,
+        )
+        .unwrap_err();
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("clearinghouse", &[]),
+            "user".to_string(),
+            Uint128::from(600u64),
+            PayoutReason::UserWithdrawal,
+            None,
+        )
+        .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetClearingHouses {
+                start_after: Some("clearinghouse".to_string()),
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: Vec<ClearingHouseResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![ClearingHouseResponse {
+                clearing_house: Addr::unchecked("isolated"),
+                balance: Uint128::from(1000u64),
+                deposit_cap: Uint128::from(1000u64),
+            }],
+            value
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000u64), value.balance);
+
+        // a clearing house with funds left cannot be revoked
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::RevokeClearingHouse {
+                clearing_house: "isolated".to_string(),
+            },
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn proper_sweep() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "clearinghouse".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("clearinghouse", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("clearinghouse", &coins(1500, "uusd"))).unwrap();
+
+        // nothing to sweep to until the admin sets a treasury
+        execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::Sweep {})
+            .unwrap_err();
+        let update = ExecuteMsg::UpdateFundTarget {
+            target: Uint128::from(1000u64),
+            treasury: "treasury".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), update.clone()).unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000u64), value.fund_target);
+        assert_eq!(Some(Addr::unchecked("treasury")), value.treasury);
+        assert_eq!(Uint128::from(500u64), value.excess);
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::Sweep {})
+            .unwrap();
+        assert_eq!(
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: coins(500, "uusd"),
+            }),
+            res.messages[0].msg
+        );
+        assert_eq!("insurance_fund_sweep", res.events[0].ty);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000u64), value.balance);
+
+        // a second sweep has nothing left to send
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::Sweep {})
+            .unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn proper_payout_history() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "clearinghouse".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("clearinghouse", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("clearinghouse", &coins(1000, "uusd"))).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("clearinghouse", &[]),
+            ExecuteMsg::Withdraw {
+                to_address: "collateralvault".to_string(),
+                amount: Uint128::from(300u64),
+                reason: Some(PayoutReason::MarketTopUp),
+                market_index: Some(1),
+            },
+        )
+        .unwrap();
+
+        // callers that do not pass a reason are still recorded
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("clearinghouse", &[]),
+            ExecuteMsg::Withdraw {
+                to_address: "user".to_string(),
+                amount: Uint128::from(100u64),
+                reason: None,
+                market_index: None,
+            },
+        )
+        .unwrap();
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("clearinghouse", &[]),
+            "user".to_string(),
+            Uint128::from(50u64),
+            PayoutReason::UserWithdrawal,
+            None,
+        )
+        .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetPayouts {
+                start_after: Some(0),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+        let value: Vec<PayoutResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![PayoutResponse {
+                id: 1,
+                ts: mock_env().block.time.seconds(),
+                clearing_house: Addr::unchecked("clearinghouse"),
+                to_address: "user".to_string(),
+                amount: Uint128::from(100u64),
+                reason: PayoutReason::Unspecified,
+                market_index: None,
+            }],
+            value
+        );
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetPayouts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: Vec<PayoutResponse> = from_binary(&res).unwrap();
+        assert_eq!(3, value.len());
+        assert_eq!(PayoutReason::MarketTopUp, value[0].reason);
+        assert_eq!(Some(1), value[0].market_index);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetPayoutTotals {}).unwrap();
+        let value: Vec<PayoutTotalResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                PayoutTotalResponse {
+                    reason: "market_top_up".to_string(),
+                    amount: Uint128::from(300u64),
+                    count: 1,
+                },
+                PayoutTotalResponse {
+                    reason: "unspecified".to_string(),
+                    amount: Uint128::from(100u64),
+                    count: 1,
+                },
+                PayoutTotalResponse {
+                    reason: "user_withdrawal".to_string(),
+                    amount: Uint128::from(50u64),
+                    count: 1,
+                },
+            ],
+            value
+        );
+    }
+
+    #[test]
+    fn held_withdrawal_is_released_with_its_reason() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            denom_stable: "uusd".to_string(),
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        propose_clearing_house(
+            deps.as_mut(),
+            mock_info("creator", &[]),
+            "clearinghouse".to_string(),
+        )
+        .unwrap();
+        accept_clearing_house(deps.as_mut(), mock_info("clearinghouse", &[])).unwrap();
+        deposit(deps.as_mut(), mock_info("clearinghouse", &coins(1000, "uusd"))).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::UpdateRateLimit {
+                max_per_window: Uint128::zero(),
+                max_per_withdrawal: Uint128::from(200u64),
+            },
+        )
+        .unwrap();
+
+        // 200 goes out, the other 100 of the top-up is held
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("clearinghouse", &[]),
+            "collateralvault".to_string(),
+            Uint128::from(300u64),
+            PayoutReason::MarketTopUp,
+            Some(2),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::ReleaseHeldWithdrawal { id: 0 },
+        )
+        .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetPayouts {
+                start_after: Some(0),
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: Vec<PayoutResponse> = from_binary(&res).unwrap();
+        assert_eq!(1, value.len());
+        assert_eq!(Uint128::from(100u64), value[0].amount);
+        assert_eq!(PayoutReason::MarketTopUp, value[0].reason);
+        assert_eq!(Some(2), value[0].market_index);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetPayoutTotals {}).unwrap();
+        let value: Vec<PayoutTotalResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![PayoutTotalResponse {
+                reason: "market_top_up".to_string(),
+                amount: Uint128::from(300u64),
+                count: 2,
+            }],
+            value
+        );
+    }
+
+    #[test]
+    fn proper_migrate_from_0_1_0() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        // storage as left behind by 0.1.0, with the admin inside State
+        set_contract_version(deps.as_mut().storage, "crates.io:insurance-funds", "0.1.0").unwrap();
+        STATE_V010
+            .save(
+                deps.as_mut().storage,
+                &StateV010 {
+                    admin: Addr::unchecked("creator"),
+                    clearing_house: Addr::unchecked("newclearing"),
+                    total_deposit: Uint128::from(1000000u64),
+                    denom_stable: "uusd".to_string(),
+                },
+            )
+            .unwrap();
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(
+            env!("CARGO_PKG_VERSION"),
+            get_contract_version(deps.as_ref().storage).unwrap().version
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("creator", value.admin);
+        assert_eq!(Addr::unchecked("newclearing"), value.clearing_house);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetBalance {}).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000000u64), value.balance);
+
+        // the deposits end up in the sub-ledger of the primary clearing house
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetClearingHouseBalance {
+                clearing_house: "newclearing".to_string(),
+            },
+        )
+        .unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1000000u64), value.balance);
+
+        // another contract's storage is refused
+        set_contract_version(deps.as_mut().storage, "crates.io:collateral-funds", "0.1.0").unwrap();
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    }
 }
@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use oracle::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ConfigResponse, PriceResponse, InfoResponse};
+use oracle::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ConfigResponse, PriceResponse, InfoResponse};
 use oracle::state::{Config, AssetInfo, Price};
 
 fn main() {
@@ -14,6 +14,7 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(PriceResponse), &out_dir);
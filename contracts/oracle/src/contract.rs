@@ -6,12 +6,12 @@ use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, Uint128,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 
 use crate::error::ContractError;
 use crate::msg::{
-    ConfigResponse, ExecuteMsg, InfoResponse, InstantiateMsg, PriceResponse, PriceResponseLuna,
-    QueryMsg,
+    ConfigResponse, ExecuteMsg, InfoResponse, InstantiateMsg, MigrateMsg, PriceResponse,
+    PriceResponseLuna, QueryMsg,
 };
 use crate::state::{Config, Price, ASSETS, CONFIG, FEEDERS};
 use terra_cosmwasm::{ExchangeRatesResponse, TerraQuerier};
@@ -113,6 +113,33 @@ pub fn try_feed_price(deps: DepsMut, info: MessageInfo, env: Env, asset: String,
     Ok(Response::new().add_attribute("method", "feed_price"))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    // every step moves the stored structs one version forward
+    // 0.1.0 is the first released layout, later versions add their transforms here
+    let version = stored.version.clone();
+    if version != CONTRACT_VERSION {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
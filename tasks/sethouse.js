@@ -5,18 +5,21 @@ task(async ({ wallets, refs, config, client }) => {
   let wallet = wallets.admin;   // wallet for testnet
   const clearing_house = client.refs["clearing-house"].contractAddresses.default;
   await client.execute(wallet, "insurance-vault", {
-    update_clearing_house: {
+    propose_clearing_house: {
       "new_clearing_house": clearing_house
     },
   });
-  const insurance_config = await client.query("insurance-vault", { get_config: {} });  
-  console.log("insurance config ", insurance_config);
-
   await client.execute(wallet, "collateral-vault", {
-    update_clearing_house: {
+    propose_clearing_house: {
       "new_clearing_house": clearing_house
     },
   });
+  await client.execute(wallet, "clearing-house", {
+    accept_vaults_clearing_house: {},
+  });
+  const insurance_config = await client.query("insurance-vault", { get_config: {} });  
+  console.log("insurance config ", insurance_config);
+
   const collateral_config = await client.query("collateral-vault", { get_config: {} });  
   console.log("collateral config ", collateral_config);
 